@@ -14,10 +14,12 @@ pub fn register_commands(cli: &mut CLI<Velocity>) {
 }
 
 #[clik_command(user, "Assign a user to a group")]
-#[clik_arg(gid, "The GID of the group to assign to")]
-#[clik_arg(uid, "The UID of the user to assign")]
+#[clik_arg(gid, "The GID or path of the group to assign to")]
+#[clik_arg(uid, "The UID or name of the user to assign")]
 #[clik_arg(permission, "The permission to assign")]
-async fn assign_user(state: &mut Velocity, gid: GID, uid: UID, permission: String) {
+async fn assign_user(state: &mut Velocity, gid: String, uid: String, permission: String) {
+    let gid = state.resolve_group(&gid).await?;
+    let uid = state.resolve_user(&uid).await?;
     state.user_add_permission(gid, uid, &permission).await?;
 
     println!(
@@ -29,8 +31,8 @@ async fn assign_user(state: &mut Velocity, gid: GID, uid: UID, permission: Strin
 }
 
 #[clik_command(pool, "Assign a pool to a group")]
-#[clik_arg(mpid, "The media pool id of the pool to assign")]
-#[clik_arg(gid, "The group id of the group to assign to")]
+#[clik_arg(mpid, "The media pool id or name of the pool to assign")]
+#[clik_arg(gid, "The group id or path of the group to assign to")]
 #[clik_arg(
     quota,
     "The quota of available space the group should have in the pool"
@@ -39,12 +41,14 @@ async fn assign_user(state: &mut Velocity, gid: GID, uid: UID, permission: Strin
 #[clik_arg(manage, "If the group can create and remove media in the pool")]
 async fn assign_pool(
     state: &mut Velocity,
-    mpid: MPID,
-    gid: GID,
+    mpid: String,
+    gid: String,
     quota: u64,
     write: bool,
     manage: bool,
 ) {
+    let mpid = state.resolve_pool(&mpid).await?;
+    let gid = state.resolve_group(&gid).await?;
     state.pool_assign(gid, mpid, quota, write, manage).await?;
 
     println!(
@@ -56,10 +60,12 @@ async fn assign_pool(
 }
 
 #[clik_command(user, "Revoke a users permission on a group")]
-#[clik_arg(gid, "The GID of the group to revoke from")]
-#[clik_arg(uid, "The UID of the user to revoke from")]
+#[clik_arg(gid, "The GID or path of the group to revoke from")]
+#[clik_arg(uid, "The UID or name of the user to revoke from")]
 #[clik_arg(permission, "The permission to revoke")]
-async fn revoke_user(state: &mut Velocity, gid: GID, uid: UID, permission: String) {
+async fn revoke_user(state: &mut Velocity, gid: String, uid: String, permission: String) {
+    let gid = state.resolve_group(&gid).await?;
+    let uid = state.resolve_user(&uid).await?;
     state.user_revoke_permission(gid, uid, &permission).await?;
 
     println!(
@@ -71,9 +77,11 @@ async fn revoke_user(state: &mut Velocity, gid: GID, uid: UID, permission: Strin
 }
 
 #[clik_command(pool, "Revoke a pool from a group")]
-#[clik_arg(mpid, "The media pool id of the pool to revoke")]
-#[clik_arg(gid, "The group id of the group to revoke the pool from")]
-async fn revoke_pool(state: &mut Velocity, mpid: MPID, gid: GID) {
+#[clik_arg(mpid, "The media pool id or name of the pool to revoke")]
+#[clik_arg(gid, "The group id or path of the group to revoke the pool from")]
+async fn revoke_pool(state: &mut Velocity, mpid: String, gid: String) {
+    let mpid = state.resolve_pool(&mpid).await?;
+    let gid = state.resolve_group(&gid).await?;
     state.pool_revoke(gid, mpid).await?;
 
     println!("Revoked pool {} from group {}", mpid, gid);
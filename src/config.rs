@@ -0,0 +1,117 @@
+//! Settings read from `~/.vcmd_config`. The file consists of `key = value` lines,
+//! `#` starts a comment:
+//!
+//! ```text
+//! # The default bandwidth limit for transfers, see the 'limit' command
+//! limit = 20M
+//! ```
+//!
+//! Sending `SIGUSR1` to vcmd reloads the limit from the file, which also changes the limit
+//! of running transfers: `kill -USR1 <pid>`
+
+use std::{error::Error, path::PathBuf};
+
+#[cfg(unix)]
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task::JoinHandle,
+};
+#[cfg(unix)]
+use velocity::transfer::BandwidthLimit;
+
+use crate::upload::parse_rate;
+
+/// The settings from the configuration file
+#[derive(Default)]
+pub struct Config {
+    /// The default bandwidth limit for transfers, `Some(None)` if the file turns it `off`
+    pub limit: Option<Option<u64>>,
+}
+
+impl Config {
+    /// The path of the configuration file
+    pub fn path() -> PathBuf {
+        home::home_dir()
+            .unwrap_or_else(|| PathBuf::from("./"))
+            .join(".vcmd_config")
+    }
+
+    /// Reads the configuration file, a missing file results in the default configuration
+    pub fn load() -> Result<Config, Box<dyn Error>> {
+        let path = Self::path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("Failed to read '{}': {}", path.display(), e).into()),
+        };
+
+        Self::parse(&content).map_err(|e| format!("Invalid '{}': {}", path.display(), e).into())
+    }
+
+    /// Parses the content of a configuration file
+    /// # Arguments
+    /// * `content` - The content of the file
+    fn parse(content: &str) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::default();
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("Expected 'key = value' on line {}", i + 1).into());
+            };
+            match key.trim() {
+                "limit" => config.limit = Some(parse_rate(value)?),
+                key => return Err(format!("Unknown setting '{}' on line {}", key, i + 1).into()),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Reloads a bandwidth limit from the configuration file whenever vcmd receives `SIGUSR1`,
+/// until the returned task gets aborted. The limit stays as it is if the file does not set one
+/// # Arguments
+/// * `limit` - The limit to update, clones of it change as well
+#[cfg(unix)]
+pub fn reload_limit_on_signal(limit: BandwidthLimit) -> std::io::Result<JoinHandle<()>> {
+    let mut signals = signal(SignalKind::user_defined1())?;
+
+    Ok(tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            match Config::load() {
+                Ok(Config { limit: Some(rate) }) => {
+                    limit.set(rate);
+                    match rate {
+                        Some(rate) => log::info!("Limited transfers to {} bytes/s", rate),
+                        None => log::info!("Removed the bandwidth limit for transfers"),
+                    }
+                }
+                Ok(_) => log::info!("'{}' sets no limit", Config::path().display()),
+                Err(e) => log::warn!("Failed to reload the bandwidth limit: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse("# Limit uploads\nlimit = 20M # at work\n\n").unwrap();
+        assert_eq!(config.limit, Some(Some(20 << 20)));
+
+        assert_eq!(Config::parse("limit = off").unwrap().limit, Some(None));
+        assert_eq!(Config::parse("").unwrap().limit, None);
+
+        assert!(Config::parse("limit = fast").is_err());
+        assert!(Config::parse("limit").is_err());
+        assert!(Config::parse("speed = 20M").is_err());
+    }
+}
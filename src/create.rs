@@ -1,8 +1,16 @@
 use clik::*;
 use rustyline::DefaultEditor;
-use velocity::*;
-
-use crate::wizard::wizard_corevm;
+use velocity::{
+    endpoints::v::{v_vm_validate::ConfigProblem, DiskConfig},
+    error::VelocityError,
+    transfer::TransferOptions,
+    *,
+};
+
+use crate::{
+    upload::seed::{build_seed, upload_seed},
+    wizard::{wizard_confirm, wizard_corevm, wizard_seed, SeedConfig},
+};
 
 pub fn register_commands(cli: &mut CLI<Velocity>) {
     let mut create_vm = create_vm();
@@ -31,8 +39,12 @@ async fn create_user(state: &mut Velocity, username: String) {
 
 #[clik_command(group, "Create a new group")]
 #[clik_arg(name, "The name for the new group")]
-#[clik_arg(parent_gid, "The group id the new group shold be a part of")]
-async fn create_group(state: &mut Velocity, name: String, parent_gid: GID) {
+#[clik_arg(
+    parent_gid,
+    "The group id or path of the group the new group shold be a part of"
+)]
+async fn create_group(state: &mut Velocity, name: String, parent_gid: String) {
+    let parent_gid = state.resolve_group(&parent_gid).await?;
     let group = state.group_create(parent_gid, &name).await?;
 
     println!("Created new group '{name}': GID = {}", group.gid);
@@ -41,8 +53,8 @@ async fn create_group(state: &mut Velocity, name: String, parent_gid: GID) {
 }
 
 #[clik_command(media, "Allocate new media")]
-#[clik_arg(mpid, "The media pool id to allocate in")]
-#[clik_arg(gid, "The group id of the group the media should belong to")]
+#[clik_arg(mpid, "The media pool id or name to allocate in")]
+#[clik_arg(gid, "The group id or path of the group the media should belong to")]
 #[clik_arg(name, "A user-friendly name for the new media")]
 #[clik_arg(
     ty,
@@ -51,12 +63,14 @@ async fn create_group(state: &mut Velocity, name: String, parent_gid: GID) {
 #[clik_arg(size, "The size of the new media")]
 async fn create_media(
     state: &mut Velocity,
-    mpid: MPID,
-    gid: GID,
+    mpid: String,
+    gid: String,
     name: String,
     ty: String,
     size: u64,
 ) {
+    let mpid = state.resolve_pool(&mpid).await?;
+    let gid = state.resolve_group(&gid).await?;
     let mid = state.media_allocate(mpid, gid, &name, &ty, size).await?.mid;
 
     println!("Created new media '{name}' in pool {mpid}. MID: {mid}");
@@ -65,26 +79,105 @@ async fn create_media(
 }
 
 #[clik_command(efi, "Create a new EFI virtual machine")]
-#[clik_arg(gid, "The group if of the group the virtual machine belongs to")]
+#[clik_arg(
+    gid,
+    "The group id or path of the group the virtual machine belongs to"
+)]
 #[clik_arg(name, "The name for the virtual machine")]
-async fn create_vm_efi(state: &mut Velocity, gid: GID, name: String) {
-    let config = velocity::endpoints::v::v_vm_efi::EFIVMConfig {
+async fn create_vm_efi(state: &mut Velocity, gid: String, name: String) {
+    let gid = state.resolve_group(&gid).await?;
+    let mut readline = DefaultEditor::new().expect("Create wizard Editor");
+
+    let core = wizard_corevm(&mut readline, state, gid, &name).await?;
+    let seed = wizard_seed(&mut readline, state, &name).await?;
+
+    // Build the seed right away, so problems with its files show up before confirming
+    let image = match &seed {
+        Some(seed) => Some(build_seed(&seed.paths, &name)?),
+        None => None,
+    };
+
+    let mut problems = state.vm_validate(&core).await?;
+    if let Some(seed) = &seed {
+        problems.extend(validate_seed(state, gid, seed).await?);
+    }
+    wizard_confirm(&mut readline, &core, seed.as_ref(), &problems)?;
+
+    let mut config = velocity::endpoints::v::v_vm_efi::EFIVMConfig {
         rosetta: true,
 
-        core: wizard_corevm(
-            &mut DefaultEditor::new().expect("Create wizard Editor"),
-            gid,
-            &name,
-        )?,
+        core,
     };
 
-    let vmid = state.vm_efi_create(config).await?;
+    let mut seed_mid = None;
+    if let (Some(seed), Some(image)) = (seed, image) {
+        let mid = upload_seed(
+            state,
+            seed.mpid,
+            gid,
+            &seed.name,
+            image,
+            TransferOptions::new(),
+        )
+        .await?;
+        println!("Uploaded seed ISO '{}': MID = {mid}", seed.name);
+
+        config.core.disks.push(DiskConfig {
+            mid: mid.clone(),
+            mode: seed.mode,
+            readonly: true,
+        });
+        seed_mid = Some(mid);
+    }
+
+    let vmid = match state.vm_efi_create(config).await {
+        Ok(vmid) => vmid,
+        Err(e) => {
+            // Nothing uses the seed without the virtual machine, don't leave it behind
+            if let Some(mid) = seed_mid {
+                match state.media_remove(mid.clone()).await {
+                    Ok(()) => println!("Removed the seed ISO {mid}"),
+                    Err(e) => println!("Failed to remove the seed ISO {mid}: {e}"),
+                }
+            }
+            return Err(e.into());
+        }
+    };
 
     println!("New VM: {vmid}");
 
     Ok(())
 }
 
+/// Checks that a seed ISO can be uploaded to its pool
+/// # Arguments
+/// * `state` - The client to check with
+/// * `gid` - The group the seed is going to belong to
+/// * `seed` - The seed to check
+async fn validate_seed(
+    state: &Velocity,
+    gid: GID,
+    seed: &SeedConfig,
+) -> Result<Vec<ConfigProblem>, VelocityError> {
+    let pools = match state.pool_list(gid).await {
+        Ok(pools) => pools,
+        // Without the permission to list pools, there's no way of telling
+        Err(e) if e.is_permission_denied() => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let message = match pools.iter().find(|p| p.mpid == seed.mpid) {
+        None => format!("Pool {} is not available to group {}", seed.mpid, gid),
+        Some(pool) if !pool.write => format!("Pool {} is read-only for group {}", seed.mpid, gid),
+        Some(_) => return Ok(Vec::new()),
+    };
+
+    Ok(vec![ConfigProblem {
+        field: "seed.mpid".to_owned(),
+        message,
+    }])
+}
+
 #[clik_command(vm, "Create a new virtual machine")]
 async fn create_vm(state: &mut Velocity) {
     Ok(())
@@ -0,0 +1,38 @@
+//! Remediation hints for errors returned by the hypervisor
+
+use std::error::Error;
+
+use velocity::error::{VelocityError, VelocityErrorKind};
+
+/// Provides a hint on how to resolve an error, if the error is a `VelocityError`
+/// # Arguments
+/// * `error` - The error to provide a hint for
+pub fn remediation_hint(error: &(dyn Error + 'static)) -> Option<&'static str> {
+    let error = error.downcast_ref::<VelocityError>()?;
+
+    Some(match error.kind() {
+        VelocityErrorKind::BadRequest => {
+            "Check the supplied arguments, the hypervisor rejected the request"
+        }
+        VelocityErrorKind::InvalidAuthkey => {
+            "The authkey has expired, use 'reauth' or 'auth <username>' to log in again"
+        }
+        VelocityErrorKind::NotAuthenticated => "Use 'auth <username>' to log in",
+        VelocityErrorKind::PermissionDenied => {
+            "Ask an administrator for the required permission, see 'userinfo'"
+        }
+        VelocityErrorKind::NotFound => "Check the supplied ids or names, see the 'list' commands",
+        VelocityErrorKind::Conflict => {
+            "The resource already exists or is still in use, remove or rename it first"
+        }
+        VelocityErrorKind::QuotaExceeded => {
+            "Free up space by removing media or ask for a bigger quota on the pool"
+        }
+        VelocityErrorKind::Internal => "The hypervisor failed, check its logs and try again",
+        VelocityErrorKind::Transport => "Check the connection to the hypervisor and try again",
+        VelocityErrorKind::ChecksumMismatch => {
+            "The data got corrupted, remove the media and upload it again"
+        }
+        VelocityErrorKind::Cancelled | VelocityErrorKind::Other => return None,
+    })
+}
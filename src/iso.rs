@@ -0,0 +1,959 @@
+//! A minimal ISO9660 writer with Joliet extensions for long, mixed case file names.
+//! It is meant for small images like cloud-init seeds and builds them in memory
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The size of a logical sector
+const SECTOR: usize = 2048;
+
+/// The first sector after the system area, where the volume descriptors start
+const FIRST_DESCRIPTOR: usize = 16;
+
+/// The maximum length of a Joliet identifier in UCS-2 characters
+const JOLIET_MAX: usize = 64;
+
+/// A node in the file tree of an image
+enum Node {
+    /// A file and the index of its data
+    File(usize),
+    Dir(Dir),
+}
+
+/// A directory in the file tree of an image
+#[derive(Default)]
+struct Dir {
+    children: BTreeMap<String, Node>,
+}
+
+/// A directory as laid out in one of the two trees of the image
+struct DirLayout {
+    /// The index of the parent directory, the root is its own parent
+    parent: usize,
+    /// The identifier of the directory in its parent
+    ident: Vec<u8>,
+    /// The identifiers of the children and what they point to, sorted by identifier
+    entries: Vec<(Vec<u8>, Entry)>,
+    /// The first sector of the directory records
+    extent: u32,
+    /// The size of the directory records, a multiple of `SECTOR`
+    size: u32,
+}
+
+/// A child of a directory in one of the two trees of the image
+enum Entry {
+    /// The index of a directory in the tree
+    Dir(usize),
+    /// The index of the data of a file
+    File(usize),
+}
+
+/// Builds an ISO9660 image with Joliet extensions
+pub struct IsoBuilder {
+    volume_id: String,
+    root: Dir,
+    files: Vec<Vec<u8>>,
+}
+
+impl IsoBuilder {
+    /// Creates a builder for an empty image
+    /// # Arguments
+    /// * `volume_id` - The label of the volume, e.g. `CIDATA` for cloud-init seeds
+    pub fn new(volume_id: &str) -> IsoBuilder {
+        IsoBuilder {
+            volume_id: volume_id.to_owned(),
+            root: Dir::default(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Checks if a file or directory exists in the image
+    /// # Arguments
+    /// * `path` - The path in the image, separated by `/`
+    pub fn contains(&self, path: &str) -> bool {
+        let mut dir = &self.root;
+        let mut parts = path.split('/').filter(|p| !p.is_empty()).peekable();
+
+        while let Some(part) = parts.next() {
+            match dir.children.get(part) {
+                None => return false,
+                Some(_) if parts.peek().is_none() => return true,
+                Some(Node::Dir(child)) => dir = child,
+                Some(Node::File(_)) => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Adds a file to the image, creating its parent directories. Existing entries get replaced
+    /// # Arguments
+    /// * `path` - The path in the image, separated by `/`
+    /// * `data` - The contents of the file
+    pub fn add_file(&mut self, path: &str, data: Vec<u8>) {
+        let mut parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        let name = match parts.pop() {
+            Some(name) => name.to_owned(),
+            None => return,
+        };
+
+        let mut dir = &mut self.root;
+        for part in parts {
+            let node = dir
+                .children
+                .entry(part.to_owned())
+                .or_insert_with(|| Node::Dir(Dir::default()));
+            if let Node::File(_) = node {
+                *node = Node::Dir(Dir::default());
+            }
+            dir = match node {
+                Node::Dir(dir) => dir,
+                Node::File(_) => unreachable!("Files have been replaced by directories"),
+            };
+        }
+
+        // A replaced file keeps its index, so its old data does not linger in the image
+        match dir.children.get(&name) {
+            Some(Node::File(file)) => self.files[*file] = data,
+            _ => {
+                self.files.push(data);
+                dir.children.insert(name, Node::File(self.files.len() - 1));
+            }
+        }
+    }
+
+    /// Adds the contents of a local directory to the image, recursively
+    /// # Arguments
+    /// * `local` - The local directory to add
+    /// * `prefix` - The path in the image to add the contents at, empty for the root
+    pub fn add_dir(&mut self, local: &Path, prefix: &str) -> io::Result<()> {
+        for entry in std::fs::read_dir(local)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = format!("{prefix}/{name}");
+
+            if entry.file_type()?.is_dir() {
+                self.add_dir(&entry.path(), &path)?;
+            } else {
+                self.add_file(&path, std::fs::read(entry.path())?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the image
+    pub fn build(&self) -> Vec<u8> {
+        let time = Timestamp::now();
+
+        let mut iso = layout(&self.root, false);
+        let mut joliet = layout(&self.root, true);
+        let iso_pt_size = path_table_size(&iso);
+        let joliet_pt_size = path_table_size(&joliet);
+
+        // Volume descriptors: primary, Joliet and the terminator
+        let mut next = FIRST_DESCRIPTOR + 3;
+        let mut allocate = |size: usize| {
+            let start = next;
+            next += size.div_ceil(SECTOR);
+            start
+        };
+
+        let iso_pt = [allocate(iso_pt_size), allocate(iso_pt_size)];
+        let joliet_pt = [allocate(joliet_pt_size), allocate(joliet_pt_size)];
+        for dir in iso.iter_mut().chain(joliet.iter_mut()) {
+            dir.extent = allocate(dir.size as usize) as u32;
+        }
+        // Files below replaced directories are no longer part of the tree and take no space
+        let mut used = vec![false; self.files.len()];
+        mark_used(&self.root, &mut used);
+        let extents: Vec<usize> = self
+            .files
+            .iter()
+            .zip(&used)
+            .map(|(f, used)| if *used { allocate(f.len()) } else { 0 })
+            .collect();
+        let total = next;
+
+        let mut image = vec![0u8; total * SECTOR];
+
+        for (tree, pt, pt_size, joliet) in [
+            (&iso, iso_pt, iso_pt_size, false),
+            (&joliet, joliet_pt, joliet_pt_size, true),
+        ] {
+            let descriptor = FIRST_DESCRIPTOR + joliet as usize;
+            self.write_descriptor(
+                sector(&mut image, descriptor),
+                tree,
+                &Descriptor {
+                    total,
+                    path_tables: pt,
+                    path_table_size: pt_size,
+                    joliet,
+                },
+                &time,
+            );
+
+            write_path_table(&mut image[pt[0] * SECTOR..], tree, false);
+            write_path_table(&mut image[pt[1] * SECTOR..], tree, true);
+
+            for dir in tree {
+                let start = dir.extent as usize * SECTOR;
+                write_dir(
+                    &mut image[start..start + dir.size as usize],
+                    dir,
+                    tree,
+                    &self.files,
+                    &extents,
+                    &time,
+                );
+            }
+        }
+
+        let terminator = sector(&mut image, FIRST_DESCRIPTOR + 2);
+        terminator[0] = 255;
+        terminator[1..6].copy_from_slice(b"CD001");
+        terminator[6] = 1;
+
+        for ((data, extent), used) in self.files.iter().zip(extents).zip(used) {
+            if used {
+                let start = extent * SECTOR;
+                image[start..start + data.len()].copy_from_slice(data);
+            }
+        }
+
+        image
+    }
+
+    /// Writes the primary or the Joliet volume descriptor
+    /// # Arguments
+    /// * `out` - The sector to write to
+    /// * `tree` - The tree the descriptor describes
+    /// * `descriptor` - The layout of the image
+    /// * `time` - The time the image gets created at
+    fn write_descriptor(
+        &self,
+        out: &mut [u8],
+        tree: &[DirLayout],
+        descriptor: &Descriptor,
+        time: &Timestamp,
+    ) {
+        let joliet = descriptor.joliet;
+
+        out[0] = if joliet { 2 } else { 1 };
+        out[1..6].copy_from_slice(b"CD001");
+        out[6] = 1;
+        text(&mut out[8..40], "", joliet);
+        text(&mut out[40..72], &self.volume_id, joliet);
+        both_u32(&mut out[80..88], descriptor.total as u32);
+        if joliet {
+            // UCS-2 level 3
+            out[88..91].copy_from_slice(b"%/E");
+        }
+        both_u16(&mut out[120..124], 1);
+        both_u16(&mut out[124..128], 1);
+        both_u16(&mut out[128..132], SECTOR as u16);
+        both_u32(&mut out[132..140], descriptor.path_table_size as u32);
+        out[140..144].copy_from_slice(&(descriptor.path_tables[0] as u32).to_le_bytes());
+        out[148..152].copy_from_slice(&(descriptor.path_tables[1] as u32).to_be_bytes());
+        dir_record(
+            &mut out[156..190],
+            &[0],
+            tree[0].extent,
+            tree[0].size,
+            true,
+            time,
+        );
+        // Volume set, publisher, preparer, application, copyright, abstract and bibliography
+        text(&mut out[190..813], "", joliet);
+        out[813..830].copy_from_slice(&time.long());
+        out[830..847].copy_from_slice(&time.long());
+        out[847..863].copy_from_slice(b"0000000000000000");
+        out[864..880].copy_from_slice(b"0000000000000000");
+        out[881] = 1;
+    }
+}
+
+/// Where the parts of the image described by a volume descriptor are
+struct Descriptor {
+    /// The size of the whole image in sectors
+    total: usize,
+    /// The sectors of the little and big endian path tables
+    path_tables: [usize; 2],
+    /// The size of a path table in bytes
+    path_table_size: usize,
+    /// If this is the Joliet descriptor
+    joliet: bool,
+}
+
+/// Lays out one of the trees of the image. Directories are ordered breadth first and
+/// entries by their identifier, the order the path table requires
+/// # Arguments
+/// * `root` - The root of the file tree
+/// * `joliet` - If Joliet identifiers should be used
+fn layout(root: &Dir, joliet: bool) -> Vec<DirLayout> {
+    let mut dirs = vec![DirLayout {
+        parent: 0,
+        ident: vec![0],
+        entries: Vec::new(),
+        extent: 0,
+        size: 0,
+    }];
+    let mut nodes = vec![root];
+
+    let mut i = 0;
+    while i < nodes.len() {
+        let mut iso_taken = HashSet::new();
+        let mut joliet_taken = HashSet::new();
+        let mut children: Vec<(Vec<u8>, &Node)> = nodes[i]
+            .children
+            .iter()
+            .map(|(name, node)| {
+                let dir = matches!(node, Node::Dir(_));
+                let ident = match joliet {
+                    true => joliet_ident(name, &mut joliet_taken),
+                    false => iso_ident(name, dir, &mut iso_taken),
+                };
+                (ident, node)
+            })
+            .collect();
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut entries = Vec::new();
+        for (ident, node) in children {
+            match node {
+                Node::File(file) => entries.push((ident, Entry::File(*file))),
+                Node::Dir(dir) => {
+                    dirs.push(DirLayout {
+                        parent: i,
+                        ident: ident.clone(),
+                        entries: Vec::new(),
+                        extent: 0,
+                        size: 0,
+                    });
+                    nodes.push(dir);
+                    entries.push((ident, Entry::Dir(dirs.len() - 1)));
+                }
+            }
+        }
+
+        // The records for `.` and `..` have one byte identifiers
+        let lengths = [1, 1]
+            .into_iter()
+            .chain(entries.iter().map(|(ident, _)| ident.len()));
+        dirs[i].size = (records_len(lengths).div_ceil(SECTOR) * SECTOR) as u32;
+        dirs[i].entries = entries;
+        i += 1;
+    }
+
+    dirs
+}
+
+/// Marks the files that are part of a tree
+/// # Arguments
+/// * `dir` - The root of the tree
+/// * `used` - Set for every file index in the tree
+fn mark_used(dir: &Dir, used: &mut [bool]) {
+    for node in dir.children.values() {
+        match node {
+            Node::File(file) => used[*file] = true,
+            Node::Dir(dir) => mark_used(dir, used),
+        }
+    }
+}
+
+/// Creates an ISO9660 identifier: upper case 8.3 names with a version for files.
+/// Collisions within a directory get a numbered suffix
+/// # Arguments
+/// * `name` - The name of the file or directory
+/// * `dir` - If this is a directory
+/// * `taken` - The identifiers already used in the directory
+fn iso_ident(name: &str, dir: bool, taken: &mut HashSet<String>) -> Vec<u8> {
+    let clean = |s: &str, max: usize| -> String {
+        s.chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_uppercase(),
+                false => '_',
+            })
+            .take(max)
+            .collect()
+    };
+
+    let (stem, ext) = match (dir, name.rsplit_once('.')) {
+        (false, Some((stem, ext))) => (clean(stem, 8), clean(ext, 3)),
+        _ => (clean(name, 8), String::new()),
+    };
+    let stem = if stem.is_empty() {
+        "_".to_owned()
+    } else {
+        stem
+    };
+
+    let format = |stem: &str| match dir {
+        true => stem.to_owned(),
+        false => format!("{stem}.{ext};1"),
+    };
+
+    let mut ident = format(&stem);
+    let mut n = 1;
+    while taken.contains(&ident) {
+        let suffix = format!("~{n}");
+        let keep = stem.len().min(8 - suffix.len());
+        ident = format(&format!("{}{suffix}", &stem[..keep]));
+        n += 1;
+    }
+
+    taken.insert(ident.clone());
+    ident.into_bytes()
+}
+
+/// Creates a Joliet identifier: up to 64 UCS-2 characters in big endian.
+/// Collisions within a directory, e.g. of truncated names, get a numbered suffix
+/// # Arguments
+/// * `name` - The name of the file or directory
+/// * `taken` - The identifiers already used in the directory
+fn joliet_ident(name: &str, taken: &mut HashSet<Vec<u16>>) -> Vec<u8> {
+    let units: Vec<u16> = name
+        .chars()
+        .map(|c| match c {
+            '*' | '/' | ':' | ';' | '?' | '\\' => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .encode_utf16()
+        .collect();
+
+    // Truncating must not split a surrogate pair
+    let truncate = |max: usize| -> Vec<u16> {
+        let mut end = units.len().min(max);
+        if end < units.len() && (0xD800..0xDC00).contains(&units[end - 1]) {
+            end -= 1;
+        }
+        units[..end].to_vec()
+    };
+
+    let mut ident = truncate(JOLIET_MAX);
+    let mut n = 1;
+    while taken.contains(&ident) {
+        let suffix: Vec<u16> = format!("~{n}").encode_utf16().collect();
+        ident = truncate(JOLIET_MAX - suffix.len());
+        ident.extend(suffix);
+        n += 1;
+    }
+
+    taken.insert(ident.clone());
+    ident.into_iter().flat_map(u16::to_be_bytes).collect()
+}
+
+/// The length of a directory record with an identifier of `len` bytes
+fn record_len(len: usize) -> usize {
+    33 + len + (len + 1) % 2
+}
+
+/// The length of the directory records with identifiers of the given lengths,
+/// records may not cross the boundary of a sector
+fn records_len(lengths: impl Iterator<Item = usize>) -> usize {
+    let mut offset = 0;
+    for len in lengths.map(record_len) {
+        if offset % SECTOR + len > SECTOR {
+            offset = offset.div_ceil(SECTOR) * SECTOR;
+        }
+        offset += len;
+    }
+    offset
+}
+
+/// The size of the path table of a tree in bytes
+fn path_table_size(tree: &[DirLayout]) -> usize {
+    tree.iter()
+        .map(|dir| 8 + dir.ident.len() + dir.ident.len() % 2)
+        .sum()
+}
+
+/// Writes the path table of a tree
+/// # Arguments
+/// * `out` - The buffer to write to
+/// * `tree` - The tree to write the path table for
+/// * `big_endian` - If this is the big endian table
+fn write_path_table(out: &mut [u8], tree: &[DirLayout], big_endian: bool) {
+    let mut offset = 0;
+    for dir in tree {
+        let (extent, parent) = match big_endian {
+            true => (
+                dir.extent.to_be_bytes(),
+                (dir.parent as u16 + 1).to_be_bytes(),
+            ),
+            false => (
+                dir.extent.to_le_bytes(),
+                (dir.parent as u16 + 1).to_le_bytes(),
+            ),
+        };
+
+        out[offset] = dir.ident.len() as u8;
+        out[offset + 2..offset + 6].copy_from_slice(&extent);
+        out[offset + 6..offset + 8].copy_from_slice(&parent);
+        out[offset + 8..offset + 8 + dir.ident.len()].copy_from_slice(&dir.ident);
+        offset += 8 + dir.ident.len() + dir.ident.len() % 2;
+    }
+}
+
+/// Writes the records of a directory
+/// # Arguments
+/// * `out` - The extent of the directory
+/// * `dir` - The directory to write
+/// * `tree` - The tree the directory belongs to
+/// * `files` - The data of all files
+/// * `extents` - The first sectors of all files
+/// * `time` - The time the image gets created at
+fn write_dir(
+    out: &mut [u8],
+    dir: &DirLayout,
+    tree: &[DirLayout],
+    files: &[Vec<u8>],
+    extents: &[usize],
+    time: &Timestamp,
+) {
+    let parent = &tree[dir.parent];
+    let mut records: Vec<(&[u8], u32, u32, bool)> = vec![
+        (&[0], dir.extent, dir.size, true),
+        (&[1], parent.extent, parent.size, true),
+    ];
+    for (ident, entry) in &dir.entries {
+        records.push(match entry {
+            Entry::Dir(i) => (ident, tree[*i].extent, tree[*i].size, true),
+            Entry::File(i) => (ident, extents[*i] as u32, files[*i].len() as u32, false),
+        });
+    }
+
+    let mut offset = 0;
+    for (ident, extent, size, is_dir) in records {
+        let len = record_len(ident.len());
+        if offset % SECTOR + len > SECTOR {
+            offset = offset.div_ceil(SECTOR) * SECTOR;
+        }
+
+        dir_record(
+            &mut out[offset..offset + len],
+            ident,
+            extent,
+            size,
+            is_dir,
+            time,
+        );
+        offset += len;
+    }
+}
+
+/// Writes a directory record
+/// # Arguments
+/// * `out` - The buffer for the record, exactly as long as the record
+/// * `ident` - The identifier of the record
+/// * `extent` - The first sector of the file or directory
+/// * `size` - The size of the file or directory
+/// * `dir` - If the record points to a directory
+/// * `time` - The time the image gets created at
+fn dir_record(out: &mut [u8], ident: &[u8], extent: u32, size: u32, dir: bool, time: &Timestamp) {
+    out[0] = record_len(ident.len()) as u8;
+    both_u32(&mut out[2..10], extent);
+    both_u32(&mut out[10..18], size);
+    out[18..25].copy_from_slice(&time.short());
+    out[25] = if dir { 0x02 } else { 0x00 };
+    both_u16(&mut out[28..32], 1);
+    out[32] = ident.len() as u8;
+    out[33..33 + ident.len()].copy_from_slice(ident);
+}
+
+/// Returns a sector of the image
+fn sector(image: &mut [u8], index: usize) -> &mut [u8] {
+    &mut image[index * SECTOR..(index + 1) * SECTOR]
+}
+
+/// Writes a number in both byte orders, as most numbers in ISO9660
+fn both_u32(out: &mut [u8], value: u32) {
+    out[..4].copy_from_slice(&value.to_le_bytes());
+    out[4..8].copy_from_slice(&value.to_be_bytes());
+}
+
+/// Writes a number in both byte orders, as most numbers in ISO9660
+fn both_u16(out: &mut [u8], value: u16) {
+    out[..2].copy_from_slice(&value.to_le_bytes());
+    out[2..4].copy_from_slice(&value.to_be_bytes());
+}
+
+/// Writes a text field padded with spaces, in UCS-2 for Joliet
+fn text(out: &mut [u8], value: &str, joliet: bool) {
+    if joliet {
+        let mut units = value.encode_utf16().chain(std::iter::repeat(b' ' as u16));
+        for pair in out.chunks_exact_mut(2) {
+            pair.copy_from_slice(&units.next().unwrap_or_default().to_be_bytes());
+        }
+    } else {
+        let mut bytes = value.bytes().chain(std::iter::repeat(b' '));
+        for byte in out.iter_mut() {
+            *byte = bytes.next().unwrap_or_default();
+        }
+    }
+}
+
+/// A point in time in UTC
+struct Timestamp {
+    year: u32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+impl Timestamp {
+    /// The current time
+    fn now() -> Timestamp {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        // Converts days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = (secs / 86400) as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+
+        Timestamp {
+            year: year as u32,
+            month: month as u32,
+            day: day as u32,
+            hour: (secs % 86400 / 3600) as u32,
+            minute: (secs % 3600 / 60) as u32,
+            second: (secs % 60) as u32,
+        }
+    }
+
+    /// The format used in directory records
+    fn short(&self) -> [u8; 7] {
+        [
+            (self.year - 1900) as u8,
+            self.month as u8,
+            self.day as u8,
+            self.hour as u8,
+            self.minute as u8,
+            self.second as u8,
+            0,
+        ]
+    }
+
+    /// The format used in volume descriptors
+    fn long(&self) -> [u8; 17] {
+        let mut out = [0u8; 17];
+        out[..16].copy_from_slice(
+            format!(
+                "{:04}{:02}{:02}{:02}{:02}{:02}00",
+                self.year, self.month, self.day, self.hour, self.minute, self.second
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory record parsed back from an image
+    #[derive(Debug)]
+    struct Record {
+        ident: Vec<u8>,
+        extent: usize,
+        size: usize,
+        dir: bool,
+    }
+
+    /// Reads a little endian u32 followed by its big endian copy
+    fn read_both_u32(bytes: &[u8]) -> usize {
+        let le = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let be = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+        assert_eq!(le, be, "both-endian halves differ");
+        le as usize
+    }
+
+    fn parse_record(bytes: &[u8]) -> Record {
+        let len = bytes[32] as usize;
+        Record {
+            ident: bytes[33..33 + len].to_vec(),
+            extent: read_both_u32(&bytes[2..10]),
+            size: read_both_u32(&bytes[10..18]),
+            dir: bytes[25] & 0x02 != 0,
+        }
+    }
+
+    /// Parses the records of a directory, checking that none crosses a sector
+    fn read_dir(image: &[u8], extent: usize, size: usize) -> Vec<Record> {
+        assert_eq!(size % SECTOR, 0, "directory size is not whole sectors");
+        let data = &image[extent * SECTOR..extent * SECTOR + size];
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < size {
+            let len = data[offset] as usize;
+            if len == 0 {
+                offset = (offset / SECTOR + 1) * SECTOR;
+                continue;
+            }
+            assert!(offset % SECTOR + len <= SECTOR, "record crosses a sector");
+            records.push(parse_record(&data[offset..offset + len]));
+            offset += len;
+        }
+        records
+    }
+
+    /// Parses a path table into (ident, extent, parent) entries
+    fn read_path_table(
+        image: &[u8],
+        location: usize,
+        size: usize,
+        big_endian: bool,
+    ) -> Vec<(Vec<u8>, usize, usize)> {
+        let data = &image[location * SECTOR..location * SECTOR + size];
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < size {
+            let len = data[offset] as usize;
+            let extent: [u8; 4] = data[offset + 2..offset + 6].try_into().unwrap();
+            let parent: [u8; 2] = data[offset + 6..offset + 8].try_into().unwrap();
+            let (extent, parent) = match big_endian {
+                true => (u32::from_be_bytes(extent), u16::from_be_bytes(parent)),
+                false => (u32::from_le_bytes(extent), u16::from_le_bytes(parent)),
+            };
+            entries.push((
+                data[offset + 8..offset + 8 + len].to_vec(),
+                extent as usize,
+                parent as usize,
+            ));
+            offset += 8 + len + len % 2;
+        }
+        entries
+    }
+
+    /// Reads a volume descriptor's root record and path table locations
+    fn read_descriptor(
+        image: &[u8],
+        index: usize,
+        kind: u8,
+    ) -> (Record, Vec<(Vec<u8>, usize, usize)>) {
+        let desc = &image[index * SECTOR..(index + 1) * SECTOR];
+        assert_eq!(desc[0], kind);
+        assert_eq!(&desc[1..6], b"CD001");
+        assert_eq!(desc[6], 1);
+        assert_eq!(read_both_u32(&desc[80..88]) * SECTOR, image.len());
+
+        let size = read_both_u32(&desc[132..140]);
+        let le = u32::from_le_bytes(desc[140..144].try_into().unwrap()) as usize;
+        let be = u32::from_be_bytes(desc[148..152].try_into().unwrap()) as usize;
+        let table = read_path_table(image, le, size, false);
+        assert_eq!(table, read_path_table(image, be, size, true));
+
+        let root = parse_record(&desc[156..190]);
+        assert!(root.dir);
+        assert_eq!(root.ident, [0]);
+        (root, table)
+    }
+
+    /// Looks up a path in a tree, returning the file contents
+    fn read_file<'a>(image: &'a [u8], root: &Record, path: &[&[u8]]) -> &'a [u8] {
+        let mut current = read_dir(image, root.extent, root.size);
+        for (i, name) in path.iter().enumerate() {
+            let record = current
+                .into_iter()
+                .find(|r| r.ident == *name)
+                .unwrap_or_else(|| panic!("{} not found", String::from_utf8_lossy(name)));
+            if i + 1 == path.len() {
+                assert!(!record.dir);
+                return &image[record.extent * SECTOR..record.extent * SECTOR + record.size];
+            }
+            current = read_dir(image, record.extent, record.size);
+        }
+        unreachable!()
+    }
+
+    fn ucs2(name: &str) -> Vec<u8> {
+        name.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn test_descriptors() {
+        let mut builder = IsoBuilder::new("cidata");
+        builder.add_file("meta-data", b"instance-id: test\n".to_vec());
+        let image = builder.build();
+
+        assert_eq!(image.len() % SECTOR, 0);
+        let primary = &image[FIRST_DESCRIPTOR * SECTOR..];
+        assert_eq!(&primary[40..46], b"cidata");
+        let joliet = &image[(FIRST_DESCRIPTOR + 1) * SECTOR..];
+        assert_eq!(&joliet[88..91], b"%/E");
+        assert_eq!(&joliet[40..52], &ucs2("cidata")[..]);
+        let terminator = &image[(FIRST_DESCRIPTOR + 2) * SECTOR..];
+        assert_eq!(terminator[0], 255);
+        assert_eq!(&terminator[1..6], b"CD001");
+
+        read_descriptor(&image, FIRST_DESCRIPTOR, 1);
+        read_descriptor(&image, FIRST_DESCRIPTOR + 1, 2);
+    }
+
+    #[test]
+    fn test_tree() {
+        let mut builder = IsoBuilder::new("test");
+        builder.add_file("b/two.txt", b"two".to_vec());
+        builder.add_file("a/nested/three.txt", b"three".to_vec());
+        builder.add_file("one.txt", b"one".to_vec());
+        let image = builder.build();
+
+        let (root, table) = read_descriptor(&image, FIRST_DESCRIPTOR, 1);
+        let idents: Vec<&[u8]> = table.iter().map(|(i, _, _)| &i[..]).collect();
+        assert_eq!(idents, [&b"\0"[..], b"A", b"B", b"NESTED"]);
+        let parents: Vec<usize> = table.iter().map(|(_, _, p)| *p).collect();
+        assert_eq!(parents, [1, 1, 1, 2]);
+        assert_eq!(table[0].1, root.extent);
+
+        let records = read_dir(&image, root.extent, root.size);
+        let idents: Vec<&[u8]> = records.iter().map(|r| &r.ident[..]).collect();
+        assert_eq!(idents, [&b"\0"[..], b"\x01", b"A", b"B", b"ONE.TXT;1"]);
+        assert_eq!(records[0].extent, root.extent);
+        assert_eq!(records[1].extent, root.extent);
+        assert_eq!(records[2].extent, table[1].1);
+        assert!(records[2].dir && !records[4].dir);
+
+        assert_eq!(read_file(&image, &root, &[b"ONE.TXT;1"]), b"one");
+        assert_eq!(read_file(&image, &root, &[b"B", b"TWO.TXT;1"]), b"two");
+        assert_eq!(
+            read_file(&image, &root, &[b"A", b"NESTED", b"THREE.TXT;1"]),
+            b"three"
+        );
+
+        let (root, table) = read_descriptor(&image, FIRST_DESCRIPTOR + 1, 2);
+        assert_eq!(table[3].0, ucs2("nested"));
+        assert_eq!(
+            read_file(
+                &image,
+                &root,
+                &[&ucs2("a"), &ucs2("nested"), &ucs2("three.txt")]
+            ),
+            b"three"
+        );
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut builder = IsoBuilder::new("test");
+        builder.add_file("user-data", vec![1; 3 * SECTOR]);
+        builder.add_file("user-data", b"replaced".to_vec());
+        builder.add_file("dir/big", vec![2; 3 * SECTOR]);
+        builder.add_file("dir", b"file".to_vec());
+        let image = builder.build();
+
+        // The replaced data takes no space in the image
+        let mut fresh = IsoBuilder::new("test");
+        fresh.add_file("user-data", b"replaced".to_vec());
+        fresh.add_file("dir", b"file".to_vec());
+        assert_eq!(image.len(), fresh.build().len());
+
+        let (root, _) = read_descriptor(&image, FIRST_DESCRIPTOR, 1);
+        assert_eq!(read_file(&image, &root, &[b"USER_DAT.;1"]), b"replaced");
+        assert_eq!(read_file(&image, &root, &[b"DIR.;1"]), b"file");
+    }
+
+    #[test]
+    fn test_directory_across_sectors() {
+        let mut builder = IsoBuilder::new("test");
+        for i in 0..100 {
+            builder.add_file(
+                &format!("dir/a-rather-long-file-name-number-{i:03}.yaml"),
+                i.to_string().into_bytes(),
+            );
+        }
+        let image = builder.build();
+
+        for (index, kind) in [(FIRST_DESCRIPTOR, 1), (FIRST_DESCRIPTOR + 1, 2)] {
+            let (root, table) = read_descriptor(&image, index, kind);
+            let dir = read_dir(&image, root.extent, root.size).remove(2);
+            assert_eq!(dir.extent, table[1].1);
+            assert!(dir.size > SECTOR);
+            let records = read_dir(&image, dir.extent, dir.size);
+            assert_eq!(records.len(), 102);
+            for record in &records[2..] {
+                let data = &image[record.extent * SECTOR..record.extent * SECTOR + record.size];
+                assert!(String::from_utf8_lossy(data).parse::<u32>().is_ok());
+            }
+        }
+
+        let (root, _) = read_descriptor(&image, FIRST_DESCRIPTOR + 1, 2);
+        let name = ucs2("a-rather-long-file-name-number-042.yaml");
+        assert_eq!(read_file(&image, &root, &[&ucs2("dir"), &name]), b"42");
+    }
+
+    #[test]
+    fn test_iso_ident() {
+        let mut taken = HashSet::new();
+        assert_eq!(iso_ident("user-data", false, &mut taken), b"USER_DAT.;1");
+        assert_eq!(iso_ident("user_data", false, &mut taken), b"USER_D~1.;1");
+        assert_eq!(iso_ident("user.data", false, &mut taken), b"USER.DAT;1");
+        assert_eq!(iso_ident("user-data", true, &mut taken), b"USER_DAT");
+        assert_eq!(iso_ident("user_data", true, &mut taken), b"USER_D~1");
+        assert_eq!(iso_ident(".hidden", false, &mut taken), b"_.HID;1");
+
+        let mut taken = HashSet::new();
+        let idents: HashSet<Vec<u8>> = (0..12)
+            .map(|i| iso_ident(&format!("network-config-{i}"), false, &mut taken))
+            .collect();
+        assert_eq!(idents.len(), 12);
+        assert!(idents.contains(&b"NETWO~10.;1"[..]));
+    }
+
+    #[test]
+    fn test_iso_ident_collision_in_image() {
+        let mut builder = IsoBuilder::new("test");
+        builder.add_file("user-data", b"dash".to_vec());
+        builder.add_file("user_data", b"underscore".to_vec());
+        let image = builder.build();
+
+        let (root, _) = read_descriptor(&image, FIRST_DESCRIPTOR, 1);
+        assert_eq!(read_file(&image, &root, &[b"USER_DAT.;1"]), b"dash");
+        assert_eq!(read_file(&image, &root, &[b"USER_D~1.;1"]), b"underscore");
+
+        let (root, _) = read_descriptor(&image, FIRST_DESCRIPTOR + 1, 2);
+        assert_eq!(read_file(&image, &root, &[&ucs2("user-data")]), b"dash");
+        assert_eq!(
+            read_file(&image, &root, &[&ucs2("user_data")]),
+            b"underscore"
+        );
+    }
+
+    #[test]
+    fn test_joliet_ident() {
+        let mut taken = HashSet::new();
+        assert_eq!(joliet_ident("a:b", &mut taken), ucs2("a_b"));
+        assert_eq!(joliet_ident("a?b", &mut taken), ucs2("a_b~1"));
+
+        let long = "x".repeat(70);
+        let ident = joliet_ident(&long, &mut taken);
+        assert_eq!(ident.len(), JOLIET_MAX * 2);
+        assert_eq!(ident, ucs2(&long[..JOLIET_MAX]));
+
+        let other = format!("{}y", "x".repeat(69));
+        let ident = joliet_ident(&other, &mut taken);
+        assert_eq!(ident, ucs2(&format!("{}~1", "x".repeat(JOLIET_MAX - 2))));
+
+        // A surrogate pair straddling the limit is dropped as a whole
+        let emoji = format!("{}\u{1F600}", "x".repeat(JOLIET_MAX - 1));
+        let ident = joliet_ident(&emoji, &mut HashSet::new());
+        assert_eq!(ident, ucs2(&"x".repeat(JOLIET_MAX - 1)));
+    }
+}
@@ -36,8 +36,9 @@ async fn groups(state: &mut Velocity) {
 }
 
 #[clik_command(pools, "List all available pools for a group")]
-#[clik_arg(gid, "The group id of the group to list available pools of")]
-async fn pools(state: &mut Velocity, gid: GID) {
+#[clik_arg(gid, "The group id or path of the group to list available pools of")]
+async fn pools(state: &mut Velocity, gid: String) {
+    let gid = state.resolve_group(&gid).await?;
     let pools = state.pool_list(gid).await?;
 
     println!("Pools available to group {}:", gid);
@@ -52,7 +53,9 @@ async fn pools(state: &mut Velocity, gid: GID) {
 }
 
 #[clik_command(media, "List all available media for a group")]
-async fn media(state: &mut Velocity, gid: GID) {
+#[clik_arg(gid, "The group id or path of the group to list available media of")]
+async fn media(state: &mut Velocity, gid: String) {
+    let gid = state.resolve_group(&gid).await?;
     let media = state.media_list(gid).await?;
 
     println!("Media available to group {}:", gid);
@@ -0,0 +1,90 @@
+//! A minimal logger writing to stderr or a log file
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Logs the records of vcmd and the velocity crate up to a configurable level,
+/// all other crates only log warnings and errors
+struct Logger {
+    level: LevelFilter,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let target = metadata.target();
+        let level = match target.starts_with("velocity") || target.starts_with("vcmd") {
+            true => self.level,
+            false => self.level.min(LevelFilter::Warn),
+        };
+
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        let line = format!(
+            "[{:.3}] {:<5} {}: {}\n",
+            timestamp,
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        // A failing log write must never take down the command that is being logged
+        match &self.file {
+            Some(file) => {
+                if let Ok(mut file) = file.lock() {
+                    file.write_all(line.as_bytes()).ok();
+                }
+            }
+            None => {
+                std::io::stderr().write_all(line.as_bytes()).ok();
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(Ok(mut file)) = self.file.as_ref().map(|f| f.lock()) {
+            file.flush().ok();
+        }
+    }
+}
+
+/// Installs the logger
+/// # Arguments
+/// * `verbosity` - `0` logs warnings, `1` debug messages and `2` or more everything
+/// * `file` - The file to append log messages to instead of writing them to stderr
+pub fn init(verbosity: u8, file: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let level = match verbosity {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let file = match file {
+        Some(path) => Some(Mutex::new(
+            OpenOptions::new().create(true).append(true).open(path)?,
+        )),
+        None => None,
+    };
+
+    log::set_boxed_logger(Box::new(Logger { level, file }))?;
+    log::set_max_level(level);
+
+    Ok(())
+}
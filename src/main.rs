@@ -3,18 +3,107 @@ use std::{error::Error, io::Write, path::PathBuf};
 use velocity::*;
 
 mod assign;
+mod config;
 mod create;
+mod hint;
+mod iso;
 mod list;
+mod logger;
 mod remove;
 mod u;
 mod upload;
+mod verify;
 mod wizard;
 
-async fn run(args: &Vec<String>) -> Result<(), Box<dyn Error>> {
-    if args.len() != 3 {
-        println!("Usage: {} <Velocity hypervisor URL> <username>", args[0]);
-        return Ok(());
+/// The options vcmd is started with
+struct Options {
+    /// The base url of the hypervisor
+    url: String,
+    /// The user to log in as
+    username: String,
+    /// How verbose logging should be: `-v` = 1, `-vv` = 2
+    verbosity: u8,
+    /// The file to write the log to instead of stderr
+    log_file: Option<PathBuf>,
+    /// A single command to run instead of starting the interactive prompt
+    command: Option<String>,
+    /// The default bandwidth limit for transfers, overriding the one from the configuration file
+    limit: Option<Option<u64>>,
+}
+
+impl Options {
+    /// Parses the options from the command line arguments, `None` if they are incomplete
+    /// # Arguments
+    /// * `args` - The command line arguments, including the program name
+    fn parse(args: &[String]) -> Result<Option<Options>, Box<dyn Error>> {
+        let mut positional = Vec::new();
+        let mut verbosity = 0;
+        let mut log_file = None;
+        let mut command = None;
+        let mut limit = None;
+
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            // Flags that take a value
+            let mut value = || iter.next().ok_or(format!("'{}' needs a value", arg));
+
+            match arg.as_str() {
+                "-v" => verbosity += 1,
+                "-vv" => verbosity += 2,
+                "--log-file" => log_file = Some(PathBuf::from(value()?)),
+                "-c" => command = Some(value()?.clone()),
+                "--limit" => limit = Some(upload::parse_rate(value()?)?),
+                _ => positional.push(arg.clone()),
+            }
+        }
+
+        Ok(match <[String; 2]>::try_from(positional) {
+            Ok([url, username]) => Some(Options {
+                url,
+                username,
+                verbosity,
+                log_file,
+                command,
+                limit,
+            }),
+            Err(_) => None,
+        })
     }
+}
+
+/// Handles a line using the CLI struct and responds to errors
+/// # Arguments
+/// * `cli` - The CLI to handle the line with
+/// * `line` - The line to handle
+/// # Returns
+/// If the line has been handled successfully
+async fn handle(cli: &mut clik::CLI<'_, Velocity>, line: &str) -> bool {
+    match cli.handle_async(line).await {
+        Ok(_) => true,
+        Err(e) => {
+            println!("ERROR: {e}");
+            if let Some(hint) = hint::remediation_hint(e.as_ref()) {
+                println!("HINT: {hint}");
+            }
+            false
+        }
+    }
+}
+
+async fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let options = match Options::parse(args)? {
+        Some(options) => options,
+        None => {
+            println!(
+                "Usage: {} [-v | -vv] [--log-file <path>] [-c <command>] [--limit <rate>] <Velocity hypervisor URL> <username>",
+                args[0]
+            );
+            return Ok(());
+        }
+    };
+
+    logger::init(options.verbosity, options.log_file.as_deref())?;
+    let config = config::Config::load()?;
 
     // Create a new rustyline editor for reading in history
     let mut readline = DefaultEditor::new()?;
@@ -28,14 +117,23 @@ async fn run(args: &Vec<String>) -> Result<(), Box<dyn Error>> {
     }
 
     // Read in the password
-    print!("Password for {}: ", args[2]);
+    print!("Password for {}: ", options.username);
     std::io::stdout().flush()?;
     let password = rpassword::read_password()?;
 
     // Log in
-    let velocity = Velocity::new(&args[1], &args[2], &password).await?;
-    println!("Logged in as {}", args[2]);
+    let velocity = Velocity::new(&options.url, &options.username, &password).await?;
+    println!("Logged in as {}", options.username);
 
+    // The command line takes precedence over the configuration file
+    if let Some(limit) = options.limit.or(config.limit) {
+        velocity.transfer_limit().set(limit);
+    }
+    #[cfg(unix)]
+    config::reload_limit_on_signal(velocity.transfer_limit().clone())?;
+
+    // Dropping the client does not log out, this clone shares its authkey
+    let session = velocity.clone();
     let mut cli = clik::CLI::new(velocity);
     u::register_commands(&mut cli);
     create::register_commands(&mut cli);
@@ -43,33 +141,36 @@ async fn run(args: &Vec<String>) -> Result<(), Box<dyn Error>> {
     list::register_commands(&mut cli);
     assign::register_commands(&mut cli);
     upload::register_commands(&mut cli);
+    verify::register_commands(&mut cli);
+
+    // Run a single command, this keeps stdin free for piping data into uploads
+    if let Some(command) = &options.command {
+        let ok = handle(&mut cli, command).await;
+        session.deauthenticate().await.ok();
+        if !ok {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     println!("\n------ vCMD ------\n{}", cli);
 
     // Handle all incoming lines
-    loop {
-        match readline.readline("vCMD >> ") {
-            Ok(line) => {
-                readline.add_history_entry(&line).unwrap();
-
-                // Handle the line using the CLI struct and respond to errors
-                match cli.handle_async(&line).await {
-                    Ok(_) => {}
-                    Err(e) => println!("ERROR: {e}"),
-                }
-            }
-            Err(_) => break,
-        }
+    while let Ok(line) = readline.readline("vCMD >> ") {
+        readline.add_history_entry(&line).unwrap();
+
+        handle(&mut cli, &line).await;
     }
 
     readline.save_history(&history_path)?;
+    session.deauthenticate().await.ok();
 
     Ok(())
 }
 
 #[tokio::main]
 async fn main() {
-    match run(&std::env::args().collect()).await {
+    match run(&std::env::args().collect::<Vec<String>>()).await {
         Ok(_) => {}
         Err(e) => println!("{}", e),
     }
@@ -10,8 +10,9 @@ pub fn register_commands(cli: &mut CLI<Velocity>) {
 }
 
 #[clik_command(user, "Remove a user")]
-#[clik_arg(uid, "The UID for the user to remove")]
-async fn user(state: &mut Velocity, uid: UID) {
+#[clik_arg(uid, "The UID or name of the user to remove")]
+async fn user(state: &mut Velocity, uid: String) {
+    let uid = state.resolve_user(&uid).await?;
     state.user_remove(uid).await?;
 
     println!("Removed user with UID = {uid}");
@@ -20,8 +21,9 @@ async fn user(state: &mut Velocity, uid: UID) {
 }
 
 #[clik_command(group, "Remove a group")]
-#[clik_arg(gid, "The GID for the user to remove")]
-async fn group(state: &mut Velocity, gid: GID) {
+#[clik_arg(gid, "The GID or path of the group to remove")]
+async fn group(state: &mut Velocity, gid: String) {
+    let gid = state.resolve_group(&gid).await?;
     state.group_remove(gid).await?;
 
     println!("Removed group with GID = {gid}");
@@ -30,8 +32,9 @@ async fn group(state: &mut Velocity, gid: GID) {
 }
 
 #[clik_command(media, "Remove a piece of media")]
-#[clik_arg(mid, "The media id of the piece of media to remove")]
-async fn media(state: &mut Velocity, mid: MID) {
+#[clik_arg(mid, "The media id or name of the piece of media to remove")]
+async fn media(state: &mut Velocity, mid: String) {
+    let mid = state.resolve_media(&mid).await?;
     state.media_remove(mid.clone()).await?;
 
     println!("Removed media with MID = {mid}");
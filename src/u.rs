@@ -19,16 +19,18 @@ async fn u_userinfo(state: &mut Velocity) {
 }
 
 #[clik_command(user, "Provide information about another user")]
-#[clik_arg(uid, "The UID of the user to retrieve information of")]
-async fn u_spec_userinfo(state: &mut Velocity, uid: UID) {
+#[clik_arg(uid, "The UID or name of the user to retrieve information of")]
+async fn u_spec_userinfo(state: &mut Velocity, uid: String) {
+    let uid = state.resolve_user(&uid).await?;
     print!("{}", state.user_info(Some(uid)).await?);
 
     Ok(())
 }
 
 #[clik_command(groupinfo, "Provide information about a group")]
-#[clik_arg(gid, "The GID of the group to retrieve information of")]
-async fn groupinfo(state: &mut Velocity, gid: GID) {
+#[clik_arg(gid, "The GID or path of the group to retrieve information of")]
+async fn groupinfo(state: &mut Velocity, gid: String) {
+    let gid = state.resolve_group(&gid).await?;
     print!("{}", state.group_info(gid).await?);
 
     Ok(())
@@ -1,63 +1,443 @@
+use std::{error::Error, path::Path};
+
 use clik::*;
-use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
-use velocity::*;
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
+use velocity::{
+    endpoints::m::MMediaUploadPUTRes,
+    error::{ClientError, VelocityError},
+    image::{ImageFormat, RawImage},
+    transfer::{
+        BandwidthLimit, CancellationToken, TransferOptions, TransferPhase, TransferProgress,
+    },
+    *,
+};
+
+mod batch;
+#[cfg(feature = "experimental-chunked")]
+mod resume;
+pub mod seed;
+
+/// The media type that gets detected from the file to upload
+const AUTO_TYPE: &str = "auto";
 
 pub fn register_commands(cli: &mut CLI<Velocity>) {
     let mut upload = upload();
-    upload.add_subcommand(upload_media());
+    #[cfg_attr(not(feature = "experimental-chunked"), allow(unused_mut))]
+    let mut media = Command::new_async(
+        "media",
+        "Upload new media: <mpid> <gid> <name> <type|auto> <path|-> <readonly> [--limit <rate>]",
+        async_fn!(Velocity, upload_media),
+    );
+    #[cfg(feature = "experimental-chunked")]
+    media.add_subcommand(Command::new_async(
+        "--resume",
+        "Resume all interrupted media uploads: [--limit <rate>]",
+        async_fn!(Velocity, resume::upload_media_resume),
+    ));
+    upload.add_subcommand(media);
+    upload.add_subcommand(Command::new_async(
+        "batch",
+        "Upload many files at once: <mpid> <gid> <glob|@manifest> [concurrency] [--limit <rate>]",
+        async_fn!(Velocity, batch::upload_batch),
+    ));
+    upload.add_subcommand(Command::new_async(
+        "seed",
+        "Pack files into a cloud-init seed ISO and upload it: <mpid> <gid> <name> <dir | user-data [meta-data] [network-config]> [--hostname <hostname>] [--limit <rate>]",
+        async_fn!(Velocity, seed::upload_seed_command),
+    ));
     cli.add_command(upload);
+    cli.add_command(limit());
+}
+
+/// Uploads new media: `upload media <mpid> <gid> <name> <type|auto> <path|-> <readonly> [--limit <rate>]`.
+/// qcow2 images get converted to raw data on the fly
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `args` - The arguments to the command
+async fn upload_media(state: &mut Velocity, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let options = transfer_options(&mut args)?;
+    let mpid: String = arg(&args, 0, "mpid")?;
+    let gid: String = arg(&args, 1, "gid")?;
+    let name: String = arg(&args, 2, "name")?;
+    let ty: String = arg(&args, 3, "ty")?;
+    let path: String = arg(&args, 4, "path")?;
+    let readonly: bool = arg(&args, 5, "readonly")?;
+    let mpid = state.resolve_pool(&mpid).await?;
+    let gid = state.resolve_group(&gid).await?;
+
+    // Data from stdin cannot be read again, so it is streamed in a single request
+    if path == "-" {
+        if ty == AUTO_TYPE {
+            return Err("The type of data from stdin cannot be detected, specify it".into());
+        }
+
+        let upload = StreamUpload {
+            mpid,
+            gid,
+            name: &name,
+            ty: &ty,
+            readonly,
+        };
+        return upload.run(state, tokio::io::stdin(), None, options).await;
+    }
+
+    let source = std::fs::canonicalize(path)?;
+    let format = ImageFormat::detect_file(&source).await?;
+    let ty = match ty.as_str() {
+        AUTO_TYPE => format.media_type().to_owned(),
+        _ => ty,
+    };
+
+    // Converted data does not match the file, so it is streamed in a single request as well
+    if !matches!(format, ImageFormat::Raw | ImageFormat::Iso9660) {
+        let image = RawImage::open(&source).await?;
+        println!(
+            "Converting {} image to {} of raw data while uploading",
+            format,
+            HumanBytes(image.size)
+        );
+
+        let upload = StreamUpload {
+            mpid,
+            gid,
+            name: &name,
+            ty: &ty,
+            readonly,
+        };
+        return upload
+            .run(state, image.reader, Some(image.size), options)
+            .await;
+    }
+
+    // Only chunked uploads can be resumed, plain ones are streamed in a single request
+    #[cfg(feature = "experimental-chunked")]
+    if state.media_upload_chunked_supported().await? {
+        return resume::upload_chunked(state, mpid, gid, &name, &ty, readonly, &source, options)
+            .await;
+    }
+
+    let upload = StreamUpload {
+        mpid,
+        gid,
+        name: &name,
+        ty: &ty,
+        readonly,
+    };
+    upload.run_file(state, &source, options).await
 }
 
-#[clik_command(media, "Upload new media")]
-#[clik_arg(mpid, "The media pool id to upload to")]
-#[clik_arg(gid, "The group id of the group the media should belong to")]
-#[clik_arg(name, "A user-friendly name for the new media")]
-#[clik_arg(
-    ty,
-    "A string describing the type of media this is - see the Velocity API documentation"
-)]
-#[clik_arg(path, "The path to the file to upload")]
-#[clik_arg(readonly, "If this media should be read-only")]
-async fn upload_media(
-    state: &mut Velocity,
+/// An upload that is streamed in a single request and cannot be resumed
+struct StreamUpload<'a> {
     mpid: MPID,
     gid: GID,
-    name: String,
-    ty: String,
-    path: String,
+    name: &'a str,
+    ty: &'a str,
     readonly: bool,
-) {
-    let file = tokio::fs::File::open(path).await?;
+}
+
+impl StreamUpload<'_> {
+    /// Uploads the data provided by a reader
+    /// # Arguments
+    /// * `state` - The client to upload with
+    /// * `reader` - The reader providing the data
+    /// * `size` - The length of the data, if known
+    /// * `options` - The options for the transfer
+    async fn run<R>(
+        &self,
+        state: &Velocity,
+        reader: R,
+        size: Option<u64>,
+        options: TransferOptions,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        R: tokio::io::AsyncRead + Send + 'static,
+    {
+        let bar = progress_bar(format!(
+            "Uploading {} to mediapool {}",
+            self.name, self.mpid
+        ));
+        let progress_bar = bar.clone();
+
+        let res = with_ctrl_c(options, |options| {
+            state.media_upload(
+                self.mpid,
+                self.gid,
+                self.name,
+                self.ty,
+                self.readonly,
+                reader,
+                size,
+                options,
+                move |progress: &TransferProgress| update_progress(&progress_bar, progress),
+            )
+        })
+        .await;
+
+        report(&bar, self.name, self.mpid, res, false)
+    }
+
+    /// Uploads a file, which gets hashed first so the hypervisor can verify the upload
+    /// # Arguments
+    /// * `state` - The client to upload with
+    /// * `source` - The path to the file
+    /// * `options` - The options for the transfer
+    async fn run_file(
+        &self,
+        state: &Velocity,
+        source: &Path,
+        options: TransferOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = progress_bar(format!(
+            "Uploading {} to mediapool {}",
+            self.name, self.mpid
+        ));
+        let progress_bar = bar.clone();
+
+        let res = with_ctrl_c(options, |options| {
+            state.media_upload_file(
+                self.mpid,
+                self.gid,
+                self.name,
+                self.ty,
+                self.readonly,
+                source,
+                options,
+                move |progress: &TransferProgress| update_progress(&progress_bar, progress),
+            )
+        })
+        .await;
+
+        report(&bar, self.name, self.mpid, res, false)
+    }
+}
+
+#[clik_command(upload, "Upload something")]
+async fn upload(state: &mut Velocity) {
+    Ok(())
+}
+
+#[clik_command(limit, "Set the default bandwidth limit for transfers")]
+#[clik_arg(rate, "The limit in bytes per second, e.g. '20M', or 'off'")]
+async fn limit(state: &mut Velocity, rate: String) {
+    let rate = parse_rate(&rate)?;
+    state.transfer_limit().set(rate);
+
+    match rate {
+        Some(rate) => println!("Limited transfers to {}/s", HumanBytes(rate)),
+        None => println!("Removed the bandwidth limit for transfers"),
+    }
+
+    Ok(())
+}
+
+/// Parses a transfer rate in bytes per second, with an optional binary suffix: `512K`, `20M`, `1G`.
+/// `off` or `0` disable the limit. Negative, infinite and fractional amounts of bytes are rejected
+/// # Arguments
+/// * `rate` - The rate to parse
+pub fn parse_rate(rate: &str) -> Result<Option<u64>, Box<dyn Error>> {
+    let rate = rate.trim();
+    if rate.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+
+    let (number, factor) = match rate.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let factor: u64 = match c.to_ascii_uppercase() {
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                _ => return Err(format!("Unknown unit '{}' in rate '{}'", c, rate).into()),
+            };
+            (&rate[..i], factor)
+        }
+        _ => (rate, 1),
+    };
+
+    let bytes = number.parse::<f64>()? * factor as f64;
+    if !bytes.is_finite() || bytes < 0.0 || bytes.fract() != 0.0 || bytes > u64::MAX as f64 {
+        return Err(format!(
+            "Invalid rate '{}', expected a positive amount of whole bytes per second",
+            rate
+        )
+        .into());
+    }
+
+    let bytes = bytes as u64;
+    Ok((bytes > 0).then_some(bytes))
+}
+
+/// Removes a `--limit <rate>` flag from the arguments of a transfer command and
+/// creates the options for the transfer from it
+/// # Arguments
+/// * `args` - The arguments of the command
+fn transfer_options(args: &mut Vec<String>) -> Result<TransferOptions, Box<dyn Error>> {
+    let mut options = TransferOptions::new();
+
+    if let Some(i) = args.iter().position(|a| a == "--limit") {
+        let rate = args
+            .get(i + 1)
+            .ok_or("The '--limit' flag needs a rate, e.g. '--limit 20M'")?;
+        options = options.limit(BandwidthLimit::new(parse_rate(rate)?));
+        args.drain(i..i + 2);
+    }
+
+    Ok(options)
+}
+
+/// Parses a positional argument of a command
+/// # Arguments
+/// * `args` - The arguments of the command
+/// * `position` - The position of the argument
+/// * `name` - The name of the argument for error messages
+fn arg<T>(args: &[String], position: usize, name: &str) -> Result<T, Box<dyn Error>>
+where
+    T: std::str::FromStr,
+    T::Err: Error + 'static,
+{
+    let ty = std::any::type_name::<T>().to_string();
+
+    match args.get(position) {
+        None => Err(clik::error::MissingArgumentError {
+            name: name.to_string(),
+            position,
+            ty,
+        }
+        .into()),
+        Some(arg) => arg.parse().map_err(|e: T::Err| {
+            clik::error::WrongArgumentError {
+                name: name.to_string(),
+                position,
+                ty,
+                inner: Box::new(e),
+            }
+            .into()
+        }),
+    }
+}
+
+/// Creates a progress bar for an upload
+/// # Arguments
+/// * `prefix` - The text to display in front of the bar
+fn progress_bar(prefix: String) -> ProgressBar {
     let bar = ProgressBar::new(0);
 
     let style_e = ProgressStyle::with_template(
-        "{prefix:<60} [{elapsed_precise}] [{msg:>23.yellow}] [{percent:>3.green}%] [{wide_bar}] ",
+        "{prefix:<60} [{elapsed_precise}] [{msg:>48.yellow}] [{percent:>3.green}%] [{wide_bar}] ",
     );
     let style = style_e.unwrap();
-    bar.set_length(file.metadata().await.unwrap().len());
     bar.set_style(style);
-    bar.set_prefix(format!("Uploading {} to mediapool {}", name, mpid));
-
-    let res = state
-        .media_upload(mpid, gid, &name, &ty, readonly, file, move |total, done| {
-            bar.set_position(done);
-            bar.set_message(
-                HumanBytes(done as u64).to_string()
-                    + " / "
-                    + HumanBytes(total as u64).to_string().as_str(),
-            );
-        })
-        .await?;
+    bar.set_prefix(prefix);
 
-    println!(
-        "Uploaded new media '{name}' in pool {mpid}. MID: {}, size: {} bytes",
-        res.mid, res.size
-    );
+    bar
+}
 
-    Ok(())
+/// Updates a progress bar with the current state of an upload
+/// # Arguments
+/// * `bar` - The bar to update
+/// * `progress` - The progress of the upload
+fn update_progress(bar: &ProgressBar, progress: &TransferProgress) {
+    bar.set_position(progress.done);
+
+    // The total is unknown when reading from a stream
+    let mut message = match progress.total {
+        Some(total) => {
+            bar.set_length(total);
+            format!("{} / {}", HumanBytes(progress.done), HumanBytes(total))
+        }
+        None => {
+            bar.tick();
+            HumanBytes(progress.done).to_string()
+        }
+    };
+
+    match progress.phase {
+        TransferPhase::Uploading => {
+            message += &format!(" @ {}/s", HumanBytes(progress.rate as u64));
+            if let Some(eta) = progress.eta {
+                message += &format!(", {} left", HumanDuration(eta));
+            }
+        }
+        phase => message = format!("{phase} {message}"),
+    }
+
+    bar.set_message(message);
 }
 
-#[clik_command(upload, "Upload something")]
-async fn upload(state: &mut Velocity) {
-    Ok(())
+/// Runs a transfer that gets cancelled on Ctrl-C instead of terminating the whole process.
+/// A limit set with `--limit` follows the configuration file on `SIGUSR1`, like the default limit
+/// # Arguments
+/// * `options` - The options for the transfer
+/// * `transfer` - Starts the transfer with the provided options
+async fn with_ctrl_c<F, Fut, T>(options: TransferOptions, transfer: F) -> Result<T, VelocityError>
+where
+    F: FnOnce(TransferOptions) -> Fut,
+    Fut: std::future::Future<Output = Result<T, VelocityError>>,
+{
+    #[cfg(unix)]
+    let reload = match &options.limit {
+        Some(limit) => Some(crate::config::reload_limit_on_signal(limit.clone())?),
+        None => None,
+    };
+
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let ctrl_c = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+
+    let res = transfer(options.cancel_token(token)).await;
+    ctrl_c.abort();
+    #[cfg(unix)]
+    if let Some(reload) = reload {
+        reload.abort();
+    }
+
+    res
+}
+
+/// Reports the outcome of an upload to the user
+/// # Arguments
+/// * `bar` - The progress bar of the upload
+/// * `name` - The name of the uploaded media
+/// * `mpid` - The media pool the media has been uploaded to
+/// * `res` - The result of the upload
+/// * `resumable` - If the upload can be resumed after it has been cancelled
+fn report(
+    bar: &ProgressBar,
+    name: &str,
+    mpid: MPID,
+    res: Result<MMediaUploadPUTRes, VelocityError>,
+    resumable: bool,
+) -> Result<(), Box<dyn Error>> {
+    match res {
+        Ok(res) => {
+            bar.finish();
+            println!(
+                "Uploaded new media '{name}' in pool {mpid}. MID: {}, size: {} bytes",
+                res.mid, res.size
+            );
+            Ok(())
+        }
+        Err(VelocityError::Client(ClientError::Cancelled { transferred })) => {
+            bar.abandon();
+            match bar.length() {
+                Some(total) if resumable && total > 0 => println!(
+                    "Cancelled upload of '{name}' after {} of {}, use 'upload media --resume' to continue",
+                    HumanBytes(transferred),
+                    HumanBytes(total)
+                ),
+                _ => println!(
+                    "Cancelled upload of '{name}' after {}",
+                    HumanBytes(transferred)
+                ),
+            }
+            Ok(())
+        }
+        Err(e) => {
+            bar.abandon();
+            Err(e.into())
+        }
+    }
 }
@@ -0,0 +1,404 @@
+use std::{
+    error::Error,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures_util::{stream, FutureExt, StreamExt};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar};
+use velocity::{
+    error::VelocityError,
+    transfer::{
+        ProgressHandler, ProgressTracker, TransferOptions, TransferPhase, TransferProgress,
+    },
+    *,
+};
+
+#[cfg(feature = "experimental-chunked")]
+use super::resume::{journal_dir, journal_name};
+use super::{progress_bar, transfer_options, update_progress, with_ctrl_c};
+
+/// How many uploads run at the same time if not specified otherwise
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A single file to upload as part of a batch
+struct BatchEntry {
+    path: PathBuf,
+    name: String,
+    ty: String,
+    readonly: bool,
+}
+
+impl BatchEntry {
+    /// Creates an entry for a file, deriving the name from the file name and
+    /// the type from the extension: `.iso` files become read-only `ISO` media, all others `DISK`
+    /// # Arguments
+    /// * `path` - The path to the file
+    fn from_path(path: PathBuf) -> BatchEntry {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let iso = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("iso"));
+
+        BatchEntry {
+            path,
+            name,
+            ty: if iso { "ISO" } else { "DISK" }.to_owned(),
+            readonly: iso,
+        }
+    }
+
+    /// Parses a line of a manifest: `<path> [name] [type] [readonly]`
+    /// # Arguments
+    /// * `line` - The line to parse
+    fn from_manifest_line(line: &str) -> Result<BatchEntry, Box<dyn Error>> {
+        let mut parts = line.split_whitespace();
+        let mut entry = BatchEntry::from_path(PathBuf::from(parts.next().unwrap_or_default()));
+
+        if let Some(name) = parts.next() {
+            entry.name = name.to_owned();
+        }
+        if let Some(ty) = parts.next() {
+            entry.ty = ty.to_owned();
+        }
+        if let Some(readonly) = parts.next() {
+            entry.readonly = readonly.parse()?;
+        }
+
+        Ok(entry)
+    }
+}
+
+/// The command line arguments of `upload batch`
+struct BatchArgs {
+    mpid: MPID,
+    gid: GID,
+    source: String,
+    concurrency: usize,
+    options: TransferOptions,
+    /// If the hypervisor supports chunked uploads, which can be resumed
+    #[cfg(feature = "experimental-chunked")]
+    chunked: bool,
+}
+
+impl BatchArgs {
+    /// Parses the arguments and resolves the pool and group, `None` if they are invalid
+    /// # Arguments
+    /// * `state` - The client to resolve names with
+    /// * `args` - The arguments to the command, without flags
+    /// * `options` - The options for all transfers
+    async fn parse(
+        state: &Velocity,
+        args: &[String],
+        options: TransferOptions,
+    ) -> Result<Option<BatchArgs>, VelocityError> {
+        let (Some(mpid), Some(gid), Some(source)) = (args.first(), args.get(1), args.get(2)) else {
+            return Ok(None);
+        };
+        let concurrency = match args.get(3).map(|c| c.parse().ok().filter(|c| *c > 0)) {
+            Some(Some(concurrency)) => concurrency,
+            Some(None) => return Ok(None),
+            None => DEFAULT_CONCURRENCY,
+        };
+
+        Ok(Some(BatchArgs {
+            mpid: state.resolve_pool(mpid).await?,
+            gid: state.resolve_group(gid).await?,
+            source: source.clone(),
+            concurrency,
+            options,
+            #[cfg(feature = "experimental-chunked")]
+            chunked: state.media_upload_chunked_supported().await?,
+        }))
+    }
+}
+
+/// Collects the files to upload, either from a glob pattern or from a manifest
+/// if the source starts with `@`
+/// # Arguments
+/// * `source` - The glob pattern or `@` and the path to a manifest
+async fn collect_entries(source: &str) -> Result<Vec<BatchEntry>, Box<dyn Error>> {
+    if let Some(manifest) = source.strip_prefix('@') {
+        return tokio::fs::read_to_string(manifest)
+            .await?
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(BatchEntry::from_manifest_line)
+            .collect();
+    }
+
+    let mut entries = Vec::new();
+    for path in glob::glob(source)? {
+        let path = path?;
+        if path.is_file() {
+            entries.push(BatchEntry::from_path(path));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Uploads many files at once: `upload batch <mpid> <gid> <glob|@manifest> [concurrency] [--limit <rate>]`
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `args` - The arguments to the command
+pub async fn upload_batch(
+    state: &mut Velocity,
+    mut args: Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    let options = transfer_options(&mut args)?;
+    let args = match BatchArgs::parse(state, &args, options).await? {
+        Some(args) => args,
+        None => {
+            println!(
+                "Usage: upload batch <mpid> <gid> <glob|@manifest> [concurrency] [--limit <rate>]"
+            );
+            return Ok(());
+        }
+    };
+
+    let entries = collect_entries(&args.source).await?;
+    if entries.is_empty() {
+        println!("No files to upload in '{}'", args.source);
+        return Ok(());
+    }
+
+    #[cfg(feature = "experimental-chunked")]
+    tokio::fs::create_dir_all(journal_dir()).await?;
+
+    let mut total_size = 0;
+    for entry in &entries {
+        total_size += tokio::fs::metadata(&entry.path).await?.len();
+    }
+
+    let multi = MultiProgress::new();
+    let total_bar = multi.add(progress_bar(format!(
+        "Uploading {} files to mediapool {}",
+        entries.len(),
+        args.mpid
+    )));
+    let tracker_bar = total_bar.clone();
+    let batch = Arc::new(BatchProgress {
+        tracker: ProgressTracker::new(
+            move |progress: &TransferProgress| update_progress(&tracker_bar, progress),
+            Some(total_size),
+        ),
+        uploaded: AtomicU64::new(0),
+        resumed: AtomicU64::new(0),
+    });
+    batch.add(0, 0);
+
+    let state = &*state;
+    let results = with_ctrl_c(args.options.clone(), |options| {
+        let multi = &multi;
+        let batch = &batch;
+        let total_bar = &total_bar;
+        let args = &args;
+
+        stream::iter(entries)
+            .map(move |entry| {
+                let options = options.clone();
+                async move {
+                    let bar = multi.insert_before(total_bar, progress_bar(entry.name.clone()));
+                    let res = upload_entry(state, args, &entry, options, &bar, batch).await;
+
+                    match &res {
+                        Ok(_) => bar.finish(),
+                        Err(_) => bar.abandon(),
+                    }
+
+                    (entry, res)
+                }
+            })
+            .buffer_unordered(args.concurrency)
+            .collect::<Vec<_>>()
+            .map(Ok)
+    })
+    .await?;
+    total_bar.finish();
+
+    let (succeeded, failed): (Vec<_>, Vec<_>) = results.iter().partition(|(_, res)| res.is_ok());
+
+    println!(
+        "\nUploaded {} of {} files ({}):",
+        succeeded.len(),
+        results.len(),
+        HumanBytes(batch.uploaded.load(Ordering::SeqCst))
+    );
+    for (entry, res) in succeeded {
+        if let Ok(mid) = res {
+            println!(" - '{}' => {}", entry.name, mid);
+        }
+    }
+
+    if !failed.is_empty() {
+        println!("Failed to upload {} files:", failed.len());
+        for (entry, res) in failed {
+            if let Err(e) = res {
+                println!(" - '{}' ({}): {}", entry.name, entry.path.display(), e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The progress of a whole batch, shared by all of its uploads
+struct BatchProgress<P> {
+    /// Reports the progress to the progress bar of the batch
+    tracker: ProgressTracker<P>,
+    /// The amount of bytes uploaded by this batch
+    uploaded: AtomicU64,
+    /// The amount of bytes of resumed files an earlier batch had uploaded already
+    resumed: AtomicU64,
+}
+
+impl<P: ProgressHandler> BatchProgress<P> {
+    /// Adds to the progress of the batch and reports it
+    /// # Arguments
+    /// * `uploaded` - The amount of bytes that have been uploaded
+    /// * `resumed` - The amount of bytes that had been uploaded before a file got resumed
+    fn add(&self, uploaded: u64, resumed: u64) {
+        let uploaded = self.uploaded.fetch_add(uploaded, Ordering::SeqCst) + uploaded;
+        let resumed = self.resumed.fetch_add(resumed, Ordering::SeqCst) + resumed;
+        self.tracker
+            .report(TransferPhase::Uploading, uploaded + resumed);
+    }
+}
+
+/// Tracks how much a single file has added to the progress of its batch
+#[derive(Default)]
+struct FileProgress {
+    /// The furthest the upload has got, `None` before its first report
+    furthest: Option<u64>,
+}
+
+impl FileProgress {
+    /// Takes the amount of bytes the upload reports as done and returns how many bytes
+    /// have been uploaded since the last report and how many had been uploaded before
+    /// the file got resumed. The first report is the baseline, a resumed upload starts at
+    /// its earlier offset. Reports below the furthest one, e.g. when a chunk is retried
+    /// from an earlier offset, add nothing until the upload has caught up again
+    /// # Arguments
+    /// * `done` - The amount of bytes the upload reports as done
+    fn advance(&mut self, done: u64) -> (u64, u64) {
+        match self.furthest {
+            None => {
+                self.furthest = Some(done);
+                (0, done)
+            }
+            Some(furthest) => {
+                self.furthest = Some(furthest.max(done));
+                (done.saturating_sub(furthest), 0)
+            }
+        }
+    }
+}
+
+/// Uploads a single entry of a batch, updating its own and the total progress bar
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `args` - The arguments of the batch
+/// * `entry` - The file to upload
+/// * `options` - The options for the transfer
+/// * `bar` - The progress bar of this upload
+/// * `batch` - The progress of the whole batch
+/// # Returns
+/// The media id of the uploaded media
+async fn upload_entry(
+    state: &Velocity,
+    args: &BatchArgs,
+    entry: &BatchEntry,
+    options: TransferOptions,
+    bar: &ProgressBar,
+    batch: &Arc<BatchProgress<impl ProgressHandler + 'static>>,
+) -> Result<MID, VelocityError> {
+    let source = tokio::fs::canonicalize(&entry.path).await?;
+
+    let bar = bar.clone();
+    let batch = batch.clone();
+    let file = Mutex::new(FileProgress::default());
+
+    let progress = move |progress: &TransferProgress| {
+        update_progress(&bar, progress);
+
+        // Hashing does not move data, only add what has been uploaded since the last call
+        if progress.phase != TransferPhase::Hashing {
+            let (uploaded, resumed) = file.lock().expect("Lock progress").advance(progress.done);
+            batch.add(uploaded, resumed);
+        }
+    };
+
+    // Continue where an earlier, interrupted batch left off
+    #[cfg(feature = "experimental-chunked")]
+    if args.chunked {
+        let journal = journal_dir().join(journal_name(args.mpid, args.gid, &entry.name));
+        let res = if journal.exists() {
+            state
+                .media_upload_resume(&journal, options, progress)
+                .await?
+        } else {
+            state
+                .media_upload_chunked(
+                    args.mpid,
+                    args.gid,
+                    &entry.name,
+                    &entry.ty,
+                    entry.readonly,
+                    &source,
+                    &journal,
+                    options,
+                    progress,
+                )
+                .await?
+        };
+
+        return Ok(res.mid);
+    }
+
+    // Without chunked uploads, the file is streamed in a single request
+    let res = state
+        .media_upload_file(
+            args.mpid,
+            args.gid,
+            &entry.name,
+            &entry.ty,
+            entry.readonly,
+            &source,
+            options,
+            progress,
+        )
+        .await?;
+
+    Ok(res.mid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_progress() {
+        let mut file = FileProgress::default();
+        assert_eq!(file.advance(0), (0, 0));
+        assert_eq!(file.advance(4096), (4096, 0));
+        assert_eq!(file.advance(8192), (4096, 0));
+
+        // A retried chunk starts over without adding anything twice
+        assert_eq!(file.advance(4096), (0, 0));
+        assert_eq!(file.advance(6144), (0, 0));
+        assert_eq!(file.advance(10240), (2048, 0));
+
+        // A resumed upload starts at its earlier offset
+        let mut file = FileProgress::default();
+        assert_eq!(file.advance(8192), (0, 8192));
+        assert_eq!(file.advance(12288), (4096, 0));
+    }
+}
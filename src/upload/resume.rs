@@ -0,0 +1,171 @@
+//! Chunked uploads that can be resumed after an interruption. They rely on the experimental
+//! chunked upload endpoints, which only some hypervisors serve
+
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use velocity::{
+    transfer::{TransferOptions, TransferProgress, UploadJournal},
+    *,
+};
+
+use super::{progress_bar, report, transfer_options, update_progress, with_ctrl_c};
+
+/// Uploads a file in chunks, recording the progress in a journal so the upload
+/// can be continued with `upload media --resume`
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `mpid` - The media pool to upload to
+/// * `gid` - The group the media belongs to
+/// * `name` - The name of the media
+/// * `ty` - The type of the media
+/// * `readonly` - If the media should be read-only
+/// * `source` - The file to upload
+/// * `options` - The options for the transfer
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_chunked(
+    state: &Velocity,
+    mpid: MPID,
+    gid: GID,
+    name: &str,
+    ty: &str,
+    readonly: bool,
+    source: &Path,
+    options: TransferOptions,
+) -> Result<(), Box<dyn Error>> {
+    let journal_dir = journal_dir();
+    tokio::fs::create_dir_all(&journal_dir).await?;
+    let journal = journal_dir.join(journal_name(mpid, gid, name));
+
+    if journal.exists() {
+        println!("An upload of '{name}' has been interrupted, use 'upload media --resume'");
+        return Ok(());
+    }
+
+    let bar = progress_bar(format!("Uploading {} to mediapool {}", name, mpid));
+    let progress_bar = bar.clone();
+
+    let res = with_ctrl_c(options, |options| {
+        state.media_upload_chunked(
+            mpid,
+            gid,
+            name,
+            ty,
+            readonly,
+            source,
+            &journal,
+            options,
+            move |progress: &TransferProgress| update_progress(&progress_bar, progress),
+        )
+    })
+    .await;
+
+    report(&bar, name, mpid, res, true)
+}
+
+/// Resumes all uploads that have a journal in the journal directory
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `args` - The arguments to the command
+pub async fn upload_media_resume(
+    state: &mut Velocity,
+    mut args: Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    let options = transfer_options(&mut args)?;
+
+    let mut journals = Vec::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(journal_dir()).await {
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_some_and(|e| e == "json") {
+                journals.push(entry.path());
+            }
+        }
+    }
+
+    if journals.is_empty() {
+        println!("There are no interrupted uploads");
+        return Ok(());
+    }
+
+    if !state.media_upload_chunked_supported().await? {
+        println!("The hypervisor does not support resuming uploads");
+        return Ok(());
+    }
+
+    // A failed upload must not keep the others from resuming
+    let mut failed = 0;
+    for journal in &journals {
+        if let Err(e) = resume_upload(state, journal, options.clone()).await {
+            println!("ERROR: Resuming '{}' failed: {e}", journal.display());
+            failed += 1;
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!(
+            "{} of {} interrupted uploads could not be resumed",
+            failed,
+            journals.len()
+        )
+        .into()),
+    }
+}
+
+/// Resumes a single interrupted upload
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `journal` - The path to the journal of the upload
+/// * `options` - The options for the transfer
+async fn resume_upload(
+    state: &Velocity,
+    journal: &Path,
+    options: TransferOptions,
+) -> Result<(), Box<dyn Error>> {
+    let record = UploadJournal::load(journal).await?;
+
+    let bar = progress_bar(format!(
+        "Resuming {} in mediapool {}",
+        record.name, record.mpid
+    ));
+    let progress_bar = bar.clone();
+
+    let res = with_ctrl_c(options, |options| {
+        state.media_upload_resume(journal, options, move |progress: &TransferProgress| {
+            update_progress(&progress_bar, progress)
+        })
+    })
+    .await;
+
+    report(&bar, &record.name, record.mpid, res, true)
+}
+
+/// The directory the journals of interrupted uploads are kept in
+pub fn journal_dir() -> PathBuf {
+    home::home_dir()
+        .unwrap_or_else(|| PathBuf::from("./"))
+        .join(".vcmd_uploads")
+}
+
+/// The file name of the journal for an upload. The hypervisor does not allow two
+/// pieces of media with the same name in a pool and group, so this is unique
+/// # Arguments
+/// * `mpid` - The media pool the media is uploaded to
+/// * `gid` - The group the media belongs to
+/// * `name` - The name of the media
+pub fn journal_name(mpid: MPID, gid: GID, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("{}-{}-{}.json", mpid, gid, name)
+}
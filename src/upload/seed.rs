@@ -0,0 +1,138 @@
+use std::{error::Error, io::Cursor, path::PathBuf};
+
+use velocity::{transfer::TransferOptions, *};
+
+use super::{arg, transfer_options};
+use crate::iso::IsoBuilder;
+
+/// The volume label cloud-init looks for to find a NoCloud seed
+const SEED_LABEL: &str = "CIDATA";
+
+/// The suffix of the name of a virtual machine that names its seed media
+const SEED_SUFFIX: &str = "-seed";
+
+/// Builds a NoCloud seed ISO from either a single directory or a list of files
+/// like `user-data`, `meta-data` and `network-config`, which are placed in the root of the image.
+/// A `meta-data` file gets generated if there is none
+/// # Arguments
+/// * `paths` - The directory or the files to pack
+/// * `hostname` - The instance id and hostname for a generated `meta-data`
+pub fn build_seed(paths: &[PathBuf], hostname: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut iso = IsoBuilder::new(SEED_LABEL);
+
+    match paths {
+        [dir] if dir.is_dir() => iso.add_dir(dir, "")?,
+        files => {
+            for file in files {
+                let name = file
+                    .file_name()
+                    .ok_or_else(|| format!("'{}' is not a file", file.display()))?;
+                iso.add_file(&name.to_string_lossy(), std::fs::read(file)?);
+            }
+        }
+    }
+
+    if !iso.contains("user-data") {
+        return Err("A seed needs a 'user-data' file".into());
+    }
+
+    if !iso.contains("meta-data") {
+        iso.add_file(
+            "meta-data",
+            format!("instance-id: {hostname}\nlocal-hostname: {hostname}\n").into_bytes(),
+        );
+    }
+
+    Ok(iso.build())
+}
+
+/// Uploads a seed ISO built by `build_seed()` as read-only media
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `mpid` - The media pool to upload to
+/// * `gid` - The group the media belongs to
+/// * `name` - The name of the new media
+/// * `image` - The seed ISO
+/// * `options` - The options for the transfer
+/// # Returns
+/// The media id of the uploaded seed
+pub async fn upload_seed(
+    state: &Velocity,
+    mpid: MPID,
+    gid: GID,
+    name: &str,
+    image: Vec<u8>,
+    options: TransferOptions,
+) -> Result<MID, Box<dyn Error>> {
+    let size = image.len() as u64;
+
+    let res = state
+        .media_upload(
+            mpid,
+            gid,
+            name,
+            "ISO",
+            true,
+            Cursor::new(image),
+            Some(size),
+            options,
+            (),
+        )
+        .await?;
+
+    Ok(res.mid)
+}
+
+/// Packs a directory or files into a cloud-init seed ISO and uploads it:
+/// `upload seed <mpid> <gid> <name> <dir | user-data [meta-data] [network-config]> [--hostname <hostname>] [--limit <rate>]`.
+/// Without a hostname, the name of the media without the `-seed` suffix `create vm` names seeds with is used
+/// # Arguments
+/// * `state` - The client to upload with
+/// * `args` - The arguments to the command
+pub async fn upload_seed_command(
+    state: &mut Velocity,
+    mut args: Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    let options = transfer_options(&mut args)?;
+    let hostname = match args.iter().position(|a| a == "--hostname") {
+        Some(i) => {
+            let hostname = args
+                .get(i + 1)
+                .ok_or("The '--hostname' flag needs a hostname, e.g. '--hostname web1'")?
+                .clone();
+            args.drain(i..i + 2);
+            Some(hostname)
+        }
+        None => None,
+    };
+    let mpid: String = arg(&args, 0, "mpid")?;
+    let gid: String = arg(&args, 1, "gid")?;
+    let name: String = arg(&args, 2, "name")?;
+    // At least one path is required
+    arg::<PathBuf>(&args, 3, "path")?;
+    let mpid = state.resolve_pool(&mpid).await?;
+    let gid = state.resolve_group(&gid).await?;
+
+    let hostname = hostname.unwrap_or_else(|| seed_hostname(&name).to_owned());
+    let paths: Vec<PathBuf> = args[3..].iter().map(PathBuf::from).collect();
+    let image = build_seed(&paths, &hostname)?;
+    let mid = upload_seed(state, mpid, gid, &name, image, options).await?;
+
+    println!("Uploaded seed ISO '{name}' in pool {mpid}. MID: {mid}");
+
+    Ok(())
+}
+
+/// Returns the name of the seed media for a virtual machine
+/// # Arguments
+/// * `vm_name` - The name of the virtual machine
+pub fn seed_name(vm_name: &str) -> String {
+    format!("{vm_name}{SEED_SUFFIX}")
+}
+
+/// Returns the hostname for a seed, which is the name of the virtual machine it has been named after
+/// # Arguments
+/// * `seed_name` - The name of the seed media
+fn seed_hostname(seed_name: &str) -> &str {
+    seed_name.strip_suffix(SEED_SUFFIX).unwrap_or(seed_name)
+}
@@ -0,0 +1,70 @@
+use clik::*;
+use velocity::{
+    error::{ClientError, VelocityError},
+    transfer, *,
+};
+
+pub fn register_commands(cli: &mut CLI<Velocity>) {
+    let mut verify = verify();
+    verify.add_subcommand(verify_media());
+    cli.add_command(verify);
+}
+
+#[clik_command(media, "Compare a local file with media stored on the hypervisor")]
+#[clik_arg(mid, "The media id or name of the media to compare with")]
+#[clik_arg(path, "The path to the local file")]
+async fn verify_media(state: &mut Velocity, mid: String, path: String) {
+    let mid = state.resolve_media(&mid).await?;
+
+    // Media can only be listed per group, so look through all groups available to us
+    let mut found = None;
+    for group in state.group_list().await? {
+        let media = match state.media_list(group.gid).await {
+            Ok(media) => media,
+            Err(e) if e.is_permission_denied() => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(media) = media.into_iter().find(|m| m.mid == mid) {
+            found = Some(media);
+            break;
+        }
+    }
+
+    let media = match found {
+        Some(media) => media,
+        None => {
+            println!("No media {mid} is available to any of your groups");
+            return Ok(());
+        }
+    };
+
+    let expected = match media.sha256 {
+        Some(sha256) => sha256,
+        None => {
+            println!("The hypervisor has no checksum for media '{}'", media.name);
+            return Ok(());
+        }
+    };
+
+    let file = tokio::fs::File::open(&path).await?;
+    let actual = transfer::sha256(file).await?;
+
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(
+            VelocityError::Client(ClientError::ChecksumMismatch { expected, actual }).into(),
+        );
+    }
+
+    println!(
+        "'{}' matches media '{}' ({} bytes, SHA-256 {})",
+        path, media.name, media.size, actual
+    );
+
+    Ok(())
+}
+
+#[clik_command(verify, "Verify something")]
+async fn verify(state: &mut Velocity) {
+    Ok(())
+}
@@ -4,17 +4,18 @@ mod corevm;
 mod disks;
 mod displays;
 mod nics;
+mod seed;
 
 pub use corevm::*;
-pub use disks::*;
-pub use displays::*;
-pub use nics::*;
+pub use seed::*;
 
 use rustyline::{error::ReadlineError, history::History, Editor, Helper};
-use std::str::FromStr;
+use std::{future::Future, str::FromStr};
+use velocity::error::VelocityError;
 
 /// A simple yes or no answer to a prompt that parses
 /// `y` to Self::YES and `n` to Self::NO
+#[allow(clippy::upper_case_acronyms)]
 pub enum YesNo {
     /// true
     YES,
@@ -34,9 +35,9 @@ impl FromStr for YesNo {
     }
 }
 
-impl Into<bool> for YesNo {
-    fn into(self) -> bool {
-        match self {
+impl From<YesNo> for bool {
+    fn from(value: YesNo) -> Self {
+        match value {
             YesNo::YES => true,
             YesNo::NO => false,
         }
@@ -52,6 +53,20 @@ trait ReadlineExt {
     fn readline_t<T: FromStr>(&mut self, prompt: &str) -> Result<T, ReadlineError>
     where
         <T as FromStr>::Err: std::fmt::Display;
+
+    /// Read in a line and resolve it, e.g. a name to an id using `Velocity::resolve_pool()`,
+    /// repeating the prompt on failure
+    /// # Arguments
+    /// * `prompt` - The prompt to display
+    /// * `resolve` - The function resolving the line
+    async fn readline_resolve<T, F, Fut>(
+        &mut self,
+        prompt: &str,
+        resolve: F,
+    ) -> Result<T, ReadlineError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<T, VelocityError>>;
 }
 
 impl<H: Helper, I: History> ReadlineExt for Editor<H, I> {
@@ -73,4 +88,28 @@ impl<H: Helper, I: History> ReadlineExt for Editor<H, I> {
             }
         }
     }
+
+    async fn readline_resolve<T, F, Fut>(
+        &mut self,
+        prompt: &str,
+        resolve: F,
+    ) -> Result<T, ReadlineError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<T, VelocityError>>,
+    {
+        let mut last = String::new();
+        loop {
+            last = self.readline_with_initial(prompt, (&last, ""))?;
+            match resolve(last.clone()).await {
+                Ok(v) => {
+                    self.add_history_entry(&last)?;
+                    return Ok(v);
+                }
+                Err(e) => {
+                    println!("{}, try again", e);
+                }
+            }
+        }
+    }
 }
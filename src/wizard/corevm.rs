@@ -1,31 +1,38 @@
 use rustyline::{error::ReadlineError, history::History, Editor, Helper};
-use velocity::{endpoints::v::CoreVM, GID};
+use velocity::{
+    endpoints::v::{v_vm_validate::ConfigProblem, CoreVM},
+    Velocity, GID,
+};
 
 use crate::wizard::{
-    disks::wizard_disks, displays::wizard_displays, nics::wizard_nics, ReadlineExt, YesNo,
+    disks::wizard_disks, displays::wizard_displays, nics::wizard_nics, ReadlineExt, SeedConfig,
+    YesNo,
 };
 
-/// Prompt the user to configure common properties for a virtual machine
+/// Prompt the user to configure common properties for a virtual machine,
+/// use `wizard_confirm()` to let the user confirm the result
 /// # Arguments
 /// * `readline` - The readline instance to use
+/// * `state` - The client to resolve names of media and host NICs with
 /// * `gid` - The group id the virtual machine should be part of
 /// * `name` - The name for the virtual machine
-pub fn wizard_corevm<'a, H: Helper, I: History>(
+pub async fn wizard_corevm<H: Helper, I: History>(
     readline: &mut Editor<H, I>,
+    state: &Velocity,
     gid: GID,
-    name: &'a str,
-) -> Result<CoreVM<'a>, ReadlineError> {
+    name: &str,
+) -> Result<CoreVM, ReadlineError> {
     let cpus = readline.readline_t("CPU count > ")?;
     let memory_mib = readline.readline_t("Memory in MiB > ")?;
 
     let displays = wizard_displays(readline)?;
-    let disks = wizard_disks(readline)?;
-    let nics = wizard_nics(readline)?;
+    let disks = wizard_disks(readline, state).await?;
+    let nics = wizard_nics(readline, state).await?;
 
     let autostart: YesNo = readline.readline_t("Autostart (y/n) > ")?;
 
-    let vm = CoreVM {
-        name,
+    Ok(CoreVM {
+        name: name.to_owned(),
         gid,
         cpus,
         memory_mib,
@@ -33,13 +40,41 @@ pub fn wizard_corevm<'a, H: Helper, I: History>(
         disks,
         nics,
         autostart: autostart.into(),
-    };
+    })
+}
 
+/// Show a virtual machine configuration along with the problems found in it
+/// and prompt the user to confirm it
+/// # Arguments
+/// * `readline` - The readline instance to use
+/// * `vm` - The configuration to confirm
+/// * `seed` - The seed ISO that is going to be attached, if any
+/// * `problems` - The problems validating the configuration has found
+pub fn wizard_confirm<H: Helper, I: History>(
+    readline: &mut Editor<H, I>,
+    vm: &CoreVM,
+    seed: Option<&SeedConfig>,
+    problems: &[ConfigProblem],
+) -> Result<(), ReadlineError> {
     println!("VM configuration: {:#?}", vm);
-    let ok: YesNo = readline.readline_t("Confirm config (y/n) > ")?;
+    if let Some(seed) = seed {
+        println!("Seed ISO to upload and attach: {:#?}", seed);
+    }
+
+    let prompt = if problems.is_empty() {
+        "Confirm config (y/n) > "
+    } else {
+        println!("The configuration has {} problem(s):", problems.len());
+        for problem in problems {
+            println!("  - {problem}");
+        }
+        "Confirm config anyway (y/n) > "
+    };
+
+    let ok: YesNo = readline.readline_t(prompt)?;
 
     match ok {
-        YesNo::YES => Ok(vm),
+        YesNo::YES => Ok(()),
         YesNo::NO => Err(ReadlineError::Interrupted),
     }
 }
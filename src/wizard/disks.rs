@@ -1,13 +1,15 @@
 use rustyline::{error::ReadlineError, history::History, Editor, Helper};
-use velocity::endpoints::v::DiskConfig;
+use velocity::{endpoints::v::DiskConfig, Velocity};
 
 use super::{ReadlineExt, YesNo};
 
 /// Prompt the user to configure disks for a virtual machine
 /// # Arguments
 /// * `readline` - The readline instance to use
-pub fn wizard_disks<'a, H: Helper, I: History>(
+/// * `state` - The client to resolve media names with
+pub async fn wizard_disks<H: Helper, I: History>(
     readline: &mut Editor<H, I>,
+    state: &Velocity,
 ) -> Result<Vec<DiskConfig>, ReadlineError> {
     let mut res = Vec::new();
 
@@ -15,7 +17,11 @@ pub fn wizard_disks<'a, H: Helper, I: History>(
         match readline.readline_t::<YesNo>("Add another disk? (y/n) > ")? {
             YesNo::NO => break,
             YesNo::YES => res.push(DiskConfig {
-                mid: readline.readline_t("MID > ")?,
+                mid: readline
+                    .readline_resolve("Media id or name > ", |media| async move {
+                        state.resolve_media(&media).await
+                    })
+                    .await?,
                 mode: readline.readline_t("Disk mode (USB/BLOCK/VIRTIO) > ")?,
                 readonly: readline.readline_t::<YesNo>("Readonly (y/n) > ")?.into(),
             }),
@@ -6,7 +6,7 @@ use super::{ReadlineExt, YesNo};
 /// Prompt the user to configure displays for a virtual machine
 /// # Arguments
 /// * `readline` - The readline instance to use
-pub fn wizard_displays<'a, H: Helper, I: History>(
+pub fn wizard_displays<H: Helper, I: History>(
     readline: &mut Editor<H, I>,
 ) -> Result<Vec<DisplayConfig>, ReadlineError> {
     let mut res = Vec::new();
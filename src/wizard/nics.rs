@@ -1,7 +1,7 @@
 use rustyline::{error::ReadlineError, history::History, Editor, Helper};
 use velocity::{
     endpoints::v::{NICConfig, NICType},
-    NICID,
+    Velocity,
 };
 
 use super::{ReadlineExt, YesNo};
@@ -9,8 +9,10 @@ use super::{ReadlineExt, YesNo};
 /// Prompt the user to configure NICs for a virtual machine
 /// # Arguments
 /// * `readline` - The readline instance to use
-pub fn wizard_nics<'a, H: Helper, I: History>(
+/// * `state` - The client to resolve host NIC names with
+pub async fn wizard_nics<H: Helper, I: History>(
     readline: &mut Editor<H, I>,
+    state: &Velocity,
 ) -> Result<Vec<NICConfig>, ReadlineError> {
     let mut res = Vec::new();
 
@@ -20,9 +22,13 @@ pub fn wizard_nics<'a, H: Helper, I: History>(
             YesNo::YES => {
                 let ty: NICType = readline.readline_t("NIC type (NAT/BRIDGE) > ")?;
                 match ty {
-                    NICType::NAT => res.push(NICConfig { ty: ty, host: None }),
+                    NICType::NAT => res.push(NICConfig { ty, host: None }),
                     NICType::BRIDGE => {
-                        let host: NICID = readline.readline_t("Host NIC id > ")?;
+                        let host = readline
+                            .readline_resolve("Host NIC id or name > ", |nic| async move {
+                                state.resolve_nic(&nic).await
+                            })
+                            .await?;
                         res.push(NICConfig {
                             ty,
                             host: Some(host),
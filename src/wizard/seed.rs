@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use rustyline::{error::ReadlineError, history::History, Editor, Helper};
+use velocity::{endpoints::v::DiskMode, Velocity, MPID};
+
+use super::{ReadlineExt, YesNo};
+use crate::upload::seed::seed_name;
+
+/// A cloud-init seed ISO to build, upload and attach to a new virtual machine
+#[derive(Debug)]
+pub struct SeedConfig {
+    /// The media pool to upload the seed to
+    pub mpid: MPID,
+    /// The name of the seed media
+    pub name: String,
+    /// The directory or the files to pack
+    pub paths: Vec<PathBuf>,
+    /// The mode to attach the seed with
+    pub mode: DiskMode,
+}
+
+/// Prompt the user if a cloud-init seed ISO should be attached to a virtual machine
+/// # Arguments
+/// * `readline` - The readline instance to use
+/// * `state` - The client to resolve media pool names with
+/// * `vm_name` - The name of the virtual machine, used to name the seed
+pub async fn wizard_seed<H: Helper, I: History>(
+    readline: &mut Editor<H, I>,
+    state: &Velocity,
+    vm_name: &str,
+) -> Result<Option<SeedConfig>, ReadlineError> {
+    if let YesNo::NO = readline.readline_t("Attach a cloud-init seed ISO? (y/n) > ")? {
+        return Ok(None);
+    }
+
+    let paths = readline.readline("Directory or user-data [meta-data] [network-config] > ")?;
+
+    Ok(Some(SeedConfig {
+        paths: paths.split_whitespace().map(PathBuf::from).collect(),
+        mpid: readline
+            .readline_resolve("Media pool id or name for the seed > ", |pool| async move {
+                state.resolve_pool(&pool).await
+            })
+            .await?,
+        name: seed_name(vm_name),
+        mode: readline.readline_t("Disk mode (USB/BLOCK/VIRTIO) > ")?,
+    }))
+}
@@ -0,0 +1,22 @@
+//! Serves the mock hypervisor seeded with the `basic` test fixture until interrupted.
+//! The browser tests in `tests/web.rs` run against it, `tests/web.sh` does the following:
+//! ```sh
+//! cargo run --example mock_hypervisor --features mock
+//! VELOCITY_MOCK_URL=<printed url> wasm-pack test --headless --firefox -- --no-default-features --features wasm
+//! ```
+
+use velocity::mock::{Fixture, MockHypervisor};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let fixture = Fixture::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/basic.json"
+    ))?;
+    let mock = MockHypervisor::start(fixture).await?;
+
+    println!("{}", mock.url());
+    std::future::pending::<()>().await;
+
+    Ok(())
+}
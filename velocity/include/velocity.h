@@ -0,0 +1,190 @@
+/* The C API of the velocity client, generated by cbindgen - do not edit */
+
+#ifndef VELOCITY_H
+#define VELOCITY_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+// The outcome of a call, every kind of `VelocityError` has its own status
+typedef enum VelocityStatus {
+  // The call succeeded
+  VELOCITY_STATUS_OK = 0,
+  // An argument is a null pointer, not valid UTF-8 or otherwise malformed
+  VELOCITY_STATUS_INVALID_ARGUMENT = 1,
+  // The request is malformed or contains invalid values
+  VELOCITY_STATUS_BAD_REQUEST = 2,
+  // The authkey is invalid or has expired, wrong credentials are `PermissionDenied`
+  VELOCITY_STATUS_INVALID_AUTHKEY = 3,
+  // The user is missing a permission required for this action or the credentials are wrong
+  VELOCITY_STATUS_PERMISSION_DENIED = 4,
+  // The requested resource does not exist
+  VELOCITY_STATUS_NOT_FOUND = 5,
+  // The resource already exists or is still in use
+  VELOCITY_STATUS_CONFLICT = 6,
+  // The action would exceed a quota or the available space
+  VELOCITY_STATUS_QUOTA_EXCEEDED = 7,
+  // The hypervisor failed to fulfill a valid request
+  VELOCITY_STATUS_INTERNAL = 8,
+  // The client has no authkey, e.g. after `velocity_logout()`
+  VELOCITY_STATUS_NOT_AUTHENTICATED = 9,
+  // The transfer has been cancelled
+  VELOCITY_STATUS_CANCELLED = 10,
+  // The transferred data has been corrupted
+  VELOCITY_STATUS_CHECKSUM_MISMATCH = 11,
+  // The hypervisor could not be reached or a local file could not be read
+  VELOCITY_STATUS_TRANSPORT = 12,
+  // An error that does not fit any of the other categories
+  VELOCITY_STATUS_OTHER = 13,
+  // The library panicked, this is a bug
+  VELOCITY_STATUS_PANIC = 14,
+} VelocityStatus;
+
+// An authenticated client, created by `velocity_login()` and released by `velocity_client_free()`
+typedef struct VelocityClient VelocityClient;
+
+// The id of a group
+typedef int64_t GID;
+
+// The id of a media pool
+typedef int64_t MPID;
+
+// Receives progress updates of an upload: the bytes uploaded until now, the total amount
+// of bytes and the `user_data` passed along with the callback. It is called on the
+// thread that started the upload
+typedef void (*VelocityProgressCallback)(uint64_t done,
+                                         uint64_t total,
+                                         void *user_data);
+
+// The id of a virtual machine
+typedef int64_t VMID;
+
+// Authenticates to a hypervisor and creates a new client. Wrong credentials fail with
+// `VELOCITY_STATUS_PERMISSION_DENIED`
+// # Arguments
+// * `base_url` - The base url to route all requests to
+// * `username` - The username needed for authentication
+// * `password` - The password needed for authentication
+// * `client` - Receives the new client
+// # Safety
+// All strings have to be valid, null terminated strings and `client` has to point to writable memory
+enum VelocityStatus velocity_login(const char *base_url,
+                                   const char *username,
+                                   const char *password,
+                                   struct VelocityClient **client);
+
+// Invalidates the authkey of a client, the client can't be used for requests afterwards
+// # Arguments
+// * `client` - The client to log out
+// # Safety
+// `client` has to be a client created by `velocity_login()` that has not been freed
+enum VelocityStatus velocity_logout(const struct VelocityClient *client);
+
+// Releases a client, passing a null pointer does nothing. This does not log out,
+// call `velocity_logout()` first
+// # Arguments
+// * `client` - The client to release
+// # Safety
+// `client` has to be a client created by `velocity_login()` that has not been freed
+void velocity_client_free(struct VelocityClient *client);
+
+// Returns the message of the last error on the calling thread, or null if the last
+// call succeeded. The message is valid until the next call on this thread
+const char *velocity_last_error(void);
+
+// Releases a string returned by this library, passing a null pointer does nothing
+// # Arguments
+// * `string` - The string to release
+// # Safety
+// `string` has to be a string returned by this library that has not been freed
+void velocity_string_free(char *string);
+
+// Lists all users as a JSON array of `{"uid", "name"}` objects
+// # Arguments
+// * `client` - The client to list with
+// * `json` - Receives the list, release it with `velocity_string_free()`
+// # Safety
+// `client` has to be a valid client and `json` has to point to writable memory
+enum VelocityStatus velocity_user_list(const struct VelocityClient *client,
+                                       char **json);
+
+// Lists all groups the user has permissions on as a JSON array of `{"gid", "name", "parent_gid"}` objects
+// # Arguments
+// * `client` - The client to list with
+// * `json` - Receives the list, release it with `velocity_string_free()`
+// # Safety
+// `client` has to be a valid client and `json` has to point to writable memory
+enum VelocityStatus velocity_group_list(const struct VelocityClient *client,
+                                        char **json);
+
+// Lists all media pools assigned to a group as a JSON array
+// # Arguments
+// * `client` - The client to list with
+// * `gid` - The group to list the pools of
+// * `json` - Receives the list, release it with `velocity_string_free()`
+// # Safety
+// `client` has to be a valid client and `json` has to point to writable memory
+enum VelocityStatus velocity_pool_list(const struct VelocityClient *client,
+                                       GID gid,
+                                       char **json);
+
+// Lists all media a group can access as a JSON array
+// # Arguments
+// * `client` - The client to list with
+// * `gid` - The group to list the media of
+// * `json` - Receives the list, release it with `velocity_string_free()`
+// # Safety
+// `client` has to be a valid client and `json` has to point to writable memory
+enum VelocityStatus velocity_media_list(const struct VelocityClient *client,
+                                        GID gid,
+                                        char **json);
+
+// Lists all host NICs available for bridging as a JSON array
+// # Arguments
+// * `client` - The client to list with
+// * `json` - Receives the list, release it with `velocity_string_free()`
+// # Safety
+// `client` has to be a valid client and `json` has to point to writable memory
+enum VelocityStatus velocity_nic_list(const struct VelocityClient *client,
+                                      char **json);
+
+// Uploads a local file as new media, the file is hashed first so the hypervisor can verify it
+// # Arguments
+// * `client` - The client to upload with
+// * `mpid` - The media pool to upload to
+// * `gid` - The group the media belongs to
+// * `name` - The name of the new media
+// * `ty` - The type of the new media, e.g. `DISK` or `ISO`
+// * `readonly` - If the media should be read-only
+// * `path` - The path to the file to upload
+// * `progress` - Receives progress updates, may be null
+// * `user_data` - Passed to `progress` as is
+// * `mid` - Receives the media id of the new media, release it with `velocity_string_free()`
+// # Safety
+// `client` has to be a valid client, all strings have to be valid, null terminated strings
+// and `mid` has to point to writable memory
+enum VelocityStatus velocity_media_upload(const struct VelocityClient *client,
+                                          MPID mpid,
+                                          GID gid,
+                                          const char *name,
+                                          const char *ty,
+                                          bool readonly,
+                                          const char *path,
+                                          VelocityProgressCallback progress,
+                                          void *user_data,
+                                          char **mid);
+
+// Creates a new EFI virtual machine from a JSON configuration like
+// `{"name", "gid", "cpus", "memory_mib", "displays", "disks", "nics", "autostart", "rosetta"}`
+// # Arguments
+// * `client` - The client to create the virtual machine with
+// * `config` - The configuration as JSON
+// * `vmid` - Receives the id of the new virtual machine
+// # Safety
+// `client` has to be a valid client, `config` a valid, null terminated string
+// and `vmid` has to point to writable memory
+enum VelocityStatus velocity_vm_efi_create(const struct VelocityClient *client,
+                                           const char *config,
+                                           VMID *vmid);
+
+#endif  /* VELOCITY_H */
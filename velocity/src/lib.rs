@@ -1,69 +1,81 @@
 //! VCMD is an API wrapper for the [Velocity](https://github.com/VelocityVMM/Velocity) hypervisor API. This crate allows easy access and bundling to all those endpoints and wraps them up nicely in pure Rust.
 //! # Example
-//! ```
-//! use vcmd::Velocity;
+//! ```no_run
+//! use velocity::Velocity;
 //!
+//! # async fn example() -> Result<(), velocity::error::VelocityError> {
 //! // Create a new Velocity instance, this will later be used to access other endpoints.
 //! // We immediately authenticate to get a valid connection to the hypervisor.
-//! let mut velocity = Velocity::new("http://localhost:8090", "root", "root").unwrap();
+//! let velocity = Velocity::new("http://localhost:8090", "root", "root").await?;
 //!
 //! // Reauthenticate this instance. This has to be done every once in a while to not loose access
-//! velocity.reauthenticate().unwrap();
+//! velocity.reauthenticate().await?;
 //!
 //! // We're done here, so let's deauthenticate and say goodbye to the hypervisor
-//! velocity.deauthenticate().unwrap();
+//! velocity.deauthenticate().await?;
+//! # Ok(())
+//! # }
 //! ```
+//! # Features
+//! * `native` (default) - Uploads from the file system, bandwidth limits and disk image conversion
+//! * `blocking` - A blocking client that runs on an internal runtime, see `blocking::Velocity`
+//! * `ffi` - A C API on top of the blocking client, the header is `include/velocity.h`
+//! * `wasm` - JavaScript bindings and uploads of browser `File`s and `Blob`s,
+//!   build with `--no-default-features --features wasm` for `wasm32-unknown-unknown`
+//! * `mock` - An in-process mock of the hypervisor for testing
+//! * `experimental-chunked` - Chunked, resumable uploads. The Velocity API does not define
+//!   the endpoints these use yet, only the mock hypervisor serves them
+
+#[cfg(all(feature = "native", target_arch = "wasm32"))]
+compile_error!(
+    "The `native` feature is not available on wasm32, build with `--no-default-features --features wasm`"
+);
 
 mod velocity;
 use error::*;
-pub use velocity::{authkey::*, *};
+pub use velocity::{authkey::*, id::*, *};
 
-use wasm_bindgen::prelude::wasm_bindgen;
+use std::sync::Arc;
+#[cfg(feature = "native")]
+use transfer::BandwidthLimit;
+use transport::{ReqwestTransport, Transport};
 
-#[allow(clippy::upper_case_acronyms)]
-pub type UID = i64;
-#[allow(clippy::upper_case_acronyms)]
-pub type GID = i64;
-#[allow(clippy::upper_case_acronyms)]
-pub type PID = i64;
-#[allow(clippy::upper_case_acronyms)]
-pub type MPID = i64;
-#[allow(clippy::upper_case_acronyms)]
-pub type MID = String;
-#[allow(clippy::upper_case_acronyms)]
-pub type NICID = i64;
-#[allow(clippy::upper_case_acronyms)]
-pub type VMID = i64;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::wasm_bindgen;
 
 /// This struct is the main workhorse of this API client, all requests and functions go through
-/// this struct and its methods
-#[derive(Debug)]
-#[wasm_bindgen]
+/// this struct and its methods.
+///
+/// Cloning a `Velocity` is cheap: all clones share the same transport and authentication
+/// state, so a key renewed through one clone is immediately used by all others.
+///
+/// Dropping the last clone does not deauthenticate, call `deauthenticate()` when done.
+/// Otherwise the authkey stays valid until it expires
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Velocity {
-    base_url: String,
-    http_client: reqwest::Client,
-    authkey: Option<Authkey>,
+    base_url: Arc<str>,
+    transport: Arc<dyn Transport>,
+    auth: Arc<AuthState>,
+    #[cfg(feature = "native")]
+    transfer_limit: BandwidthLimit,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[allow(deprecated)]
 impl Velocity {
     /// Creates a new and authenticated `Velocity` instance. If the authentication fails, this will error out
     /// # Arguments
     /// * `base_url` - The base url to route all requests to
     /// * `username` - The username needed for authentication
     /// * `password` - The passwrod needed for authentication
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub async fn new(
         base_url: &str,
         username: &str,
         password: &str,
     ) -> Result<Velocity, VelocityError> {
-        let http_client = reqwest::Client::new();
-        let mut v = Velocity {
-            base_url: base_url.to_owned(),
-            http_client,
-            authkey: Default::default(),
-        };
+        let v = Velocity::with_transport(base_url, Arc::new(ReqwestTransport::default()));
 
         v.authenticate(username, password).await?;
 
@@ -71,13 +83,26 @@ impl Velocity {
     }
 }
 
-impl Drop for Velocity {
-    /// Tries to deauthenticate this client before dropping it
-    fn drop(&mut self) {
-        if self.authkey.is_some() {
-            // Try to deauthenticate, if this fails we don't care since the key
-            // will eventually expire and the hypervisor will catch that
-            drop(self.deauthenticate());
+impl Velocity {
+    /// Creates a new, unauthenticated `Velocity` instance that delivers all requests
+    /// through the supplied transport
+    /// # Arguments
+    /// * `base_url` - The base url to route all requests to
+    /// * `transport` - The transport to deliver requests with
+    pub fn with_transport(base_url: &str, transport: Arc<dyn Transport>) -> Velocity {
+        Velocity {
+            base_url: base_url.into(),
+            transport,
+            auth: Default::default(),
+            #[cfg(feature = "native")]
+            transfer_limit: Default::default(),
         }
     }
+
+    /// Returns the default bandwidth limit for transfers that do not set their own.
+    /// It is shared by all clones, changing it affects running transfers as well
+    #[cfg(feature = "native")]
+    pub fn transfer_limit(&self) -> &BandwidthLimit {
+        &self.transfer_limit
+    }
 }
@@ -1,4 +1,21 @@
 pub mod authkey;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod endpoints;
 pub mod error;
+// The unit tests link this crate a second time through the dev-dependency,
+// which would export every C symbol twice
+#[cfg(all(feature = "ffi", not(test)))]
+pub mod ffi;
+pub mod id;
+#[cfg(feature = "native")]
+pub mod image;
+#[cfg(feature = "wasm")]
+mod js;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod redact;
 mod reqwest;
+mod resolve;
+pub mod transfer;
+pub mod transport;
@@ -1,20 +1,25 @@
 //! Authkey definition and utility implementations
 use std::{
-    ops::Add,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    ops::{Add, Deref},
+    sync::RwLock,
+    time::Duration,
 };
 
+use tokio::sync::{RwLock as AsyncRwLock, RwLockReadGuard, RwLockWriteGuard};
+use web_time::{SystemTime, UNIX_EPOCH};
+
 use crate::{
     error::{ClientError, VelocityError},
     Velocity,
 };
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::wasm_bindgen;
 
 /// An authkey has a key value that authenticates and an expiration datetime
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 #[allow(dead_code)]
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Authkey {
     /// The key string to authenticate to Velocity
     key: String,
@@ -22,6 +27,16 @@ pub struct Authkey {
     expires: SystemTime,
 }
 
+impl std::fmt::Debug for Authkey {
+    /// Never reveals the key itself, so an `Authkey` can't leak into logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Authkey")
+            .field("key", &"<redacted>")
+            .field("expires", &self.expires)
+            .finish()
+    }
+}
+
 impl Authkey {
     /// Creates a new authkey
     /// # Arguments
@@ -45,14 +60,73 @@ impl Authkey {
     }
 }
 
-#[wasm_bindgen]
+/// The authentication state shared by all clones of a `Velocity` instance
+#[derive(Debug, Default)]
+pub(crate) struct AuthState {
+    /// Held shared by every authenticated request and exclusively while the key changes
+    gate: AsyncRwLock<()>,
+    /// The current authkey
+    key: RwLock<Option<Authkey>>,
+}
+
+impl AuthState {
+    /// Locks the state exclusively, waiting for all requests to finish
+    /// and blocking new ones until the returned guard is dropped
+    pub(crate) async fn lock(&self) -> RwLockWriteGuard<'_, ()> {
+        self.gate.write().await
+    }
+
+    /// Returns the current authkey
+    pub(crate) fn get(&self) -> Option<Authkey> {
+        self.key.read().expect("Read authkey").clone()
+    }
+
+    /// Replaces the current authkey
+    /// # Arguments
+    /// * `key` - The new authkey, or `None` to drop it
+    pub(crate) fn set(&self, key: Option<Authkey>) {
+        *self.key.write().expect("Write authkey") = key;
+    }
+}
+
+/// An authkey that is guaranteed to stay valid on the client side while the guard is held:
+/// Reauthentication waits for all guards to be dropped before replacing the key
+pub struct AuthkeyGuard<'a> {
+    key: Authkey,
+    _guard: RwLockReadGuard<'a, ()>,
+}
+
+impl Deref for AuthkeyGuard<'_> {
+    type Target = Authkey;
+
+    fn deref(&self) -> &Self::Target {
+        &self.key
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Velocity {
     /// Tries to retrieve the authkey from this instance. If it doesn't exist, this will error
     /// with a `ClientError::NotAuthenticated`
     pub fn get_authkey(&self) -> Result<Authkey, VelocityError> {
-        match &self.authkey {
-            Some(key) => Ok(key.clone()),
+        match self.auth.get() {
+            Some(key) => Ok(key),
             None => Err(VelocityError::Client(ClientError::NotAuthenticated)),
         }
     }
 }
+
+impl Velocity {
+    /// Retrieves the authkey for a request, waiting for a reauthentication in progress
+    /// to finish. The key won't be replaced until the returned guard is dropped, so
+    /// hold it until the request has been answered. If there is no authkey, this will
+    /// error with a `ClientError::NotAuthenticated`
+    pub async fn authkey(&self) -> Result<AuthkeyGuard<'_>, VelocityError> {
+        let guard = self.auth.gate.read().await;
+
+        Ok(AuthkeyGuard {
+            key: self.get_authkey()?,
+            _guard: guard,
+        })
+    }
+}
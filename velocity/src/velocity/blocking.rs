@@ -0,0 +1,519 @@
+//! A blocking client for tools that don't want to set up an async runtime themselves
+//!
+//! `blocking::Velocity` mirrors the async `Velocity`, but every method blocks until the
+//! request is done. The requests run on an internal runtime, so this client must not be
+//! used from within an async runtime, it panics there like `reqwest::blocking` does:
+//! ```no_run
+//! use velocity::blocking::Velocity;
+//!
+//! # fn example() -> Result<(), velocity::error::VelocityError> {
+//! let velocity = Velocity::new("http://localhost:8090", "root", "root")?;
+//!
+//! for user in velocity.user_list()? {
+//!     println!("{}: {}", user.uid, user.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    future::Future,
+    io::{self, Read},
+    path::Path,
+    sync::Arc,
+    thread,
+};
+
+use bytes::Bytes;
+use tokio::{io::AsyncRead, runtime::Runtime, sync::mpsc};
+use tokio_util::io::StreamReader;
+
+use crate::{
+    endpoints::{
+        m::*,
+        u::*,
+        v::{v_vm_efi::EFIVMConfig, v_vm_validate::ConfigProblem, *},
+    },
+    error::VelocityError,
+    transfer::{BandwidthLimit, ProgressHandler, TransferOptions},
+    transport::Transport,
+    Authkey, GID, MID, MPID, NICID, UID, VMID,
+};
+
+/// The amount of bytes read at once from readers passed to `media_upload()`
+const READ_SIZE: usize = 64 * 1024;
+
+/// A blocking client for the Velocity API, see the async `Velocity` for the details.
+///
+/// Cloning it is cheap: all clones share the same internal runtime and, like the
+/// async `Velocity`, the same transport and authentication state
+#[derive(Debug, Clone)]
+pub struct Velocity {
+    inner: crate::Velocity,
+    runtime: Arc<Runtime>,
+}
+
+impl Velocity {
+    /// Creates a new and authenticated `Velocity` instance. If the authentication fails, this will error out
+    /// # Arguments
+    /// * `base_url` - The base url to route all requests to
+    /// * `username` - The username needed for authentication
+    /// * `password` - The password needed for authentication
+    pub fn new(base_url: &str, username: &str, password: &str) -> Result<Velocity, VelocityError> {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(crate::Velocity::new(base_url, username, password))?;
+
+        Ok(Velocity {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Creates a new, unauthenticated `Velocity` instance that delivers all requests
+    /// through the supplied transport
+    /// # Arguments
+    /// * `base_url` - The base url to route all requests to
+    /// * `transport` - The transport to deliver requests with
+    pub fn with_transport(
+        base_url: &str,
+        transport: Arc<dyn Transport>,
+    ) -> Result<Velocity, VelocityError> {
+        Ok(Velocity {
+            inner: crate::Velocity::with_transport(base_url, transport),
+            runtime: Arc::new(runtime()?),
+        })
+    }
+
+    /// Returns the async client this one wraps, it shares the authentication state with this one
+    pub fn as_async(&self) -> &crate::Velocity {
+        &self.inner
+    }
+
+    /// Returns the default bandwidth limit for transfers that do not set their own.
+    /// It is shared by all clones, changing it affects running transfers as well
+    pub fn transfer_limit(&self) -> &BandwidthLimit {
+        self.inner.transfer_limit()
+    }
+
+    /// Tries to retrieve the authkey from this instance. If it doesn't exist, this will error
+    /// with a `ClientError::NotAuthenticated`
+    pub fn get_authkey(&self) -> Result<Authkey, VelocityError> {
+        self.inner.get_authkey()
+    }
+
+    /// Authenticate this Velocity instance
+    /// # Arguments
+    /// * `username` - The username for the user
+    /// * `password` - The password for the user
+    /// # Returns
+    /// The key on success
+    pub fn authenticate(&self, username: &str, password: &str) -> Result<Authkey, VelocityError> {
+        self.block_on(self.inner.authenticate(username, password))
+    }
+
+    /// Reauthenticates the current authkey
+    /// # Returns
+    /// The key on success
+    pub fn reauthenticate(&self) -> Result<Authkey, VelocityError> {
+        self.block_on(self.inner.reauthenticate())
+    }
+
+    /// Deauthenticates and drops the internal authkey
+    pub fn deauthenticate(&self) -> Result<(), VelocityError> {
+        self.block_on(self.inner.deauthenticate())
+    }
+
+    /// Creates a new user using the provided credentials
+    /// # Arguments
+    /// * `username` - The unique username to use for the newly created user
+    /// * `password` - The password to use for the newly created user
+    /// # Return
+    /// The `uid` of the new user
+    pub fn user_create(&self, username: &str, password: &str) -> Result<UID, VelocityError> {
+        self.block_on(self.inner.user_create(username, password))
+    }
+
+    /// Removes a user with the supplied user id
+    /// # Arguments
+    /// * `uid` - The `uid` of the user to remove
+    pub fn user_remove(&self, uid: UID) -> Result<(), VelocityError> {
+        self.block_on(self.inner.user_remove(uid))
+    }
+
+    /// Retrieves user information about the user
+    /// # Arguments
+    /// * `uid` - The `uid`, or None for the user that is authenticated by the current authkey
+    pub fn user_info(&self, uid: Option<UID>) -> Result<UserInfo, VelocityError> {
+        self.block_on(self.inner.user_info(uid))
+    }
+
+    /// Lists all users on a velocity instance
+    pub fn user_list(&self) -> Result<Vec<UUserListPOSTRes>, VelocityError> {
+        self.block_on(self.inner.user_list())
+    }
+
+    /// Adds a new permission to a user on a group
+    /// # Arguments
+    /// * `gid` - The group to permit on
+    /// * `uid` - The user to permit
+    /// * `permission` - The permission string to permit
+    pub fn user_add_permission(
+        &self,
+        gid: GID,
+        uid: UID,
+        permission: &str,
+    ) -> Result<(), VelocityError> {
+        self.block_on(self.inner.user_add_permission(gid, uid, permission))
+    }
+
+    /// Revoke a permission of a user on a group
+    /// # Arguments
+    /// * `gid` - The group to revoke from
+    /// * `uid` - The user to revoke the permission from
+    /// * `permission` - The permission string to revoke
+    pub fn user_revoke_permission(
+        &self,
+        gid: GID,
+        uid: UID,
+        permission: &str,
+    ) -> Result<(), VelocityError> {
+        self.block_on(self.inner.user_revoke_permission(gid, uid, permission))
+    }
+
+    /// Provides group information
+    /// # Arguments
+    /// * `gid` - The group id of the group to inform about
+    pub fn group_info(&self, gid: GID) -> Result<GroupInfo, VelocityError> {
+        self.block_on(self.inner.group_info(gid))
+    }
+
+    /// Creates a new group within the Velocity system
+    /// # Arguments
+    /// * `parent_gid` - The `gid` of the parent group this new group should be a part of
+    /// * `name` - A unique name for the new group within the parent group
+    pub fn group_create(&self, parent_gid: GID, name: &str) -> Result<UGroupPUTRes, VelocityError> {
+        self.block_on(self.inner.group_create(parent_gid, name))
+    }
+
+    /// Removes a group from the Velocity system
+    /// # Arguments
+    /// * `gid` - The `gid` of the group to remove
+    pub fn group_remove(&self, gid: GID) -> Result<(), VelocityError> {
+        self.block_on(self.inner.group_remove(gid))
+    }
+
+    /// List all groups visible to the current user
+    pub fn group_list(&self) -> Result<Vec<UGroupListPOSTRes>, VelocityError> {
+        self.block_on(self.inner.group_list())
+    }
+
+    /// List all pools assigned to the group with the provided `gid`
+    ///
+    /// To retrieve a list of all pools available, run this command against the
+    /// `root` group (GID=0)
+    /// # Arguments
+    /// * `gid` - The group id of the group to look up
+    pub fn pool_list(&self, gid: GID) -> Result<Vec<MPoolListPOSTRes>, VelocityError> {
+        self.block_on(self.inner.pool_list(gid))
+    }
+
+    /// Assign a pool to a group
+    /// # Arguments
+    /// * `gid` - The group id of the group to assign to
+    /// * `mpid` - The mediapool id of the pool to assign
+    /// * `quota` - The quota in bytes for the assignment
+    /// * `write` - If the group can write to pool media
+    /// * `manage` - If the group can create, delete media and manage the pool
+    pub fn pool_assign(
+        &self,
+        gid: GID,
+        mpid: MPID,
+        quota: u64,
+        write: bool,
+        manage: bool,
+    ) -> Result<(), VelocityError> {
+        self.block_on(self.inner.pool_assign(gid, mpid, quota, write, manage))
+    }
+
+    /// Revoke all permissions of a group on a mediapool
+    /// # Arguments
+    /// * `gid` - The group id of the group to revoke from
+    /// * `mpid` - The mediapool id of the pool to revoke
+    pub fn pool_revoke(&self, gid: GID, mpid: MPID) -> Result<(), VelocityError> {
+        self.block_on(self.inner.pool_revoke(gid, mpid))
+    }
+
+    /// List all available media for a group
+    /// # Arguments
+    /// * `gid` - The group id of the group to list of
+    pub fn media_list(&self, gid: GID) -> Result<Vec<MMediaListPOSTRes>, VelocityError> {
+        self.block_on(self.inner.media_list(gid))
+    }
+
+    /// Allocate new media on the hypervisor
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `size` - The size in bytes for the new media
+    pub fn media_allocate(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        size: u64,
+    ) -> Result<MMediaCreatePUTRes, VelocityError> {
+        self.block_on(self.inner.media_allocate(mpid, gid, name, ty, size))
+    }
+
+    /// Upload data to the hypervisor as a new piece of media. The reader is read on a
+    /// separate thread. If the length of the data is unknown, e.g. when reading from a pipe,
+    /// the data is sent using chunked transfer encoding
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `readonly` - If the file should be read-only
+    /// * `reader` - The reader providing the data to upload
+    /// * `size` - The length of the data, if known
+    /// * `options` - Options for the transfer, such as a token to cancel it
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[allow(clippy::too_many_arguments)]
+    pub fn media_upload<R, P>(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        readonly: bool,
+        reader: R,
+        size: Option<u64>,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        R: Read + Send + 'static,
+        P: ProgressHandler + 'static,
+    {
+        self.block_on(self.inner.media_upload(
+            mpid,
+            gid,
+            name,
+            ty,
+            readonly,
+            async_reader(reader),
+            size,
+            options,
+            progress,
+        ))
+    }
+
+    /// Upload a file to the hypervisor as a new piece of media. The file is hashed
+    /// before it is sent, so the hypervisor can reject data that got corrupted on the way
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `readonly` - If the file should be read-only
+    /// * `source` - The path to the file to upload
+    /// * `options` - Options for the transfer, such as a token to cancel it
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[allow(clippy::too_many_arguments)]
+    pub fn media_upload_file<P>(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        readonly: bool,
+        source: &Path,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler + 'static,
+    {
+        self.block_on(
+            self.inner
+                .media_upload_file(mpid, gid, name, ty, readonly, source, options, progress),
+        )
+    }
+
+    /// Upload a file to the hypervisor in chunks. The progress is recorded in a journal file,
+    /// which allows `media_upload_resume()` to continue the upload after an interruption.
+    /// Chunks that fail due to connection problems are retried according to `options`.
+    /// Once the upload completes, the journal gets removed
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `readonly` - If the file should be read-only
+    /// * `source` - The path to the file to upload
+    /// * `journal` - The path to the journal file to record the progress in
+    /// * `options` - Options for the transfer, such as the chunk size
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[cfg(feature = "experimental-chunked")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn media_upload_chunked<P>(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        readonly: bool,
+        source: &Path,
+        journal: &Path,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler,
+    {
+        self.block_on(self.inner.media_upload_chunked(
+            mpid, gid, name, ty, readonly, source, journal, options, progress,
+        ))
+    }
+
+    /// Resume a chunked upload that has been interrupted. The hypervisor is asked
+    /// for the last acknowledged offset and the upload continues from there
+    /// # Arguments
+    /// * `journal` - The path to the journal file of the upload
+    /// * `options` - Options for the transfer, such as the chunk size
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[cfg(feature = "experimental-chunked")]
+    pub fn media_upload_resume<P>(
+        &self,
+        journal: &Path,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler,
+    {
+        self.block_on(self.inner.media_upload_resume(journal, options, progress))
+    }
+
+    /// Query the state of a chunked upload
+    /// # Arguments
+    /// * `mid` - The media id of the media being uploaded
+    #[cfg(feature = "experimental-chunked")]
+    pub fn media_upload_status(
+        &self,
+        mid: MID,
+    ) -> Result<MMediaUploadStatusPOSTRes, VelocityError> {
+        self.block_on(self.inner.media_upload_status(mid))
+    }
+
+    /// Checks if the hypervisor supports chunked uploads
+    #[cfg(feature = "experimental-chunked")]
+    pub fn media_upload_chunked_supported(&self) -> Result<bool, VelocityError> {
+        self.block_on(self.inner.media_upload_chunked_supported())
+    }
+
+    /// Remove a piece of media and delete it
+    /// # Arguments
+    /// * `mid` - The media id of the media to remove
+    pub fn media_remove(&self, mid: MID) -> Result<(), VelocityError> {
+        self.block_on(self.inner.media_remove(mid))
+    }
+
+    /// List all available host NICs to user for bridge mode
+    pub fn nic_list(&self) -> Result<Vec<VNICListPOSTRes>, VelocityError> {
+        self.block_on(self.inner.nic_list())
+    }
+
+    /// Create a new EFI virtual machine
+    /// # Arguments
+    /// * `vm_config` - A config that describes the virtual machine
+    pub fn vm_efi_create(&self, vm_config: EFIVMConfig) -> Result<VMID, VelocityError> {
+        self.block_on(self.inner.vm_efi_create(vm_config))
+    }
+
+    /// Validates a virtual machine configuration, including the referenced media and host NICs
+    /// # Arguments
+    /// * `vm` - The configuration to validate
+    pub fn vm_validate(&self, vm: &CoreVM) -> Result<Vec<ConfigProblem>, VelocityError> {
+        self.block_on(self.inner.vm_validate(vm))
+    }
+
+    /// Resolves a user by its name or `UID`
+    /// # Arguments
+    /// * `name_or_id` - The name or `UID` of the user
+    pub fn resolve_user(&self, name_or_id: &str) -> Result<UID, VelocityError> {
+        self.block_on(self.inner.resolve_user(name_or_id))
+    }
+
+    /// Resolves a group by its name, its path like `root/team/ci` or its `GID`
+    /// # Arguments
+    /// * `path_or_id` - The name, path or `GID` of the group
+    pub fn resolve_group(&self, path_or_id: &str) -> Result<GID, VelocityError> {
+        self.block_on(self.inner.resolve_group(path_or_id))
+    }
+
+    /// Resolves a media pool by its name or `MPID`
+    /// # Arguments
+    /// * `name_or_id` - The name or `MPID` of the pool
+    pub fn resolve_pool(&self, name_or_id: &str) -> Result<MPID, VelocityError> {
+        self.block_on(self.inner.resolve_pool(name_or_id))
+    }
+
+    /// Resolves a piece of media by its name or `MID`
+    /// # Arguments
+    /// * `name_or_id` - The name or `MID` of the media
+    pub fn resolve_media(&self, name_or_id: &str) -> Result<MID, VelocityError> {
+        self.block_on(self.inner.resolve_media(name_or_id))
+    }
+
+    /// Resolves a host NIC by its identifier, its description or its `NICID`
+    /// # Arguments
+    /// * `name_or_id` - The identifier, description or `NICID` of the NIC
+    pub fn resolve_nic(&self, name_or_id: &str) -> Result<NICID, VelocityError> {
+        self.block_on(self.inner.resolve_nic(name_or_id))
+    }
+
+    /// Runs a future on the internal runtime until it completes
+    /// # Arguments
+    /// * `future` - The future to run
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+/// Creates the runtime all requests of a client run on
+fn runtime() -> io::Result<Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+}
+
+/// Provides the data of a blocking reader asynchronously by reading it on a separate thread.
+/// The thread stops once the returned reader is dropped
+/// # Arguments
+/// * `reader` - The reader to read from
+fn async_reader<R: Read + Send + 'static>(mut reader: R) -> impl AsyncRead + Send + 'static {
+    let (tx, mut rx) = mpsc::channel::<io::Result<Bytes>>(4);
+
+    thread::spawn(move || {
+        let mut buf = vec![0u8; READ_SIZE];
+        loop {
+            let chunk = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => Ok(Bytes::copy_from_slice(&buf[..n])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+
+            let failed = chunk.is_err();
+            if tx.blocking_send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    StreamReader::new(futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx)))
+}
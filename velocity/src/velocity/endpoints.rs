@@ -1,3 +1,3 @@
-mod m;
-mod u;
+pub mod m;
+pub mod u;
 pub mod v;
@@ -1,2 +1,5 @@
 mod m_media;
 mod m_pool;
+
+pub use m_media::*;
+pub use m_pool::*;
@@ -1,16 +1,44 @@
 use futures_util::StreamExt;
-use reqwest::{header::HeaderMap, Body, Method};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
-use tokio::fs::File;
+use sha2::{Digest, Sha256};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+#[cfg(feature = "native")]
+use std::{io::SeekFrom, path::Path};
+use tokio::io::AsyncRead;
+#[cfg(feature = "native")]
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
-use crate::{error::VelocityError, Velocity, GID, MID, MPID};
+#[cfg(feature = "native")]
+use crate::transfer::{BandwidthLimit, TransferProgress};
+use crate::{
+    error::VelocityError,
+    transfer::{self, ProgressHandler, ProgressTracker, TransferOptions, TransferPhase},
+    transport::{TransportBody, TransportRequest},
+    Velocity, GID, MID, MPID,
+};
+
+#[cfg(feature = "wasm")]
+mod m_media_blob;
+#[cfg(feature = "experimental-chunked")]
+mod m_media_chunked;
+#[cfg(feature = "experimental-chunked")]
+pub use m_media_chunked::MMediaUploadStatusPOSTRes;
 
 impl Velocity {
-    /// List all available media for a group
+    /// List all media available to a group, including the media of its parent groups
     /// # Arguments
     /// * `gid` - The group id of the group to list of
     pub async fn media_list(&self, gid: GID) -> Result<Vec<MMediaListPOSTRes>, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = MMediaListPOSTReq {
             authkey: authkey.key(),
@@ -44,7 +72,7 @@ impl Velocity {
         ty: &str,
         size: u64,
     ) -> Result<MMediaCreatePUTRes, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = MMediaCreatePUTReq {
             authkey: authkey.key(),
@@ -61,97 +89,230 @@ impl Velocity {
             .response)
     }
 
-    /// Upload a file to the hypervisor as a new piece of media
+    /// Upload data to the hypervisor as a new piece of media. If the length of the data
+    /// is unknown, e.g. when reading from a pipe, the data is sent using chunked transfer encoding
     /// # Arguments
     /// * `mpid` - The mediapool id where the media should live in
     /// * `gid` - The group id that should own the newly created media
     /// * `name` - A user-friendly name for the new media
     /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
     /// * `readonly` - If the file should be read-only
-    /// * `file` - The file to upload
-    /// * `progress_callback` - A callback informing the caller about the upload progress. Args: `(total: u64, uploaded: u64)`
-    pub async fn media_upload<F>(
+    /// * `reader` - The reader providing the data to upload
+    /// * `size` - The length of the data, if known
+    /// * `options` - Options for the transfer, such as a token to cancel it
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn media_upload<R, P>(
         &self,
         mpid: MPID,
         gid: GID,
         name: &str,
         ty: &str,
         readonly: bool,
-        file: File,
-        progress_callback: F,
+        reader: R,
+        size: Option<u64>,
+        options: TransferOptions,
+        progress: P,
     ) -> Result<MMediaUploadPUTRes, VelocityError>
     where
-        F: Fn(u64, u64) + Send + Sync + 'static,
+        R: AsyncRead + Send + 'static,
+        P: ProgressHandler + 'static,
     {
-        let authkey = self.get_authkey()?;
-
-        let mut headers = HeaderMap::new();
-        let file_size = file.metadata().await.expect("Read File metadata").len();
-
-        // Apply all headers for the Velocity API
-        // This API call does not have a JSON request, but relies on HTTP headers
-        headers.insert("Content-Length", file_size.into());
-        headers.insert(
-            "x-velocity-authkey",
-            authkey.key().parse().expect("Parse authkey to HeaderValue"),
-        );
-        headers.insert("x-velocity-mpid", mpid.into());
-        headers.insert("x-velocity-gid", gid.into());
-        headers.insert(
-            "x-velocity-name",
-            name.parse().expect("Parse name to HeaderValue"),
-        );
-        headers.insert(
-            "x-velocity-type",
-            ty.parse().expect("Parse type to HeaderValue"),
-        );
-        headers.insert(
-            "x-velocity-readonly",
-            match readonly {
-                true => "true",
-                false => "false",
-            }
-            .parse()
-            .unwrap(),
-        );
+        let mut request = self.upload_request(mpid, gid, name, ty, readonly, &options)?;
+        if let Some(size) = size {
+            request.headers.insert("Content-Length", size.into());
+        }
 
         // Create a ReaderStream to provide a way of obtaining progress
-        let mut reader_stream = tokio_util::io::ReaderStream::new(file);
+        let reader_stream = tokio_util::io::ReaderStream::new(reader);
+        // Throttling waits on the tokio timer, which is not available in the browser
+        #[cfg(feature = "native")]
+        let reader_stream = transfer::throttle(reader_stream, self.limit_for(&options));
 
-        // The amount of bytes uploaded until now
-        let mut uploaded = 0u64;
+        // The amount of bytes uploaded until now, shared to report it on cancellation
+        let uploaded = Arc::new(AtomicU64::new(0));
+        let stream_uploaded = uploaded.clone();
 
-        // Move the chunks from the file into the stream, noting down progress
+        // The checksum of all data read until now
+        let hasher = Arc::new(Mutex::new(Sha256::new()));
+        let stream_hasher = hasher.clone();
+
+        let tracker = ProgressTracker::new(progress, size);
+
+        // Move the chunks from the reader into the stream, noting down progress
         let async_stream = async_stream::stream! {
+            futures_util::pin_mut!(reader_stream);
+            tracker.report(TransferPhase::Uploading, 0);
             while let Some(chunk) = reader_stream.next().await {
                 if let Ok(chunk) = &chunk {
+                    stream_hasher.lock().expect("Lock hasher").update(chunk);
+
                     // Append the newly uploaded bytes and call the progress callback
-                    uploaded += chunk.len() as u64;
-                    progress_callback(file_size, uploaded);
+                    let uploaded = stream_uploaded.fetch_add(chunk.len() as u64, Ordering::SeqCst)
+                        + chunk.len() as u64;
+                    tracker.report(TransferPhase::Uploading, uploaded);
+
+                    // With a known length, the transport stops reading once the last chunk is out
+                    if size == Some(uploaded) {
+                        tracker.report(TransferPhase::Processing, uploaded);
+                    }
                 }
                 yield chunk;
             }
+
+            // Everything has been sent, the hypervisor has the last word now
+            if size.is_none() {
+                tracker.report(TransferPhase::Processing, stream_uploaded.load(Ordering::SeqCst));
+            }
         };
 
-        // Construct the request
-        let request = self
-            .http_client
-            .put(self.url("/m/media/upload"))
-            .headers(headers)
-            .body(Body::wrap_stream(async_stream));
+        // Attach the stream as the body of the request
+        request.body = TransportBody::Stream(Box::pin(async_stream));
 
-        // And expect the response
-        Ok(self
-            .request_json_raw::<MMediaUploadPUTRes>(request)
+        // And expect the response, unless the transfer gets cancelled
+        let mut res = options
+            .run(self.request_json_raw::<MMediaUploadPUTRes>(request), || {
+                uploaded.load(Ordering::SeqCst)
+            })
             .await?
-            .response)
+            .response;
+
+        // Make sure the hypervisor received what has been read
+        let sha256 = transfer::hex(&hasher.lock().expect("Lock hasher").clone().finalize());
+        verify_checksums(&options, res.sha256.as_deref(), &sha256)?;
+        res.sha256 = Some(sha256);
+
+        Ok(res)
+    }
+
+    /// Upload a file to the hypervisor as a new piece of media. The file is hashed
+    /// before it is sent, so its checksum travels along with the upload and the hypervisor
+    /// can reject data that got corrupted on the way. Data that can only be read once,
+    /// e.g. from a pipe, can't be hashed in advance, use `media_upload()` for it
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `readonly` - If the file should be read-only
+    /// * `source` - The path to the file to upload
+    /// * `options` - Options for the transfer, such as a token to cancel it
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[cfg(feature = "native")]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn media_upload_file<P>(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        readonly: bool,
+        source: &Path,
+        mut options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler + 'static,
+    {
+        let mut file = File::open(source).await?;
+        let size = file.metadata().await?.len();
+
+        // Hashing and uploading report to the same handler
+        let progress = Arc::new(progress);
+        let hashing = progress.clone();
+        let tracker =
+            ProgressTracker::new(move |p: &TransferProgress| hashing.progress(p), Some(size));
+
+        tracker.report(TransferPhase::Hashing, 0);
+        let hasher = options
+            .run(
+                hash_prefix(&mut file, size, |done| {
+                    tracker.report(TransferPhase::Hashing, done)
+                }),
+                || 0,
+            )
+            .await?;
+        let sha256 = transfer::hex(&hasher.finalize());
+
+        // There is no point in sending a file that does not match the expected checksum
+        options.verify_sha256(&sha256)?;
+        options.sha256 = Some(sha256);
+
+        file.seek(SeekFrom::Start(0)).await?;
+        self.media_upload(
+            mpid,
+            gid,
+            name,
+            ty,
+            readonly,
+            file,
+            Some(size),
+            options,
+            move |p: &TransferProgress| progress.progress(p),
+        )
+        .await
+    }
+
+    /// Builds the request for `/m/media/upload`, which does not have a JSON body,
+    /// but relies on HTTP headers. The body is left to the caller
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `readonly` - If the file should be read-only
+    /// * `options` - Options for the transfer, providing the expected checksum
+    fn upload_request(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        readonly: bool,
+        options: &TransferOptions,
+    ) -> Result<TransportRequest, VelocityError> {
+        // Uploads can run for a long time and holding on to the authkey would block
+        // renewing it for that long, so the current key is only read once
+        let authkey = self.get_authkey()?;
+
+        let mut request = TransportRequest::new(Method::PUT, self.url("/m/media/upload"));
+
+        request.header("x-velocity-authkey", authkey.key())?;
+        request.headers.insert("x-velocity-mpid", mpid.0.into());
+        request.headers.insert("x-velocity-gid", gid.0.into());
+        request.header("x-velocity-name", name)?;
+        request.header("x-velocity-type", ty)?;
+        request.header(
+            "x-velocity-readonly",
+            match readonly {
+                true => "true",
+                false => "false",
+            },
+        )?;
+        if let Some(sha256) = &options.sha256 {
+            transfer::check_sha256(sha256)?;
+            request.header("x-velocity-sha256", sha256)?;
+        }
+
+        Ok(request)
+    }
+
+    /// Returns the bandwidth limit a transfer uses
+    /// # Arguments
+    /// * `options` - The options of the transfer
+    #[cfg(feature = "native")]
+    fn limit_for(&self, options: &TransferOptions) -> BandwidthLimit {
+        options
+            .limit
+            .clone()
+            .unwrap_or_else(|| self.transfer_limit().clone())
     }
 
     /// Remove a piece of media and delete it
     /// # Arguments
     /// * `mid` - The media id of the media to remove
     pub async fn media_remove(&self, mid: MID) -> Result<(), VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = MMediaDELETEReq {
             authkey: authkey.key(),
@@ -164,6 +325,51 @@ impl Velocity {
     }
 }
 
+/// Verifies the checksum of uploaded data against the expected one and the one the
+/// hypervisor reported
+/// # Arguments
+/// * `options` - The options of the transfer, holding the expected checksum
+/// * `remote` - The checksum the hypervisor reported, if any
+/// * `local` - The checksum of the data that has been read
+fn verify_checksums(
+    options: &TransferOptions,
+    remote: Option<&str>,
+    local: &str,
+) -> Result<(), VelocityError> {
+    options.verify_sha256(local)?;
+    transfer::verify_sha256(Some(local), remote.unwrap_or(local))
+}
+
+/// Reads the start of a file and computes its checksum
+/// # Arguments
+/// * `file` - The file to read
+/// * `len` - The amount of bytes to hash
+/// * `on_progress` - Called with the amount of bytes hashed until now
+#[cfg(feature = "native")]
+async fn hash_prefix(
+    file: &mut File,
+    len: u64,
+    on_progress: impl Fn(u64),
+) -> Result<Sha256, VelocityError> {
+    file.seek(SeekFrom::Start(0)).await?;
+
+    let mut hasher = Sha256::new();
+    let mut reader = file.take(len);
+    let mut hashed = 0;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        hashed += n as u64;
+        on_progress(hashed);
+    }
+
+    Ok(hasher)
+}
+
 /// `/m/media/list - POST` Request structure
 #[derive(Serialize)]
 struct MMediaListPOSTReq<'a> {
@@ -172,7 +378,8 @@ struct MMediaListPOSTReq<'a> {
 }
 
 /// `/m/media/list - POST` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct MMediaListPOSTRes {
     pub mid: MID,
     pub mpid: MPID,
@@ -181,6 +388,9 @@ pub struct MMediaListPOSTRes {
     pub ty: String,
     pub size: u64,
     pub readonly: bool,
+    /// The SHA-256 checksum of the media, if the hypervisor knows it
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
 /// `/m/media/create - PUT` Request structure
@@ -196,17 +406,22 @@ struct MMediaCreatePUTReq<'a> {
 }
 
 /// `/m/media/create - PUT` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct MMediaCreatePUTRes {
     pub mid: MID,
     pub size: u64,
 }
 
 /// `/m/media/upload - PUT` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct MMediaUploadPUTRes {
     pub mid: MID,
     pub size: u64,
+    /// The SHA-256 checksum of the uploaded data, as lowercase hex
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
 /// `/m/media - DELETE` Request structure
@@ -0,0 +1,186 @@
+//! Uploads of browser `File`s and `Blob`s. Browsers can't stream request bodies through
+//! `fetch`, so these are sent using an `XMLHttpRequest`, which reads the data on its own
+//! and reports the progress of the upload
+
+use std::{cell::Cell, io, rc::Rc};
+
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    StatusCode,
+};
+use tokio::sync::oneshot;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{Blob, ProgressEvent, XmlHttpRequest, XmlHttpRequestResponseType};
+
+use super::MMediaUploadPUTRes;
+use crate::{
+    error::VelocityError,
+    transfer::{self, ProgressHandler, ProgressTracker, TransferOptions, TransferPhase},
+    transport::TransportResponse,
+    velocity::reqwest::json_response,
+    Velocity, GID, MPID,
+};
+
+impl Velocity {
+    /// Upload a browser `File` or `Blob` to the hypervisor as a new piece of media.
+    /// The browser reads the data itself, so bandwidth limits do not apply and
+    /// an expected checksum is only verified by the hypervisor
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `readonly` - If the file should be read-only
+    /// * `blob` - The `File` or `Blob` to upload
+    /// * `options` - Options for the transfer, such as a token to cancel it
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn media_upload_blob<P>(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        readonly: bool,
+        blob: &Blob,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler + 'static,
+    {
+        let request = self.upload_request(mpid, gid, name, ty, readonly, &options)?;
+        let size = blob.size() as u64;
+
+        let xhr = XmlHttpRequest::new().map_err(js_error)?;
+        xhr.open("PUT", &request.url).map_err(js_error)?;
+        xhr.set_response_type(XmlHttpRequestResponseType::Arraybuffer);
+        for (name, value) in &request.headers {
+            xhr.set_request_header(name.as_str(), &String::from_utf8_lossy(value.as_bytes()))
+                .map_err(js_error)?;
+        }
+
+        // The amount of bytes uploaded until now, shared to report it on cancellation
+        let uploaded = Rc::new(Cell::new(0));
+        let tracker = Rc::new(ProgressTracker::new(progress, Some(size)));
+
+        let on_progress = {
+            let uploaded = uploaded.clone();
+            let tracker = tracker.clone();
+            Closure::<dyn FnMut(ProgressEvent)>::new(move |event: ProgressEvent| {
+                uploaded.set(event.loaded() as u64);
+                tracker.report(TransferPhase::Uploading, uploaded.get());
+            })
+        };
+
+        // Once the browser has sent everything, the hypervisor has the last word
+        let on_upload_load = {
+            let tracker = tracker.clone();
+            Closure::<dyn FnMut()>::new(move || tracker.report(TransferPhase::Processing, size))
+        };
+
+        // Fired once the request is over, no matter if it succeeded, failed or got aborted
+        let (done, finished) = oneshot::channel();
+        let mut done = Some(done);
+        let on_load_end = Closure::<dyn FnMut()>::new(move || {
+            if let Some(done) = done.take() {
+                let _ = done.send(());
+            }
+        });
+
+        let upload = xhr.upload().map_err(js_error)?;
+        upload.set_onprogress(Some(on_progress.as_ref().unchecked_ref()));
+        upload.set_onload(Some(on_upload_load.as_ref().unchecked_ref()));
+        xhr.set_onloadend(Some(on_load_end.as_ref().unchecked_ref()));
+
+        // Declared after the closures, so the request gets aborted before they are dropped
+        let request = PendingRequest(&xhr);
+
+        tracker.report(TransferPhase::Uploading, 0);
+        xhr.send_with_opt_blob(Some(blob)).map_err(js_error)?;
+
+        let response = options
+            .run(
+                async {
+                    let _ = finished.await;
+                    request.response()
+                },
+                || uploaded.get(),
+            )
+            .await?;
+
+        let res: MMediaUploadPUTRes = json_response(response)?.response;
+
+        // Only the hypervisor has seen all the data, so its checksum is the one to check
+        if let Some(sha256) = &res.sha256 {
+            transfer::verify_sha256(options.sha256.as_deref(), sha256)?;
+        }
+
+        Ok(res)
+    }
+}
+
+/// An `XMLHttpRequest` that has been sent. If it is dropped before it is done,
+/// e.g. because the transfer got cancelled, the request gets aborted
+struct PendingRequest<'a>(&'a XmlHttpRequest);
+
+impl PendingRequest<'_> {
+    /// Collects the response of the finished request
+    fn response(&self) -> Result<TransportResponse, VelocityError> {
+        let xhr = self.0;
+
+        // A status of 0 means no response has been received at all
+        let status = match xhr.status().map_err(js_error)? {
+            0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    "The upload failed due to a network error",
+                )
+                .into())
+            }
+            status => StatusCode::from_u16(status)
+                .map_err(|e| VelocityError::IO(io::Error::new(io::ErrorKind::InvalidData, e)))?,
+        };
+
+        let mut response = TransportResponse {
+            status,
+            headers: Default::default(),
+            body: match xhr.response().map_err(js_error)? {
+                body if body.is_null() => Bytes::new(),
+                body => js_sys::Uint8Array::new(&body).to_vec().into(),
+            },
+        };
+
+        // The content type is the only header that gets looked at, e.g. for unexpected responses
+        if let Some(content_type) = xhr
+            .get_response_header("content-type")
+            .map_err(js_error)?
+            .and_then(|v| HeaderValue::from_str(&v).ok())
+        {
+            response.headers.insert(CONTENT_TYPE, content_type);
+        }
+
+        Ok(response)
+    }
+}
+
+impl Drop for PendingRequest<'_> {
+    fn drop(&mut self) {
+        if self.0.ready_state() != XmlHttpRequest::DONE {
+            let _ = self.0.abort();
+        }
+    }
+}
+
+/// Converts an exception thrown by a browser API to an error
+/// # Arguments
+/// * `value` - The thrown value
+fn js_error(value: JsValue) -> VelocityError {
+    let message = match value.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => format!("{:?}", value),
+    };
+
+    VelocityError::IO(io::Error::other(message))
+}
@@ -0,0 +1,509 @@
+//! Chunked, resumable uploads through `/m/media/upload/{start,chunk,status}`.
+//!
+//! These endpoints are an experimental extension, the Velocity API does not define them
+//! and only the mock hypervisor serves them. They are only built with the
+//! `experimental-chunked` feature, `media_upload()` works with every hypervisor
+
+#[cfg(feature = "native")]
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+#[cfg(feature = "native")]
+use bytes::Bytes;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "native")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "native")]
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+#[cfg(feature = "native")]
+use super::{hash_prefix, verify_checksums, MMediaUploadPUTRes};
+use crate::{
+    error::{VelocityError, VelocityErrorKind},
+    Velocity, MID,
+};
+#[cfg(feature = "native")]
+use crate::{
+    transfer::{
+        self, BandwidthLimit, ProgressHandler, ProgressTracker, TransferOptions, TransferPhase,
+        UploadJournal,
+    },
+    transport::{TransportBody, TransportRequest},
+    GID, MPID,
+};
+
+/// The delay before the first retry of a failed chunk, it doubles with every further retry
+#[cfg(feature = "native")]
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest delay between two retries
+#[cfg(feature = "native")]
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+impl Velocity {
+    /// Upload a file to the hypervisor in chunks, if it supports them, see
+    /// `media_upload_chunked_supported()`. The progress is recorded in a journal file,
+    /// which allows `media_upload_resume()` to continue the upload after an interruption.
+    /// Chunks that fail due to connection problems are retried according to `options`,
+    /// waiting twice as long before every further retry. Once the upload completes, the journal gets removed
+    /// # Arguments
+    /// * `mpid` - The mediapool id where the media should live in
+    /// * `gid` - The group id that should own the newly created media
+    /// * `name` - A user-friendly name for the new media
+    /// * `ty` - A string describing the type of media to use - see the Velocity API documentation
+    /// * `readonly` - If the file should be read-only
+    /// * `source` - The path to the file to upload
+    /// * `journal` - The path to the journal file to record the progress in
+    /// * `options` - Options for the transfer, such as the chunk size
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[cfg(feature = "native")]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn media_upload_chunked<P>(
+        &self,
+        mpid: MPID,
+        gid: GID,
+        name: &str,
+        ty: &str,
+        readonly: bool,
+        source: &Path,
+        journal: &Path,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler,
+    {
+        if let Some(sha256) = &options.sha256 {
+            transfer::check_sha256(sha256)?;
+        }
+        let size = tokio::fs::metadata(source).await?.len();
+
+        let res = {
+            let authkey = self.authkey().await?;
+
+            let request = MMediaUploadStartPUTReq {
+                authkey: authkey.key(),
+                mpid,
+                gid,
+                name,
+                ty,
+                size,
+                readonly,
+                sha256: options.sha256.as_deref(),
+            };
+
+            self.request_json::<_, MMediaUploadStartPUTRes>(
+                Method::PUT,
+                "/m/media/upload/start",
+                &request,
+            )
+            .await?
+            .response
+        };
+
+        let record = UploadJournal {
+            mid: res.mid,
+            mpid,
+            gid,
+            name: name.to_owned(),
+            source: source.to_owned(),
+            size,
+            offset: 0,
+        };
+        record.save(journal).await?;
+
+        self.media_upload_chunks(record, journal, options, progress)
+            .await
+    }
+
+    /// Resume a chunked upload that has been interrupted. The hypervisor is asked
+    /// for the last acknowledged offset and the upload continues from there
+    /// # Arguments
+    /// * `journal` - The path to the journal file of the upload
+    /// * `options` - Options for the transfer, such as the chunk size
+    /// * `progress` - Receives progress updates, e.g. a callback or a `watch::Sender`
+    #[cfg(feature = "native")]
+    pub async fn media_upload_resume<P>(
+        &self,
+        journal: &Path,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler,
+    {
+        let mut record = UploadJournal::load(journal).await?;
+
+        // The hypervisor knows best how much it has received
+        record.offset = self.media_upload_status(record.mid.clone()).await?.offset;
+        record.save(journal).await?;
+
+        self.media_upload_chunks(record, journal, options, progress)
+            .await
+    }
+
+    /// Query the state of a chunked upload, if the hypervisor supports them, see
+    /// `media_upload_chunked_supported()`
+    /// # Arguments
+    /// * `mid` - The media id of the media being uploaded
+    pub async fn media_upload_status(
+        &self,
+        mid: MID,
+    ) -> Result<MMediaUploadStatusPOSTRes, VelocityError> {
+        let authkey = self.authkey().await?;
+
+        let request = MMediaUploadStatusPOSTReq {
+            authkey: authkey.key(),
+            mid,
+        };
+
+        Ok(self
+            .request_json(Method::POST, "/m/media/upload/status", &request)
+            .await?
+            .response)
+    }
+
+    /// Checks if the hypervisor supports chunked uploads. They are an experimental extension
+    /// the Velocity API does not define, so `media_upload_chunked()` and `media_upload_resume()` should only
+    /// be used if this returns `true`, `media_upload()` works with every hypervisor.
+    ///
+    /// The status of an upload that can't exist is queried: A hypervisor with chunked
+    /// uploads responds with a not found API error, one without them does not know the
+    /// route and responds with an error of its web framework
+    pub async fn media_upload_chunked_supported(&self) -> Result<bool, VelocityError> {
+        const NIL_MID: &str = "00000000-0000-0000-0000-000000000000";
+
+        match self.media_upload_status(NIL_MID.to_owned()).await {
+            Ok(_) => Ok(true),
+            Err(VelocityError::APIError(e)) if e.kind() == VelocityErrorKind::NotFound => Ok(true),
+            Err(e) if e.is_not_found() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Uploads the remaining chunks of a chunked upload, starting at the journal's offset
+    /// # Arguments
+    /// * `record` - The journal of the upload
+    /// * `journal` - The path to the journal file to update
+    /// * `options` - Options for the transfer
+    /// * `progress` - Receives progress updates
+    #[cfg(feature = "native")]
+    async fn media_upload_chunks<P>(
+        &self,
+        mut record: UploadJournal,
+        journal: &Path,
+        options: TransferOptions,
+        progress: P,
+    ) -> Result<MMediaUploadPUTRes, VelocityError>
+    where
+        P: ProgressHandler,
+    {
+        let mut file = File::open(&record.source).await?;
+        if file.metadata().await?.len() != record.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "'{}' has changed its size since the upload started",
+                    record.source.display()
+                ),
+            )
+            .into());
+        }
+
+        let mut backoff = Backoff::new(options.retries);
+        let tracker = ProgressTracker::new(progress, Some(record.size));
+        let limit = self.limit_for(&options);
+
+        // The checksum of the file up to `hashed`, which trails the acknowledged offset
+        let mut hasher = Sha256::new();
+        let mut hashed = 0;
+
+        // The checksum the hypervisor reports once it has received everything
+        let mut remote_sha256 = None;
+
+        while record.offset < record.size {
+            // A resumed upload needs the checksum of what has been uploaded before
+            if hashed != record.offset {
+                hasher = hash_prefix(&mut file, record.offset, |done| {
+                    tracker.report(TransferPhase::Hashing, done)
+                })
+                .await?;
+                hashed = record.offset;
+            }
+            tracker.report(TransferPhase::Uploading, record.offset);
+
+            let len = options.chunk_size.min(record.size - record.offset);
+            let mut chunk = vec![0u8; len as usize];
+            file.seek(SeekFrom::Start(record.offset)).await?;
+            file.read_exact(&mut chunk).await?;
+
+            let offset = record.offset;
+            let chunk = Bytes::from(chunk);
+
+            // The last chunk carries the checksum of the whole media, so the hypervisor
+            // can verify the assembled media before it accepts the upload
+            let media_sha256 = (offset + len == record.size).then(|| {
+                let mut hasher = hasher.clone();
+                hasher.update(&chunk);
+                transfer::hex(&hasher.finalize())
+            });
+
+            let res = options
+                .run(
+                    self.media_upload_chunk(
+                        &record.mid,
+                        offset,
+                        chunk.clone(),
+                        media_sha256.as_deref(),
+                        &limit,
+                    ),
+                    || offset,
+                )
+                .await;
+
+            match res {
+                Ok(res) => {
+                    if res.offset == offset + len {
+                        hasher.update(&chunk);
+                        hashed = res.offset;
+                    }
+                    record.offset = res.offset;
+                    remote_sha256 = res.sha256;
+                    backoff = Backoff::new(options.retries);
+                }
+                Err(e) if e.kind() == VelocityErrorKind::Transport => {
+                    log::warn!("Chunk at offset {} failed: {}", offset, e);
+                    record.offset = self
+                        .recover_offset(&record.mid, offset, &options, &mut backoff, e)
+                        .await?;
+                }
+                Err(e) => return Err(e),
+            }
+
+            record.save(journal).await?;
+        }
+
+        tracker.report(TransferPhase::Uploading, record.size);
+        tracker.report(TransferPhase::Processing, record.size);
+        if hashed != record.size {
+            hasher = hash_prefix(&mut file, record.size, |done| {
+                tracker.report(TransferPhase::Hashing, done)
+            })
+            .await?;
+        }
+        let sha256 = transfer::hex(&hasher.finalize());
+        verify_checksums(&options, remote_sha256.as_deref(), &sha256)?;
+
+        tokio::fs::remove_file(journal).await?;
+
+        Ok(MMediaUploadPUTRes {
+            mid: record.mid,
+            size: record.size,
+            sha256: Some(sha256),
+        })
+    }
+
+    /// Asks the hypervisor how much of an upload it has received after a chunk failed.
+    /// Connection problems are retried with a backoff, sharing the budget of the chunk
+    /// # Arguments
+    /// * `mid` - The media id of the media being uploaded
+    /// * `offset` - The offset of the chunk that failed
+    /// * `options` - Options for the transfer, providing the cancellation token
+    /// * `backoff` - The retries left for the chunk
+    /// * `error` - The error the chunk failed with, returned once no retries are left
+    #[cfg(feature = "native")]
+    async fn recover_offset(
+        &self,
+        mid: &str,
+        offset: u64,
+        options: &TransferOptions,
+        backoff: &mut Backoff,
+        mut error: VelocityError,
+    ) -> Result<u64, VelocityError> {
+        loop {
+            if !backoff.wait(options, offset).await? {
+                return Err(error);
+            }
+
+            match options
+                .run(self.media_upload_status(mid.to_owned()), || offset)
+                .await
+            {
+                Ok(status) => return Ok(status.offset),
+                Err(e) if e.kind() == VelocityErrorKind::Transport => {
+                    log::warn!("Querying the state of the upload failed: {}", e);
+                    error = e;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Upload a single chunk of a chunked upload
+    /// # Arguments
+    /// * `mid` - The media id of the media being uploaded
+    /// * `offset` - The offset of the chunk within the file
+    /// * `chunk` - The data of the chunk
+    /// * `media_sha256` - The checksum of the whole media, sent along with the last chunk
+    /// * `limit` - The bandwidth limit to send the chunk with
+    #[cfg(feature = "native")]
+    async fn media_upload_chunk(
+        &self,
+        mid: &str,
+        offset: u64,
+        chunk: Bytes,
+        media_sha256: Option<&str>,
+        limit: &BandwidthLimit,
+    ) -> Result<MMediaUploadChunkPUTRes, VelocityError> {
+        let authkey = self.authkey().await?;
+
+        let mut request = TransportRequest::new(Method::PUT, self.url("/m/media/upload/chunk"));
+        request.header("x-velocity-authkey", authkey.key())?;
+        request.header("x-velocity-mid", mid)?;
+        request.headers.insert("Content-Length", chunk.len().into());
+        request.headers.insert("x-velocity-offset", offset.into());
+        request.header("x-velocity-sha256", &transfer::hex(&Sha256::digest(&chunk)))?;
+        if let Some(media_sha256) = media_sha256 {
+            request.header("x-velocity-media-sha256", media_sha256)?;
+        }
+        request.body = TransportBody::Stream(Box::pin(transfer::throttle(
+            transfer::pieces(chunk),
+            limit.clone(),
+        )));
+
+        Ok(self.request_json_raw(request).await?.response)
+    }
+}
+
+/// The retries left for a chunk and the delay before the next one
+#[cfg(feature = "native")]
+struct Backoff {
+    retries: u32,
+    delay: Duration,
+}
+
+#[cfg(feature = "native")]
+impl Backoff {
+    /// Creates a backoff starting at `RETRY_DELAY`
+    /// # Arguments
+    /// * `retries` - The amount of retries
+    fn new(retries: u32) -> Backoff {
+        Backoff {
+            retries,
+            delay: RETRY_DELAY,
+        }
+    }
+
+    /// Takes a retry, returning the delay to wait before it, `None` if there are no retries left
+    fn next_delay(&mut self) -> Option<Duration> {
+        self.retries = self.retries.checked_sub(1)?;
+
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(MAX_RETRY_DELAY);
+        Some(delay)
+    }
+
+    /// Waits before the next retry, returns `false` without waiting if there are no retries left
+    /// # Arguments
+    /// * `options` - The options of the transfer, waiting ends early on cancellation
+    /// * `offset` - The amount of bytes transferred, reported on cancellation
+    async fn wait(
+        &mut self,
+        options: &TransferOptions,
+        offset: u64,
+    ) -> Result<bool, VelocityError> {
+        let Some(delay) = self.next_delay() else {
+            return Ok(false);
+        };
+
+        options
+            .run(
+                async {
+                    tokio::time::sleep(delay).await;
+                    Ok(())
+                },
+                || offset,
+            )
+            .await?;
+
+        Ok(true)
+    }
+}
+
+/// `/m/media/upload/start - PUT` Request structure
+#[cfg(feature = "native")]
+#[derive(Serialize)]
+struct MMediaUploadStartPUTReq<'a> {
+    authkey: &'a str,
+    mpid: MPID,
+    gid: GID,
+    name: &'a str,
+    #[serde(rename = "type")]
+    ty: &'a str,
+    size: u64,
+    readonly: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<&'a str>,
+}
+
+/// `/m/media/upload/start - PUT` Response structure
+#[cfg(feature = "native")]
+#[derive(Deserialize, Debug)]
+struct MMediaUploadStartPUTRes {
+    mid: MID,
+}
+
+/// `/m/media/upload/chunk - PUT` Response structure
+#[cfg(feature = "native")]
+#[derive(Deserialize, Debug)]
+struct MMediaUploadChunkPUTRes {
+    offset: u64,
+    /// The checksum of the whole media, once the last chunk has been received
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+/// `/m/media/upload/status - POST` Request structure
+#[derive(Serialize)]
+struct MMediaUploadStatusPOSTReq<'a> {
+    authkey: &'a str,
+    mid: MID,
+}
+
+/// `/m/media/upload/status - POST` Response structure
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct MMediaUploadStatusPOSTRes {
+    pub mid: MID,
+    pub size: u64,
+    pub offset: u64,
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(8);
+        let delays: Vec<Duration> = std::iter::from_fn(|| backoff.next_delay()).collect();
+
+        assert_eq!(delays.len(), 8);
+        assert_eq!(delays[0], RETRY_DELAY);
+        assert_eq!(delays[1], RETRY_DELAY * 2);
+        assert_eq!(delays[2], RETRY_DELAY * 4);
+        assert_eq!(delays[7], MAX_RETRY_DELAY);
+        assert!(delays.windows(2).all(|d| d[0] <= d[1]));
+
+        assert_eq!(Backoff::new(0).next_delay(), None);
+    }
+}
@@ -1,5 +1,7 @@
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 use crate::{error::VelocityError, Velocity, GID, MPID};
 
@@ -11,7 +13,7 @@ impl Velocity {
     /// # Arguments
     /// * `gid` - The group id of the group to look up
     pub async fn pool_list(&self, gid: GID) -> Result<Vec<MPoolListPOSTRes>, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = MPoolListPOSTReq {
             authkey: authkey.key(),
@@ -45,7 +47,7 @@ impl Velocity {
         write: bool,
         manage: bool,
     ) -> Result<(), VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = MPoolAssignPUTReq {
             authkey: authkey.key(),
@@ -67,7 +69,7 @@ impl Velocity {
     /// * `gid` - The group id of the group to revoke from
     /// * `mpid` - The mediapool id of the pool to revoke
     pub async fn pool_revoke(&self, gid: GID, mpid: MPID) -> Result<(), VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = MPoolAssignDELETEReq {
             authkey: authkey.key(),
@@ -90,7 +92,8 @@ struct MPoolListPOSTReq<'a> {
 }
 
 /// `/m/pool/list - POST` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct MPoolListPOSTRes {
     pub mpid: MPID,
     pub name: String,
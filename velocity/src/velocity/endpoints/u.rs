@@ -2,3 +2,6 @@ mod u_auth;
 mod u_group;
 mod u_user;
 mod u_user_permission;
+
+pub use u_group::*;
+pub use u_user::*;
@@ -11,10 +11,13 @@ impl Velocity {
     /// # Returns
     /// The key on success
     pub async fn authenticate(
-        &mut self,
+        &self,
         username: &str,
         password: &str,
     ) -> Result<Authkey, VelocityError> {
+        // Block all other requests until the new key is in place
+        let _lock = self.auth.lock().await;
+
         let req = UAuthPOSTReq { username, password };
 
         let res = self
@@ -23,7 +26,7 @@ impl Velocity {
 
         let key = Authkey::new(&res.response.authkey, res.response.expires);
 
-        self.authkey = Some(key.clone());
+        self.auth.set(Some(key.clone()));
 
         Ok(key)
     }
@@ -31,7 +34,10 @@ impl Velocity {
     /// Reauthenticates the current authkey
     /// # Returns
     /// The key on success
-    pub async fn reauthenticate(&mut self) -> Result<Authkey, VelocityError> {
+    pub async fn reauthenticate(&self) -> Result<Authkey, VelocityError> {
+        // Wait for all requests using the old key to finish and block new ones
+        // until the renewed key is in place, as the old key gets invalidated
+        let _lock = self.auth.lock().await;
         let authkey = self.get_authkey()?;
 
         let req = UAuthPATCHReq {
@@ -44,13 +50,14 @@ impl Velocity {
 
         let key = Authkey::new(&res.response.authkey, res.response.expires);
 
-        self.authkey = Some(key.clone());
+        self.auth.set(Some(key.clone()));
 
         Ok(key)
     }
 
     /// Deauthenticates and drops the internal authkey
-    pub async fn deauthenticate(&mut self) -> Result<(), VelocityError> {
+    pub async fn deauthenticate(&self) -> Result<(), VelocityError> {
+        let _lock = self.auth.lock().await;
         let authkey = self.get_authkey()?;
 
         let req = UAuthDELETEReq {
@@ -60,7 +67,7 @@ impl Velocity {
         let res = self.request(Method::DELETE, "/u/auth", &req).await?;
 
         if res == StatusCode::OK {
-            self.authkey = None
+            self.auth.set(None)
         }
 
         Ok(())
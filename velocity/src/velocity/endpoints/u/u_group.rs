@@ -2,6 +2,8 @@ use std::fmt::Display;
 
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 use crate::{error::VelocityError, Velocity};
 
@@ -14,7 +16,7 @@ impl Velocity {
     /// # Arguments
     /// * `gid` - The group id of the group to inform about
     pub async fn group_info(&self, gid: GID) -> Result<GroupInfo, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UGroupPOSTReq {
             authkey: authkey.key(),
@@ -36,7 +38,7 @@ impl Velocity {
         parent_gid: GID,
         name: &str,
     ) -> Result<UGroupPUTRes, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UGroupPUTReq {
             authkey: authkey.key(),
@@ -54,7 +56,7 @@ impl Velocity {
     /// # Arguments
     /// * `gid` - The `gid` of the group to remove
     pub async fn group_remove(&self, gid: GID) -> Result<(), VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UGroupDELETEReq {
             authkey: authkey.key(),
@@ -68,7 +70,7 @@ impl Velocity {
 
     /// List all groups visible to the current user
     pub async fn group_list(&self) -> Result<Vec<UGroupListPOSTRes>, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UGroupListPOSTReq {
             authkey: authkey.key(),
@@ -88,7 +90,8 @@ impl Velocity {
 }
 
 /// A struct providing information about a group
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct GroupInfo {
     pub name: String,
     pub gid: GID,
@@ -120,7 +123,8 @@ impl Display for GroupInfo {
 }
 
 /// Describes a membership
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct GroupMembership {
     pub uid: UID,
     pub name: String,
@@ -143,7 +147,8 @@ struct UGroupPUTReq<'a> {
 }
 
 /// `/u/group - PUT` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct UGroupPUTRes {
     pub gid: GID,
     pub parent_gid: GID,
@@ -164,7 +169,8 @@ struct UGroupListPOSTReq<'a> {
 }
 
 /// `/u/group/list - POST` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct UGroupListPOSTRes {
     pub name: String,
     pub gid: GID,
@@ -1,6 +1,8 @@
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 use crate::{error::VelocityError, Velocity};
 
@@ -14,7 +16,7 @@ impl Velocity {
     /// # Return
     /// The `uid` of the new user
     pub async fn user_create(&self, username: &str, password: &str) -> Result<UID, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UUserPUTReq {
             authkey: authkey.key(),
@@ -33,7 +35,7 @@ impl Velocity {
     /// # Arguments
     /// * `uid` - The `uid` of the user to remove
     pub async fn user_remove(&self, uid: UID) -> Result<(), VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UUserDELETEReq {
             authkey: authkey.key(),
@@ -49,7 +51,7 @@ impl Velocity {
     /// # Arguments
     /// * `uid` - The `uid`, or None for the user that is authenticated by the current authkey
     pub async fn user_info(&self, uid: Option<UID>) -> Result<UserInfo, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UUserPOSTReq {
             authkey: authkey.key(),
@@ -64,7 +66,7 @@ impl Velocity {
 
     /// Lists all users on a velocity instance
     pub async fn user_list(&self) -> Result<Vec<UUserListPOSTRes>, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UUserListPOSTReq {
             authkey: authkey.key(),
@@ -84,7 +86,8 @@ impl Velocity {
 }
 
 /// A struct providing information about a user
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct UserInfo {
     pub uid: UID,
     pub name: String,
@@ -111,7 +114,8 @@ impl Display for UserInfo {
 }
 
 /// Describes a membership
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct Membership {
     pub gid: GID,
     pub parent_gid: GID,
@@ -120,7 +124,8 @@ pub struct Membership {
 }
 
 /// A permission
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct Permission {
     pub pid: PID,
     pub name: String,
@@ -143,7 +148,8 @@ struct UUserPUTReq<'a> {
 }
 
 /// `/u/user - PUT` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct UUserPUTRes {
     pub uid: UID,
     pub name: String,
@@ -163,7 +169,8 @@ struct UUserListPOSTReq<'a> {
 }
 
 /// `/u/user/list - POST` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct UUserListPOSTRes {
     pub uid: UID,
     pub name: String,
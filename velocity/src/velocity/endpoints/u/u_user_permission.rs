@@ -15,7 +15,7 @@ impl Velocity {
         uid: UID,
         permission: &str,
     ) -> Result<(), VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UUserPermissionPUTReq {
             authkey: authkey.key(),
@@ -41,7 +41,7 @@ impl Velocity {
         uid: UID,
         permission: &str,
     ) -> Result<(), VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = UUserPermissionDELETEReq {
             authkey: authkey.key(),
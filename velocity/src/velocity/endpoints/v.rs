@@ -1,4 +1,5 @@
 mod v_nic;
 mod v_vm;
 
+pub use v_nic::*;
 pub use v_vm::*;
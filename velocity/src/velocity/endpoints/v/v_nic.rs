@@ -1,12 +1,14 @@
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 use crate::{error::VelocityError, Velocity, NICID};
 
 impl Velocity {
     /// List all available host NICs to user for bridge mode
     pub async fn nic_list(&self) -> Result<Vec<VNICListPOSTRes>, VelocityError> {
-        let authkey = self.get_authkey()?;
+        let authkey = self.authkey().await?;
 
         let request = VNICListPOSTReq {
             authkey: authkey.key(),
@@ -32,7 +34,8 @@ struct VNICListPOSTReq<'a> {
 }
 
 /// `/v/nic/list - POST` Response structure
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct VNICListPOSTRes {
     pub nicid: NICID,
     pub description: String,
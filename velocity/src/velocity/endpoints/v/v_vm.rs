@@ -1,16 +1,20 @@
 use std::str::FromStr;
 
 use crate::{GID, MID, NICID};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 pub mod v_vm_efi;
+pub mod v_vm_validate;
 
 /// A core virtual machine configuration that is common across
 /// all virtual machine types
-#[derive(Debug, Serialize)]
-pub struct CoreVM<'a> {
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct CoreVM {
     /// The name for the virtual machine
-    pub name: &'a str,
+    pub name: String,
     /// The group id the virtual machine should belong to
     pub gid: GID,
 
@@ -27,7 +31,8 @@ pub struct CoreVM<'a> {
 }
 
 /// A configuration for a display for a virtual machine
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct DisplayConfig {
     /// A user-friendly name for the display
     pub name: String,
@@ -40,7 +45,8 @@ pub struct DisplayConfig {
 }
 
 /// A configuration for a disk to attach to a virtual machine
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct DiskConfig {
     /// The media id of the piece of media to attach
     pub mid: MID,
@@ -51,7 +57,8 @@ pub struct DiskConfig {
 }
 
 /// The possible modes a disk can be attached to a virtual machine
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub enum DiskMode {
     /// Attach the disk over `USB`
     USB,
@@ -62,7 +69,8 @@ pub enum DiskMode {
 }
 
 /// A configuration for a virtual machine NIC
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub struct NICConfig {
     /// The type of NIC to use
     #[serde(rename = "type")]
@@ -72,7 +80,8 @@ pub struct NICConfig {
 }
 
 /// The possible types a NIC can be in a virtual machine
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
 pub enum NICType {
     /// A `NAT` NIC
     NAT,
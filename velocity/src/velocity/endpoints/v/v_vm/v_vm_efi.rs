@@ -1,5 +1,7 @@
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 use crate::{error::VelocityError, Velocity, VMID};
 
@@ -9,11 +11,8 @@ impl Velocity {
     /// Create a new EFI virtual machine
     /// # Arguments
     /// * `vm_config` - A config that describes the virtual machine
-    pub async fn vm_efi_create<'a>(
-        &self,
-        vm_config: EFIVMConfig<'a>,
-    ) -> Result<VMID, VelocityError> {
-        let authkey = self.get_authkey()?;
+    pub async fn vm_efi_create(&self, vm_config: EFIVMConfig) -> Result<VMID, VelocityError> {
+        let authkey = self.authkey().await?;
 
         let request = VVMEFIPUTReq {
             authkey: authkey.key(),
@@ -31,11 +30,12 @@ impl Velocity {
 }
 
 /// A configuration for a `EFI` virtual machine
-#[derive(Serialize)]
-pub struct EFIVMConfig<'a> {
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct EFIVMConfig {
     /// The core virtual machine configuration
     #[serde(flatten)]
-    pub core: CoreVM<'a>,
+    pub core: CoreVM,
 
     /// If this virtual machine should use the `rosetta` translation layer if available
     pub rosetta: bool,
@@ -46,7 +46,7 @@ pub struct EFIVMConfig<'a> {
 struct VVMEFIPUTReq<'a> {
     authkey: &'a str,
     #[serde(flatten)]
-    config: EFIVMConfig<'a>,
+    config: EFIVMConfig,
 }
 
 /// `/v/vm/efi - PUT` Response structure
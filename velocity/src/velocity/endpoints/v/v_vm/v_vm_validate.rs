@@ -0,0 +1,132 @@
+use std::fmt;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use crate::{error::VelocityError, Velocity};
+
+use super::{CoreVM, NICType};
+
+/// A single problem with a virtual machine configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct ConfigProblem {
+    /// The path to the offending value, e.g. `nics[1].host`
+    pub field: String,
+    /// A human-readable description of the problem
+    pub message: String,
+}
+
+impl ConfigProblem {
+    /// Creates a new problem
+    /// # Arguments
+    /// * `field` - The path to the offending value
+    /// * `message` - A description of the problem
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl CoreVM {
+    /// Checks the configuration for values the hypervisor is going to reject,
+    /// without contacting it. All problems are returned at once, none means the
+    /// configuration looks fine. Use `Velocity::vm_validate()` to check the
+    /// referenced media and host NICs as well
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        if self.name.is_empty() {
+            problems.push(ConfigProblem::new("name", "The name must not be empty"));
+        }
+        if self.cpus == 0 {
+            problems.push(ConfigProblem::new("cpus", "At least 1 CPU is required"));
+        }
+        if self.memory_mib == 0 {
+            problems.push(ConfigProblem::new("memory_mib", "Memory must not be 0"));
+        }
+
+        for (i, display) in self.displays.iter().enumerate() {
+            for (field, value) in [
+                ("width", display.width),
+                ("height", display.height),
+                ("ppi", display.ppi),
+            ] {
+                if value == 0 {
+                    problems.push(ConfigProblem::new(
+                        format!("displays[{i}].{field}"),
+                        "Must not be 0",
+                    ));
+                }
+            }
+        }
+
+        for (i, nic) in self.nics.iter().enumerate() {
+            if matches!(nic.ty, NICType::BRIDGE) && nic.host.is_none() {
+                problems.push(ConfigProblem::new(
+                    format!("nics[{i}].host"),
+                    "A BRIDGE NIC requires a host NIC",
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+impl Velocity {
+    /// Validates a virtual machine configuration like `CoreVM::validate()` and
+    /// additionally checks that the host NICs exist and that the disks refer to media
+    /// the group can use
+    /// # Arguments
+    /// * `vm` - The configuration to validate
+    /// # Returns
+    /// All problems that have been found, none means the configuration looks fine
+    pub async fn vm_validate(&self, vm: &CoreVM) -> Result<Vec<ConfigProblem>, VelocityError> {
+        let mut problems = vm.validate();
+
+        if vm.nics.iter().any(|nic| nic.host.is_some()) {
+            let host_nics = self.nic_list().await?;
+
+            for (i, nic) in vm.nics.iter().enumerate() {
+                if let Some(host) = nic.host {
+                    if !host_nics.iter().any(|n| n.nicid == host) {
+                        problems.push(ConfigProblem::new(
+                            format!("nics[{i}].host"),
+                            format!("There is no host NIC {host}"),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if !vm.disks.is_empty() {
+            let media = self.media_list(vm.gid).await?;
+
+            for (i, disk) in vm.disks.iter().enumerate() {
+                match media.iter().find(|m| m.mid == disk.mid) {
+                    None => problems.push(ConfigProblem::new(
+                        format!("disks[{i}].mid"),
+                        format!("Media {} is not available to group {}", disk.mid, vm.gid),
+                    )),
+                    Some(m) if m.readonly && !disk.readonly => problems.push(ConfigProblem::new(
+                        format!("disks[{i}].readonly"),
+                        format!("Media {} is read-only", disk.mid),
+                    )),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        Ok(problems)
+    }
+}
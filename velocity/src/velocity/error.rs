@@ -2,8 +2,8 @@
 use std::{error::Error, fmt::Display};
 
 use serde::Deserialize;
-use wasm_bindgen::prelude::wasm_bindgen;
-use wasm_bindgen::JsValue;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
 /// An enumeration of all possible errors that can result from this API
 #[derive(Debug)]
@@ -12,8 +12,13 @@ pub enum VelocityError {
     APIError(VelocityAPIError),
     /// An error thrown by the reqwest crate, regarding client-side errors
     Reqwest(reqwest::Error),
+    /// An I/O error, e.g. while reading a file to upload
+    IO(std::io::Error),
     /// An error by the client / user of this library
     Client(ClientError),
+    /// The hypervisor (or something in between) responded with something that is not
+    /// a valid response or a Velocity API error
+    UnexpectedResponse(UnexpectedResponse),
 }
 
 /// A ClientError is an error that regards the user of this API
@@ -21,14 +26,68 @@ pub enum VelocityError {
 pub enum ClientError {
     /// An authkey is required, but there is none
     NotAuthenticated,
+    /// A transfer has been cancelled through its `CancellationToken`
+    Cancelled {
+        /// The amount of bytes that were transferred before the cancellation
+        transferred: u64,
+    },
+    /// The checksum of transferred data does not match the expected one
+    ChecksumMismatch {
+        /// The SHA-256 checksum that was expected, as lowercase hex
+        expected: String,
+        /// The SHA-256 checksum that was found, as lowercase hex
+        actual: String,
+    },
+    /// A value could not be converted, e.g. a request body or a value passed from JavaScript
+    InvalidValue(String),
+    /// No resource of a kind has the supplied name
+    UnknownName {
+        /// The kind of resource, e.g. `group`
+        kind: &'static str,
+        /// The name that has been looked up
+        name: String,
+    },
+    /// More than one resource of a kind has the supplied name
+    AmbiguousName {
+        /// The kind of resource, e.g. `group`
+        kind: &'static str,
+        /// The name that has been looked up
+        name: String,
+        /// A description of every matching resource, including its id
+        candidates: Vec<String>,
+    },
 }
 
 impl ClientError {
+    /// Returns a human-readable description of this error
     pub fn message(&self) -> String {
         match self {
-            Self::NotAuthenticated => "This client is not authenticated",
+            Self::NotAuthenticated => "This client is not authenticated".to_owned(),
+            Self::Cancelled { transferred } => {
+                format!(
+                    "The transfer has been cancelled after {} bytes",
+                    transferred
+                )
+            }
+            Self::ChecksumMismatch { expected, actual } => {
+                format!(
+                    "Checksum mismatch: expected SHA-256 {}, got {}",
+                    expected, actual
+                )
+            }
+            Self::InvalidValue(message) => format!("Invalid value: {}", message),
+            Self::UnknownName { kind, name } => format!("There is no {} named '{}'", kind, name),
+            Self::AmbiguousName {
+                kind,
+                name,
+                candidates,
+            } => format!(
+                "The {} name '{}' is ambiguous, it matches {}",
+                kind,
+                name,
+                candidates.join(", ")
+            ),
         }
-        .to_owned()
     }
 }
 
@@ -39,6 +98,199 @@ pub struct VelocityAPIError {
     pub code: u32,
     /// A message describing the error in a human-readable format
     pub message: String,
+    /// The HTTP status code the hypervisor responded with
+    #[serde(skip)]
+    pub status: u16,
+}
+
+/// A response that could not be interpreted, e.g. a proxy error page or an empty `502`
+#[derive(Debug)]
+pub struct UnexpectedResponse {
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The `Content-Type` header of the response, if present
+    pub content_type: Option<String>,
+    /// The raw body, lossily converted to UTF-8 and truncated to `MAX_BODY_LEN` bytes
+    pub body: String,
+    /// The error that occured while deserializing the body, if any
+    source: Option<serde_json::Error>,
+}
+
+impl UnexpectedResponse {
+    /// The maximum amount of bytes of the body that are retained
+    pub const MAX_BODY_LEN: usize = 512;
+
+    /// Creates a new `UnexpectedResponse`, truncating the body
+    /// # Arguments
+    /// * `status` - The HTTP status code of the response
+    /// * `content_type` - The `Content-Type` header of the response
+    /// * `body` - The full raw body
+    /// * `source` - The error that occured while deserializing the body
+    pub fn new(
+        status: u16,
+        content_type: Option<String>,
+        body: &[u8],
+        source: Option<serde_json::Error>,
+    ) -> Self {
+        let body =
+            String::from_utf8_lossy(&body[..body.len().min(Self::MAX_BODY_LEN)]).into_owned();
+
+        Self {
+            status,
+            content_type,
+            body,
+            source,
+        }
+    }
+}
+
+impl Display for UnexpectedResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unexpected response with status {} ({})",
+            self.status,
+            self.content_type.as_deref().unwrap_or("no content type")
+        )?;
+
+        match self.body.trim() {
+            "" => write!(f, " and an empty body"),
+            body => write!(f, ": {}", body),
+        }
+    }
+}
+
+impl VelocityAPIError {
+    /// Classifies this error into one of the error categories the hypervisor documents
+    pub fn kind(&self) -> VelocityErrorKind {
+        VelocityErrorKind::from_status(self.status)
+    }
+}
+
+/// The categories of errors the hypervisor documents in its `Errors.md` article.
+///
+/// Every error the hypervisor responds with falls into exactly one of these, which
+/// allows callers to react to errors without matching on the error message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VelocityErrorKind {
+    /// The request is malformed or contains invalid values (`400`)
+    BadRequest,
+    /// The supplied authkey is invalid or has expired (`401`)
+    InvalidAuthkey,
+    /// The user is missing a permission required for this action (`403`)
+    PermissionDenied,
+    /// The requested resource does not exist (`404`)
+    NotFound,
+    /// The resource already exists or is still in use (`409`)
+    Conflict,
+    /// The action would exceed a quota or the available space (`413` / `507`)
+    QuotaExceeded,
+    /// The hypervisor failed to fulfill a valid request (`5xx`)
+    Internal,
+    /// This client has no authkey to authenticate the request with
+    NotAuthenticated,
+    /// The transfer has been cancelled by the caller
+    Cancelled,
+    /// The transferred data has been corrupted
+    ChecksumMismatch,
+    /// The request could not be transmitted, the response could not be received
+    /// or a local I/O operation failed
+    Transport,
+    /// An error that does not fit any of the other categories
+    Other,
+}
+
+impl VelocityErrorKind {
+    /// Maps a HTTP status code the hypervisor responded with to its error kind
+    /// # Arguments
+    /// * `status` - The HTTP status code to classify
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            400 => Self::BadRequest,
+            401 => Self::InvalidAuthkey,
+            403 => Self::PermissionDenied,
+            404 => Self::NotFound,
+            409 => Self::Conflict,
+            413 | 507 => Self::QuotaExceeded,
+            500..=599 => Self::Internal,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl Display for VelocityErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::BadRequest => "bad request",
+                Self::InvalidAuthkey => "invalid authkey",
+                Self::PermissionDenied => "permission denied",
+                Self::NotFound => "not found",
+                Self::Conflict => "conflict",
+                Self::QuotaExceeded => "quota exceeded",
+                Self::Internal => "internal hypervisor error",
+                Self::NotAuthenticated => "not authenticated",
+                Self::Cancelled => "cancelled",
+                Self::ChecksumMismatch => "checksum mismatch",
+                Self::Transport => "transport error",
+                Self::Other => "other",
+            }
+        )
+    }
+}
+
+impl VelocityError {
+    /// Classifies this error into a `VelocityErrorKind`
+    pub fn kind(&self) -> VelocityErrorKind {
+        match self {
+            Self::APIError(e) => e.kind(),
+            Self::Reqwest(_) | Self::IO(_) => VelocityErrorKind::Transport,
+            Self::Client(ClientError::NotAuthenticated) => VelocityErrorKind::NotAuthenticated,
+            Self::Client(ClientError::Cancelled { .. }) => VelocityErrorKind::Cancelled,
+            Self::Client(ClientError::ChecksumMismatch { .. }) => {
+                VelocityErrorKind::ChecksumMismatch
+            }
+            Self::Client(ClientError::InvalidValue(_) | ClientError::AmbiguousName { .. }) => {
+                VelocityErrorKind::Other
+            }
+            Self::Client(ClientError::UnknownName { .. }) => VelocityErrorKind::NotFound,
+            Self::UnexpectedResponse(e) => match e.status {
+                200 => VelocityErrorKind::Other,
+                status => VelocityErrorKind::from_status(status),
+            },
+        }
+    }
+
+    /// Returns `true` if the requested resource does not exist
+    pub fn is_not_found(&self) -> bool {
+        self.kind() == VelocityErrorKind::NotFound
+    }
+
+    /// Returns `true` if the user lacks a permission for the requested action
+    pub fn is_permission_denied(&self) -> bool {
+        self.kind() == VelocityErrorKind::PermissionDenied
+    }
+
+    /// Returns `true` if the requested action would exceed a quota
+    pub fn is_quota_exceeded(&self) -> bool {
+        self.kind() == VelocityErrorKind::QuotaExceeded
+    }
+
+    /// Returns `true` if the request conflicts with an existing resource
+    pub fn is_conflict(&self) -> bool {
+        self.kind() == VelocityErrorKind::Conflict
+    }
+
+    /// Returns `true` if the client needs to (re)authenticate to continue,
+    /// either because there is no authkey or because the authkey is no longer valid
+    pub fn is_auth_error(&self) -> bool {
+        matches!(
+            self.kind(),
+            VelocityErrorKind::InvalidAuthkey | VelocityErrorKind::NotAuthenticated
+        )
+    }
 }
 
 impl From<reqwest::Error> for VelocityError {
@@ -47,21 +299,32 @@ impl From<reqwest::Error> for VelocityError {
     }
 }
 
+impl From<std::io::Error> for VelocityError {
+    fn from(value: std::io::Error) -> Self {
+        VelocityError::IO(value)
+    }
+}
+
 impl Display for VelocityError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Reqwest(e) => e.fmt(f),
+            Self::IO(e) => e.fmt(f),
             Self::APIError(e) => write!(f, "{}: {}", e.code, e.message),
-            Self::Client(e) => match e {
-                ClientError::NotAuthenticated => write!(f, "{}", e.message()),
-            },
+            Self::Client(e) => write!(f, "{}", e.message()),
+            Self::UnexpectedResponse(e) => e.fmt(f),
         }
     }
 }
 
 impl Error for VelocityError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+        match self {
+            Self::Reqwest(e) => Some(e),
+            Self::IO(e) => Some(e),
+            Self::UnexpectedResponse(e) => e.source.as_ref().map(|e| e as &(dyn Error + 'static)),
+            Self::APIError(_) | Self::Client(_) => None,
+        }
     }
 
     fn description(&self) -> &str {
@@ -73,6 +336,14 @@ impl Error for VelocityError {
     }
 }
 
+#[cfg(feature = "wasm")]
+impl From<serde_wasm_bindgen::Error> for VelocityError {
+    fn from(value: serde_wasm_bindgen::Error) -> Self {
+        VelocityError::Client(ClientError::InvalidValue(value.to_string()))
+    }
+}
+
+#[cfg(feature = "wasm")]
 impl From<VelocityError> for JsValue {
     fn from(value: VelocityError) -> Self {
         JSVelocityError {
@@ -82,12 +353,14 @@ impl From<VelocityError> for JsValue {
     }
 }
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 #[allow(dead_code)]
 struct JSVelocityError {
     message: String,
 }
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 #[allow(dead_code)]
 impl JSVelocityError {
@@ -96,3 +369,26 @@ impl JSVelocityError {
         self.message.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(code: u32, status: u16) -> VelocityAPIError {
+        VelocityAPIError {
+            code,
+            message: String::new(),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_kind_from_status() {
+        assert_eq!(api_error(1404, 404).kind(), VelocityErrorKind::NotFound);
+        assert_eq!(api_error(400, 400).kind(), VelocityErrorKind::BadRequest);
+        assert_eq!(api_error(0, 409).kind(), VelocityErrorKind::Conflict);
+        assert_eq!(api_error(0, 507).kind(), VelocityErrorKind::QuotaExceeded);
+        assert_eq!(api_error(0, 502).kind(), VelocityErrorKind::Internal);
+        assert_eq!(api_error(1700, 418).kind(), VelocityErrorKind::Other);
+    }
+}
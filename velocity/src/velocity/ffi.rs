@@ -0,0 +1,474 @@
+//! A C API on top of the blocking client, the header for it lives in `include/velocity.h`
+//!
+//! Clients are opaque `VelocityClient` handles. Every function returns a `VelocityStatus`,
+//! results are written to out parameters. If a call fails, `velocity_last_error()` describes
+//! what went wrong. Lists are returned as JSON arrays in strings that have to be released
+//! with `velocity_string_free()`
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_void, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr::{self, NonNull},
+};
+
+use serde::Serialize;
+
+use crate::{
+    blocking,
+    endpoints::v::v_vm_efi::EFIVMConfig,
+    error::{VelocityError, VelocityErrorKind},
+    transfer::{TransferOptions, TransferProgress},
+    GID, MPID, VMID,
+};
+
+/// An authenticated client, created by `velocity_login()` and released by `velocity_client_free()`
+pub struct VelocityClient(blocking::Velocity);
+
+/// The outcome of a call, every kind of `VelocityError` has its own status
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VelocityStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// An argument is a null pointer, not valid UTF-8 or otherwise malformed
+    InvalidArgument = 1,
+    /// The request is malformed or contains invalid values
+    BadRequest = 2,
+    /// The authkey is invalid or has expired, wrong credentials are `PermissionDenied`
+    InvalidAuthkey = 3,
+    /// The user is missing a permission required for this action or the credentials are wrong
+    PermissionDenied = 4,
+    /// The requested resource does not exist
+    NotFound = 5,
+    /// The resource already exists or is still in use
+    Conflict = 6,
+    /// The action would exceed a quota or the available space
+    QuotaExceeded = 7,
+    /// The hypervisor failed to fulfill a valid request
+    Internal = 8,
+    /// The client has no authkey, e.g. after `velocity_logout()`
+    NotAuthenticated = 9,
+    /// The transfer has been cancelled
+    Cancelled = 10,
+    /// The transferred data has been corrupted
+    ChecksumMismatch = 11,
+    /// The hypervisor could not be reached or a local file could not be read
+    Transport = 12,
+    /// An error that does not fit any of the other categories
+    Other = 13,
+    /// The library panicked, this is a bug
+    Panic = 14,
+}
+
+impl From<VelocityErrorKind> for VelocityStatus {
+    fn from(kind: VelocityErrorKind) -> Self {
+        match kind {
+            VelocityErrorKind::BadRequest => Self::BadRequest,
+            VelocityErrorKind::InvalidAuthkey => Self::InvalidAuthkey,
+            VelocityErrorKind::PermissionDenied => Self::PermissionDenied,
+            VelocityErrorKind::NotFound => Self::NotFound,
+            VelocityErrorKind::Conflict => Self::Conflict,
+            VelocityErrorKind::QuotaExceeded => Self::QuotaExceeded,
+            VelocityErrorKind::Internal => Self::Internal,
+            VelocityErrorKind::NotAuthenticated => Self::NotAuthenticated,
+            VelocityErrorKind::Cancelled => Self::Cancelled,
+            VelocityErrorKind::ChecksumMismatch => Self::ChecksumMismatch,
+            VelocityErrorKind::Transport => Self::Transport,
+            VelocityErrorKind::Other => Self::Other,
+        }
+    }
+}
+
+/// Receives progress updates of an upload: the bytes uploaded until now, the total amount
+/// of bytes and the `user_data` passed along with the callback. It is called on the
+/// thread that started the upload
+pub type VelocityProgressCallback =
+    Option<unsafe extern "C" fn(done: u64, total: u64, user_data: *mut c_void)>;
+
+thread_local! {
+    /// The message of the last error on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An error that is reported to the caller of a function
+struct FfiError {
+    status: VelocityStatus,
+    message: String,
+}
+
+impl From<VelocityError> for FfiError {
+    fn from(e: VelocityError) -> Self {
+        Self {
+            status: e.kind().into(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl FfiError {
+    /// Creates an error for an invalid argument
+    /// # Arguments
+    /// * `message` - What is wrong with the argument
+    fn invalid(message: String) -> Self {
+        Self {
+            status: VelocityStatus::InvalidArgument,
+            message,
+        }
+    }
+}
+
+/// Authenticates to a hypervisor and creates a new client. Wrong credentials fail with
+/// `VELOCITY_STATUS_PERMISSION_DENIED`
+/// # Arguments
+/// * `base_url` - The base url to route all requests to
+/// * `username` - The username needed for authentication
+/// * `password` - The password needed for authentication
+/// * `client` - Receives the new client
+/// # Safety
+/// All strings have to be valid, null terminated strings and `client` has to point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn velocity_login(
+    base_url: *const c_char,
+    username: *const c_char,
+    password: *const c_char,
+    client: *mut *mut VelocityClient,
+) -> VelocityStatus {
+    run(|| {
+        let client = out_arg(client, "client")?;
+        let velocity = blocking::Velocity::new(
+            str_arg(base_url, "base_url")?,
+            str_arg(username, "username")?,
+            str_arg(password, "password")?,
+        )?;
+
+        client.write(Box::into_raw(Box::new(VelocityClient(velocity))));
+        Ok(())
+    })
+}
+
+/// Invalidates the authkey of a client, the client can't be used for requests afterwards
+/// # Arguments
+/// * `client` - The client to log out
+/// # Safety
+/// `client` has to be a client created by `velocity_login()` that has not been freed
+#[no_mangle]
+pub unsafe extern "C" fn velocity_logout(client: *const VelocityClient) -> VelocityStatus {
+    run(|| Ok(client_arg(client)?.deauthenticate()?))
+}
+
+/// Releases a client, passing a null pointer does nothing. This does not log out,
+/// call `velocity_logout()` first
+/// # Arguments
+/// * `client` - The client to release
+/// # Safety
+/// `client` has to be a client created by `velocity_login()` that has not been freed
+#[no_mangle]
+pub unsafe extern "C" fn velocity_client_free(client: *mut VelocityClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Returns the message of the last error on the calling thread, or null if the last
+/// call succeeded. The message is valid until the next call on this thread
+#[no_mangle]
+pub extern "C" fn velocity_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Releases a string returned by this library, passing a null pointer does nothing
+/// # Arguments
+/// * `string` - The string to release
+/// # Safety
+/// `string` has to be a string returned by this library that has not been freed
+#[no_mangle]
+pub unsafe extern "C" fn velocity_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Lists all users as a JSON array of `{"uid", "name"}` objects
+/// # Arguments
+/// * `client` - The client to list with
+/// * `json` - Receives the list, release it with `velocity_string_free()`
+/// # Safety
+/// `client` has to be a valid client and `json` has to point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn velocity_user_list(
+    client: *const VelocityClient,
+    json: *mut *mut c_char,
+) -> VelocityStatus {
+    run(|| {
+        let json = out_arg(json, "json")?;
+        write_json(json, &client_arg(client)?.user_list()?)
+    })
+}
+
+/// Lists all groups the user has permissions on as a JSON array of `{"gid", "name", "parent_gid"}` objects
+/// # Arguments
+/// * `client` - The client to list with
+/// * `json` - Receives the list, release it with `velocity_string_free()`
+/// # Safety
+/// `client` has to be a valid client and `json` has to point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn velocity_group_list(
+    client: *const VelocityClient,
+    json: *mut *mut c_char,
+) -> VelocityStatus {
+    run(|| {
+        let json = out_arg(json, "json")?;
+        write_json(json, &client_arg(client)?.group_list()?)
+    })
+}
+
+/// Lists all media pools assigned to a group as a JSON array
+/// # Arguments
+/// * `client` - The client to list with
+/// * `gid` - The group to list the pools of
+/// * `json` - Receives the list, release it with `velocity_string_free()`
+/// # Safety
+/// `client` has to be a valid client and `json` has to point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn velocity_pool_list(
+    client: *const VelocityClient,
+    gid: GID,
+    json: *mut *mut c_char,
+) -> VelocityStatus {
+    run(|| {
+        let json = out_arg(json, "json")?;
+        write_json(json, &client_arg(client)?.pool_list(gid)?)
+    })
+}
+
+/// Lists all media a group can access as a JSON array
+/// # Arguments
+/// * `client` - The client to list with
+/// * `gid` - The group to list the media of
+/// * `json` - Receives the list, release it with `velocity_string_free()`
+/// # Safety
+/// `client` has to be a valid client and `json` has to point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn velocity_media_list(
+    client: *const VelocityClient,
+    gid: GID,
+    json: *mut *mut c_char,
+) -> VelocityStatus {
+    run(|| {
+        let json = out_arg(json, "json")?;
+        write_json(json, &client_arg(client)?.media_list(gid)?)
+    })
+}
+
+/// Lists all host NICs available for bridging as a JSON array
+/// # Arguments
+/// * `client` - The client to list with
+/// * `json` - Receives the list, release it with `velocity_string_free()`
+/// # Safety
+/// `client` has to be a valid client and `json` has to point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn velocity_nic_list(
+    client: *const VelocityClient,
+    json: *mut *mut c_char,
+) -> VelocityStatus {
+    run(|| {
+        let json = out_arg(json, "json")?;
+        write_json(json, &client_arg(client)?.nic_list()?)
+    })
+}
+
+/// Uploads a local file as new media, the file is hashed first so the hypervisor can verify it
+/// # Arguments
+/// * `client` - The client to upload with
+/// * `mpid` - The media pool to upload to
+/// * `gid` - The group the media belongs to
+/// * `name` - The name of the new media
+/// * `ty` - The type of the new media, e.g. `DISK` or `ISO`
+/// * `readonly` - If the media should be read-only
+/// * `path` - The path to the file to upload
+/// * `progress` - Receives progress updates, may be null
+/// * `user_data` - Passed to `progress` as is
+/// * `mid` - Receives the media id of the new media, release it with `velocity_string_free()`
+/// # Safety
+/// `client` has to be a valid client, all strings have to be valid, null terminated strings
+/// and `mid` has to point to writable memory
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn velocity_media_upload(
+    client: *const VelocityClient,
+    mpid: MPID,
+    gid: GID,
+    name: *const c_char,
+    ty: *const c_char,
+    readonly: bool,
+    path: *const c_char,
+    progress: VelocityProgressCallback,
+    user_data: *mut c_void,
+    mid: *mut *mut c_char,
+) -> VelocityStatus {
+    run(|| {
+        let client = client_arg(client)?;
+        let mid = out_arg(mid, "mid")?;
+        let name = str_arg(name, "name")?;
+        let ty = str_arg(ty, "type")?;
+        let path = str_arg(path, "path")?;
+        let progress = CallbackProgress {
+            progress,
+            user_data,
+        };
+
+        let res = client.media_upload_file(
+            mpid,
+            gid,
+            name,
+            ty,
+            readonly,
+            Path::new(path),
+            TransferOptions::default(),
+            move |p: &TransferProgress| progress.report(p),
+        )?;
+
+        mid.write(string(res.mid)?);
+        Ok(())
+    })
+}
+
+/// Creates a new EFI virtual machine from a JSON configuration like
+/// `{"name", "gid", "cpus", "memory_mib", "displays", "disks", "nics", "autostart", "rosetta"}`
+/// # Arguments
+/// * `client` - The client to create the virtual machine with
+/// * `config` - The configuration as JSON
+/// * `vmid` - Receives the id of the new virtual machine
+/// # Safety
+/// `client` has to be a valid client, `config` a valid, null terminated string
+/// and `vmid` has to point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn velocity_vm_efi_create(
+    client: *const VelocityClient,
+    config: *const c_char,
+    vmid: *mut VMID,
+) -> VelocityStatus {
+    run(|| {
+        let client = client_arg(client)?;
+        let vmid = out_arg(vmid, "vmid")?;
+        let config: EFIVMConfig = serde_json::from_str(str_arg(config, "config")?)
+            .map_err(|e| FfiError::invalid(format!("Invalid configuration: {}", e)))?;
+
+        let id = client.vm_efi_create(config)?;
+
+        vmid.write(id);
+        Ok(())
+    })
+}
+
+/// Reports progress to a C callback
+struct CallbackProgress {
+    progress: VelocityProgressCallback,
+    user_data: *mut c_void,
+}
+
+// The callback is only ever called on the thread that started the upload,
+// as the blocking client drives all requests on the calling thread
+unsafe impl Send for CallbackProgress {}
+unsafe impl Sync for CallbackProgress {}
+
+impl CallbackProgress {
+    /// Calls the callback, if there is one
+    /// # Arguments
+    /// * `progress` - The current progress
+    fn report(&self, progress: &TransferProgress) {
+        if let Some(callback) = self.progress {
+            unsafe {
+                callback(
+                    progress.done,
+                    progress.total.unwrap_or_default(),
+                    self.user_data,
+                )
+            }
+        }
+    }
+}
+
+/// Runs the body of a function, turning errors and panics into a status
+/// and recording the error message for `velocity_last_error()`
+/// # Arguments
+/// * `f` - The body to run
+fn run(f: impl FnOnce() -> Result<(), FfiError>) -> VelocityStatus {
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => (VelocityStatus::Ok, None),
+        Ok(Err(e)) => (e.status, Some(e.message)),
+        Err(_) => (
+            VelocityStatus::Panic,
+            Some("The velocity library panicked".to_owned()),
+        ),
+    };
+
+    LAST_ERROR.with(|e| {
+        *e.borrow_mut() = message.map(|m| CString::new(m.replace('\0', " ")).expect("No nul"))
+    });
+
+    status
+}
+
+/// Borrows a string argument
+/// # Arguments
+/// * `ptr` - The null terminated string
+/// * `name` - The name of the argument for the error message
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if ptr.is_null() {
+        return Err(FfiError::invalid(format!("'{}' is null", name)));
+    }
+
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| FfiError::invalid(format!("'{}' is not valid UTF-8", name)))
+}
+
+/// Borrows the client argument
+/// # Arguments
+/// * `client` - The client handle
+unsafe fn client_arg<'a>(
+    client: *const VelocityClient,
+) -> Result<&'a blocking::Velocity, FfiError> {
+    client
+        .as_ref()
+        .map(|c| &c.0)
+        .ok_or_else(|| FfiError::invalid("'client' is null".to_owned()))
+}
+
+/// Checks an out parameter, this has to happen before any request is made or
+/// any value is allocated for it so a null pointer neither leaks nor has side effects
+/// # Arguments
+/// * `out` - The out parameter
+/// * `name` - The name of the out parameter for the error message
+fn out_arg<T>(out: *mut T, name: &str) -> Result<NonNull<T>, FfiError> {
+    NonNull::new(out).ok_or_else(|| FfiError::invalid(format!("'{}' is null", name)))
+}
+
+/// Serializes a value to JSON and writes it to an out parameter as an owned string
+/// # Arguments
+/// * `out` - The out parameter
+/// * `value` - The value to serialize
+unsafe fn write_json<T: Serialize>(out: NonNull<*mut c_char>, value: &T) -> Result<(), FfiError> {
+    let json = serde_json::to_string(value).map_err(|e| FfiError {
+        status: VelocityStatus::Other,
+        message: e.to_string(),
+    })?;
+
+    out.write(string(json)?);
+    Ok(())
+}
+
+/// Converts a string to an owned C string
+/// # Arguments
+/// * `value` - The string to convert
+fn string(value: String) -> Result<*mut c_char, FfiError> {
+    CString::new(value)
+        .map(CString::into_raw)
+        .map_err(|e| FfiError {
+            status: VelocityStatus::Other,
+            message: e.to_string(),
+        })
+}
@@ -0,0 +1,106 @@
+//! Distinct id types for every kind of resource, so the compiler catches swapped arguments.
+//! They are transparent wrappers around an `i64`, both in JSON and in the C API
+//! ```compile_fail
+//! use velocity::{Velocity, GID, MPID};
+//!
+//! # async fn example(velocity: Velocity) {
+//! let (gid, mpid) = (GID(1), MPID(0));
+//! velocity.pool_assign(mpid, gid, 1000, true, false).await;
+//! # }
+//! ```
+use std::{fmt, num::ParseIntError, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+/// The id of a user
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct UID(pub i64);
+
+/// The id of a group
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct GID(pub i64);
+
+/// The id of a permission
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct PID(pub i64);
+
+/// The id of a media pool
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct MPID(pub i64);
+
+/// The id of a host NIC
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct NICID(pub i64);
+
+/// The id of a virtual machine
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct VMID(pub i64);
+
+/// The id of a piece of media, a UUID assigned by the hypervisor
+#[allow(clippy::upper_case_acronyms)]
+pub type MID = String;
+
+/// Implements the conversions every id supports
+macro_rules! id_impls {
+    ($($name:ident),*) => {$(
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map(Self)
+            }
+        }
+
+        impl From<i64> for $name {
+            fn from(id: i64) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+    )*};
+}
+
+id_impls!(UID, GID, PID, MPID, NICID, VMID);
@@ -0,0 +1,147 @@
+//! Detection and conversion of local disk images. The hypervisor stores raw data,
+//! so images in other formats have to be converted before they get uploaded
+
+use std::{fmt::Display, io, path::Path, pin::Pin};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+mod qcow2;
+pub use qcow2::Qcow2Image;
+
+/// The offset of the standard identifier of the first ISO9660 volume descriptor
+const ISO9660_MAGIC_OFFSET: usize = 0x8001;
+
+/// The amount of bytes from the start of an image needed to detect its format
+pub const DETECT_LEN: usize = ISO9660_MAGIC_OFFSET + 5;
+
+/// The format of a disk image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Raw data, the format the hypervisor expects
+    Raw,
+    /// An ISO9660 file system, e.g. an installer
+    Iso9660,
+    /// A QEMU copy-on-write image
+    Qcow2,
+    /// A VMware virtual disk, either sparse or a descriptor
+    Vmdk,
+}
+
+impl ImageFormat {
+    /// Detects the format of an image from its first bytes, falling back to raw data
+    /// # Arguments
+    /// * `header` - The start of the image, at least `DETECT_LEN` bytes to detect ISO9660
+    pub fn detect(header: &[u8]) -> ImageFormat {
+        if header.starts_with(qcow2::MAGIC) {
+            ImageFormat::Qcow2
+        } else if header.starts_with(b"KDMV")
+            || header.starts_with(b"COWD")
+            || header.starts_with(b"# Disk DescriptorFile")
+        {
+            ImageFormat::Vmdk
+        } else if header.get(ISO9660_MAGIC_OFFSET..DETECT_LEN) == Some(b"CD001") {
+            ImageFormat::Iso9660
+        } else {
+            ImageFormat::Raw
+        }
+    }
+
+    /// Detects the format of a local image
+    /// # Arguments
+    /// * `path` - The path to the image
+    pub async fn detect_file(path: &Path) -> io::Result<ImageFormat> {
+        let mut header = Vec::with_capacity(DETECT_LEN);
+        tokio::fs::File::open(path)
+            .await?
+            .take(DETECT_LEN as u64)
+            .read_to_end(&mut header)
+            .await?;
+
+        Ok(ImageFormat::detect(&header))
+    }
+
+    /// The type of media to create for images of this format on the hypervisor
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Iso9660 => "ISO",
+            _ => "DISK",
+        }
+    }
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageFormat::Raw => write!(f, "raw"),
+            ImageFormat::Iso9660 => write!(f, "ISO9660"),
+            ImageFormat::Qcow2 => write!(f, "qcow2"),
+            ImageFormat::Vmdk => write!(f, "VMDK"),
+        }
+    }
+}
+
+/// A local image opened to be read as raw data
+pub struct RawImage {
+    /// The format the image is stored in
+    pub format: ImageFormat,
+    /// The size of the raw data
+    pub size: u64,
+    /// Provides the raw data, converting it on the fly if needed
+    pub reader: Pin<Box<dyn AsyncRead + Send>>,
+}
+
+impl RawImage {
+    /// Opens a local image, detecting its format and converting it to raw data while reading
+    /// # Arguments
+    /// * `path` - The path to the image
+    pub async fn open(path: &Path) -> io::Result<RawImage> {
+        let format = ImageFormat::detect_file(path).await?;
+
+        let (size, reader): (u64, Pin<Box<dyn AsyncRead + Send>>) = match format {
+            ImageFormat::Raw | ImageFormat::Iso9660 => {
+                let file = tokio::fs::File::open(path).await?;
+                (file.metadata().await?.len(), Box::pin(file))
+            }
+            ImageFormat::Qcow2 => {
+                let image = Qcow2Image::open(path).await?;
+                (image.virtual_size(), Box::pin(image.into_raw()))
+            }
+            ImageFormat::Vmdk => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "VMDK images cannot be converted yet, convert them to raw data first",
+                ))
+            }
+        };
+
+        Ok(RawImage {
+            format,
+            size,
+            reader,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let mut iso = vec![0u8; DETECT_LEN];
+        iso[ISO9660_MAGIC_OFFSET..].copy_from_slice(b"CD001");
+
+        assert_eq!(ImageFormat::detect(&iso), ImageFormat::Iso9660);
+        assert_eq!(
+            ImageFormat::detect(b"QFI\xfb\0\0\0\x03"),
+            ImageFormat::Qcow2
+        );
+        assert_eq!(ImageFormat::detect(b"KDMV\x01\0\0\0"), ImageFormat::Vmdk);
+        assert_eq!(
+            ImageFormat::detect(b"# Disk DescriptorFile\nversion=1"),
+            ImageFormat::Vmdk
+        );
+        assert_eq!(ImageFormat::detect(&[0u8; 512]), ImageFormat::Raw);
+        assert_eq!(ImageFormat::detect(&[]), ImageFormat::Raw);
+    }
+}
@@ -0,0 +1,339 @@
+//! Reading QEMU copy-on-write (qcow2) images as raw data
+
+use std::{io, io::SeekFrom, path::Path};
+
+use bytes::Bytes;
+use flate2::{Decompress, FlushDecompress};
+use futures_util::Stream;
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::StreamReader;
+
+/// The magic bytes every qcow2 image starts with
+pub(super) const MAGIC: &[u8] = b"QFI\xfb";
+
+/// The bits of L1 and L2 table entries holding an offset into the image
+const OFFSET_MASK: u64 = 0x00ff_ffff_ffff_fe00;
+/// Set in L2 table entries of compressed clusters
+const COMPRESSED: u64 = 1 << 62;
+/// Set in L2 table entries of clusters that read as zeros, only in version 3
+const ZERO: u64 = 1;
+
+/// The incompatible feature bits of version 3 images
+const FEATURE_CORRUPT: u64 = 1 << 1;
+const FEATURE_EXTERNAL_DATA: u64 = 1 << 2;
+const FEATURE_COMPRESSION_TYPE: u64 = 1 << 3;
+const FEATURE_EXTENDED_L2: u64 = 1 << 4;
+/// The incompatible features this reader understands, the dirty bit only concerns refcounts
+const FEATURES_KNOWN: u64 = 1 | FEATURE_COMPRESSION_TYPE;
+
+/// The largest L1 table that is loaded, the same limit QEMU uses
+const MAX_L1_SIZE: u64 = 32 * 1024 * 1024;
+
+/// A qcow2 image that can be read as raw data. Images with backing files,
+/// encryption or compression other than deflate are not supported
+pub struct Qcow2Image {
+    file: File,
+    /// The size of a cluster as a power of two
+    cluster_bits: u32,
+    /// The size of the virtual disk
+    size: u64,
+    /// The L1 table pointing to the L2 tables
+    l1: Vec<u64>,
+}
+
+impl Qcow2Image {
+    /// Opens a qcow2 image and reads its header and L1 table
+    /// # Arguments
+    /// * `path` - The path to the image
+    pub async fn open(path: &Path) -> io::Result<Qcow2Image> {
+        let mut file = File::open(path).await?;
+
+        let mut header = Vec::with_capacity(112);
+        (&mut file).take(112).read_to_end(&mut header).await?;
+        if header.len() < 72 || !header.starts_with(MAGIC) {
+            return Err(invalid("not a qcow2 image"));
+        }
+
+        let version = be_u32(&header, 4);
+        let backing_file_offset = be_u64(&header, 8);
+        let cluster_bits = be_u32(&header, 20);
+        let size = be_u64(&header, 24);
+        let crypt_method = be_u32(&header, 32);
+        let l1_size = be_u32(&header, 36) as u64;
+        let l1_table_offset = be_u64(&header, 40);
+
+        if version != 2 && version != 3 {
+            return Err(unsupported(format!(
+                "qcow2 version {version} is not supported"
+            )));
+        }
+        if !(9..=21).contains(&cluster_bits) {
+            return Err(invalid(format!("invalid cluster size 2^{cluster_bits}")));
+        }
+        if backing_file_offset != 0 {
+            return Err(unsupported(
+                "qcow2 images with a backing file are not supported",
+            ));
+        }
+        if crypt_method != 0 {
+            return Err(unsupported("encrypted qcow2 images are not supported"));
+        }
+
+        if version == 3 {
+            if header.len() < 104 {
+                return Err(invalid("truncated qcow2 header"));
+            }
+
+            let features = be_u64(&header, 72);
+            let header_length = be_u32(&header, 100) as usize;
+
+            if features & FEATURE_CORRUPT != 0 {
+                return Err(invalid("the qcow2 image is marked as corrupt"));
+            }
+            if features & FEATURE_EXTERNAL_DATA != 0 {
+                return Err(unsupported(
+                    "qcow2 images with external data files are not supported",
+                ));
+            }
+            if features & FEATURE_EXTENDED_L2 != 0 {
+                return Err(unsupported(
+                    "qcow2 images with subclusters are not supported",
+                ));
+            }
+            if features & !(FEATURES_KNOWN | FEATURE_EXTERNAL_DATA | FEATURE_EXTENDED_L2) != 0 {
+                return Err(unsupported(format!(
+                    "unknown qcow2 features {:#x}",
+                    features & !FEATURES_KNOWN
+                )));
+            }
+            // Only deflate (0) is supported, zstd (1) is not
+            if features & FEATURE_COMPRESSION_TYPE != 0
+                && header_length > 104
+                && header.get(104).is_some_and(|ty| *ty != 0)
+            {
+                return Err(unsupported(
+                    "qcow2 images compressed with zstd are not supported",
+                ));
+            }
+        }
+
+        if l1_size * 8 > MAX_L1_SIZE {
+            return Err(invalid("the qcow2 L1 table is too large"));
+        }
+
+        let mut table = vec![0u8; (l1_size * 8) as usize];
+        file.seek(SeekFrom::Start(l1_table_offset)).await?;
+        file.read_exact(&mut table).await?;
+
+        Ok(Qcow2Image {
+            file,
+            cluster_bits,
+            size,
+            l1: table.chunks_exact(8).map(|e| be_u64(e, 0)).collect(),
+        })
+    }
+
+    /// The size of the virtual disk, the amount of raw data this image provides
+    pub fn virtual_size(&self) -> u64 {
+        self.size
+    }
+
+    /// Converts the image into a reader that provides the raw data of the virtual disk
+    pub fn into_raw(self) -> impl AsyncRead + Send + 'static {
+        StreamReader::new(self.clusters())
+    }
+
+    /// Reads the virtual disk cluster by cluster, loading one L2 table at a time
+    fn clusters(mut self) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static {
+        let cluster_size = 1u64 << self.cluster_bits;
+        let l2_entries = cluster_size / 8;
+        let zeros = Bytes::from(vec![0u8; cluster_size as usize]);
+
+        async_stream::try_stream! {
+            let mut l2: Option<(usize, Vec<u64>)> = None;
+            let mut position = 0;
+
+            while position < self.size {
+                let cluster = position / cluster_size;
+                let len = cluster_size.min(self.size - position);
+                let l1_index = (cluster / l2_entries) as usize;
+
+                if l2.as_ref().map(|(index, _)| *index) != Some(l1_index) {
+                    let offset = self.l1.get(l1_index).copied().unwrap_or(0) & OFFSET_MASK;
+                    let table = if offset == 0 {
+                        Vec::new()
+                    } else {
+                        let mut table = vec![0u8; cluster_size as usize];
+                        self.file.seek(SeekFrom::Start(offset)).await?;
+                        self.file.read_exact(&mut table).await?;
+                        table.chunks_exact(8).map(|e| be_u64(e, 0)).collect()
+                    };
+                    l2 = Some((l1_index, table));
+                }
+
+                let entry = l2
+                    .as_ref()
+                    .and_then(|(_, table)| table.get((cluster % l2_entries) as usize))
+                    .copied()
+                    .unwrap_or(0);
+
+                let data = if entry & COMPRESSED != 0 {
+                    self.read_compressed(entry, len).await?
+                } else if entry & ZERO != 0 || entry & OFFSET_MASK == 0 {
+                    // Without backing files, unallocated clusters read as zeros as well
+                    zeros.slice(..len as usize)
+                } else {
+                    let mut data = vec![0u8; len as usize];
+                    self.file.seek(SeekFrom::Start(entry & OFFSET_MASK)).await?;
+                    self.file.read_exact(&mut data).await?;
+                    Bytes::from(data)
+                };
+
+                position += len;
+                yield data;
+            }
+        }
+    }
+
+    /// Reads and inflates a compressed cluster
+    /// # Arguments
+    /// * `entry` - The L2 table entry of the cluster
+    /// * `len` - The amount of data to take from the cluster
+    async fn read_compressed(&mut self, entry: u64, len: u64) -> io::Result<Bytes> {
+        // The entry holds the offset and the amount of additional 512 byte sectors
+        let offset_bits = 62 - (self.cluster_bits - 8);
+        let offset = entry & ((1 << offset_bits) - 1);
+        let sectors = ((entry & !COMPRESSED) >> offset_bits) + 1;
+        let compressed_len = sectors * 512 - (offset & 511);
+
+        let mut compressed = Vec::with_capacity(compressed_len as usize);
+        self.file.seek(SeekFrom::Start(offset)).await?;
+        (&mut self.file)
+            .take(compressed_len)
+            .read_to_end(&mut compressed)
+            .await?;
+
+        let mut data = vec![0u8; 1 << self.cluster_bits];
+        let mut inflate = Decompress::new(false);
+        inflate
+            .decompress(&compressed, &mut data, FlushDecompress::Finish)
+            .map_err(|e| invalid(format!("invalid compressed cluster: {e}")))?;
+
+        if inflate.total_out() < len {
+            return Err(invalid("truncated compressed cluster"));
+        }
+
+        data.truncate(len as usize);
+        Ok(Bytes::from(data))
+    }
+}
+
+/// Reads a big endian `u32` at `offset`
+fn be_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+/// Reads a big endian `u64` at `offset`
+fn be_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(data[offset..offset + 8].try_into().expect("8 bytes"))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn unsupported(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::DeflateEncoder, Compression};
+
+    use super::*;
+
+    /// Writes a version 3 image with 512 byte clusters to a temporary file
+    /// # Arguments
+    /// * `name` - The name of the file
+    /// * `size` - The size of the virtual disk
+    /// * `l2` - The L2 table, the only one of the image
+    /// * `data` - The clusters following the L2 table
+    fn write_image(name: &str, size: u64, l2: &[u64], data: &[u8]) -> std::path::PathBuf {
+        let mut image = vec![0u8; 3 * 512];
+        image[..4].copy_from_slice(MAGIC);
+        image[4..8].copy_from_slice(&3u32.to_be_bytes());
+        image[20..24].copy_from_slice(&9u32.to_be_bytes());
+        image[24..32].copy_from_slice(&size.to_be_bytes());
+        image[36..40].copy_from_slice(&1u32.to_be_bytes());
+        image[40..48].copy_from_slice(&512u64.to_be_bytes());
+        image[100..104].copy_from_slice(&104u32.to_be_bytes());
+        image[512..520].copy_from_slice(&1024u64.to_be_bytes());
+        for (i, entry) in l2.iter().enumerate() {
+            image[1024 + i * 8..1032 + i * 8].copy_from_slice(&entry.to_be_bytes());
+        }
+        image.extend_from_slice(data);
+
+        let path = std::env::temp_dir().join(format!("velocity-{}-{name}", std::process::id()));
+        std::fs::write(&path, image).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_qcow2_to_raw() {
+        let plain = vec![0xabu8; 512];
+        let packed: Vec<u8> = (0..512).map(|i| (i % 7) as u8).collect();
+        let tail = vec![0xcdu8; 100];
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&packed).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < 512);
+
+        // Clusters: data, unallocated, zero, compressed, partial data
+        let mut data = plain.clone();
+        data.extend_from_slice(&compressed);
+        data.resize(2 * 512, 0);
+        data.extend_from_slice(&tail);
+        data.resize(3 * 512, 0);
+
+        let l2 = [
+            (1 << 63) | (3 * 512),
+            0,
+            ZERO,
+            COMPRESSED | (4 * 512),
+            (1 << 63) | (5 * 512),
+        ];
+        let path = write_image("raw.qcow2", 4 * 512 + 100, &l2, &data);
+
+        let image = Qcow2Image::open(&path).await.unwrap();
+        assert_eq!(image.virtual_size(), 4 * 512 + 100);
+
+        let mut raw = Vec::new();
+        let mut reader = Box::pin(image.into_raw());
+        reader.read_to_end(&mut raw).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let mut expected = plain;
+        expected.extend_from_slice(&[0u8; 2 * 512]);
+        expected.extend_from_slice(&packed);
+        expected.extend_from_slice(&tail);
+        assert_eq!(raw, expected);
+    }
+
+    #[tokio::test]
+    async fn test_qcow2_backing_file() {
+        let path = write_image("backing.qcow2", 512, &[], &[]);
+        let mut image = std::fs::read(&path).unwrap();
+        image[8..16].copy_from_slice(&200u64.to_be_bytes());
+        std::fs::write(&path, image).unwrap();
+
+        let res = Qcow2Image::open(&path).await;
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(res.err().unwrap().kind(), io::ErrorKind::Unsupported);
+    }
+}
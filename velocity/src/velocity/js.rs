@@ -0,0 +1,254 @@
+//! Bindings for using the client from JavaScript. Results are returned as plain objects,
+//! their TypeScript definitions are generated from the response structures
+
+use serde::Serialize;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+use crate::{
+    endpoints::v::{v_vm_efi::EFIVMConfig, CoreVM},
+    error::{ClientError, VelocityError},
+    Authkey, Velocity, MID,
+};
+
+mod upload;
+
+/// The largest integer JavaScript numbers can represent exactly
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+#[wasm_bindgen]
+impl Velocity {
+    /// Renews the authkey of this client
+    #[wasm_bindgen(js_name = reauthenticate)]
+    pub async fn js_reauthenticate(&self) -> Result<Authkey, VelocityError> {
+        self.reauthenticate().await
+    }
+
+    /// Invalidates the authkey of this client
+    #[wasm_bindgen(js_name = deauthenticate)]
+    pub async fn js_deauthenticate(&self) -> Result<(), VelocityError> {
+        self.deauthenticate().await
+    }
+
+    /// Creates a new user and returns its id
+    #[wasm_bindgen(js_name = userCreate, unchecked_return_type = "number")]
+    pub async fn js_user_create(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<JsValue, VelocityError> {
+        to_js(&self.user_create(&username, &password).await?)
+    }
+
+    /// Removes a user
+    #[wasm_bindgen(js_name = userRemove)]
+    pub async fn js_user_remove(&self, uid: f64) -> Result<(), VelocityError> {
+        self.user_remove(integer("uid", uid)?).await
+    }
+
+    /// Retrieves information about a user, the current one if `uid` is omitted
+    #[wasm_bindgen(js_name = userInfo, unchecked_return_type = "UserInfo")]
+    pub async fn js_user_info(&self, uid: Option<f64>) -> Result<JsValue, VelocityError> {
+        let uid = uid.map(|uid| integer("uid", uid)).transpose()?;
+        to_js(&self.user_info(uid).await?)
+    }
+
+    /// Lists all users
+    #[wasm_bindgen(js_name = userList, unchecked_return_type = "UUserListPOSTRes[]")]
+    pub async fn js_user_list(&self) -> Result<JsValue, VelocityError> {
+        to_js(&self.user_list().await?)
+    }
+
+    /// Adds a permission to a user on a group
+    #[wasm_bindgen(js_name = userAddPermission)]
+    pub async fn js_user_add_permission(
+        &self,
+        gid: f64,
+        uid: f64,
+        permission: String,
+    ) -> Result<(), VelocityError> {
+        self.user_add_permission(integer("gid", gid)?, integer("uid", uid)?, &permission)
+            .await
+    }
+
+    /// Revokes a permission of a user on a group
+    #[wasm_bindgen(js_name = userRevokePermission)]
+    pub async fn js_user_revoke_permission(
+        &self,
+        gid: f64,
+        uid: f64,
+        permission: String,
+    ) -> Result<(), VelocityError> {
+        self.user_revoke_permission(integer("gid", gid)?, integer("uid", uid)?, &permission)
+            .await
+    }
+
+    /// Retrieves information about a group
+    #[wasm_bindgen(js_name = groupInfo, unchecked_return_type = "GroupInfo")]
+    pub async fn js_group_info(&self, gid: f64) -> Result<JsValue, VelocityError> {
+        to_js(&self.group_info(integer("gid", gid)?).await?)
+    }
+
+    /// Creates a new group as a child of `parentGid`
+    #[wasm_bindgen(js_name = groupCreate, unchecked_return_type = "UGroupPUTRes")]
+    pub async fn js_group_create(
+        &self,
+        parent_gid: f64,
+        name: String,
+    ) -> Result<JsValue, VelocityError> {
+        to_js(
+            &self
+                .group_create(integer("parentGid", parent_gid)?, &name)
+                .await?,
+        )
+    }
+
+    /// Removes a group
+    #[wasm_bindgen(js_name = groupRemove)]
+    pub async fn js_group_remove(&self, gid: f64) -> Result<(), VelocityError> {
+        self.group_remove(integer("gid", gid)?).await
+    }
+
+    /// Lists all groups the current user has permissions on
+    #[wasm_bindgen(js_name = groupList, unchecked_return_type = "UGroupListPOSTRes[]")]
+    pub async fn js_group_list(&self) -> Result<JsValue, VelocityError> {
+        to_js(&self.group_list().await?)
+    }
+
+    /// Lists all pools assigned to a group
+    #[wasm_bindgen(js_name = poolList, unchecked_return_type = "MPoolListPOSTRes[]")]
+    pub async fn js_pool_list(&self, gid: f64) -> Result<JsValue, VelocityError> {
+        to_js(&self.pool_list(integer("gid", gid)?).await?)
+    }
+
+    /// Assigns a pool to a group
+    #[wasm_bindgen(js_name = poolAssign)]
+    pub async fn js_pool_assign(
+        &self,
+        gid: f64,
+        mpid: f64,
+        quota: f64,
+        write: bool,
+        manage: bool,
+    ) -> Result<(), VelocityError> {
+        self.pool_assign(
+            integer("gid", gid)?,
+            integer("mpid", mpid)?,
+            integer("quota", quota)?,
+            write,
+            manage,
+        )
+        .await
+    }
+
+    /// Revokes all permissions of a group on a pool
+    #[wasm_bindgen(js_name = poolRevoke)]
+    pub async fn js_pool_revoke(&self, gid: f64, mpid: f64) -> Result<(), VelocityError> {
+        self.pool_revoke(integer("gid", gid)?, integer("mpid", mpid)?)
+            .await
+    }
+
+    /// Lists all media a group can access
+    #[wasm_bindgen(js_name = mediaList, unchecked_return_type = "MMediaListPOSTRes[]")]
+    pub async fn js_media_list(&self, gid: f64) -> Result<JsValue, VelocityError> {
+        to_js(&self.media_list(integer("gid", gid)?).await?)
+    }
+
+    /// Allocates new, empty media
+    #[wasm_bindgen(js_name = mediaAllocate, unchecked_return_type = "MMediaCreatePUTRes")]
+    pub async fn js_media_allocate(
+        &self,
+        mpid: f64,
+        gid: f64,
+        name: String,
+        #[wasm_bindgen(js_name = type)] ty: String,
+        size: f64,
+    ) -> Result<JsValue, VelocityError> {
+        let res = self
+            .media_allocate(
+                integer("mpid", mpid)?,
+                integer("gid", gid)?,
+                &name,
+                &ty,
+                integer("size", size)?,
+            )
+            .await?;
+        to_js(&res)
+    }
+
+    /// Retrieves how much of a chunked upload the hypervisor has received
+    #[cfg(feature = "experimental-chunked")]
+    #[wasm_bindgen(
+        js_name = mediaUploadStatus,
+        unchecked_return_type = "MMediaUploadStatusPOSTRes"
+    )]
+    pub async fn js_media_upload_status(&self, mid: MID) -> Result<JsValue, VelocityError> {
+        to_js(&self.media_upload_status(mid).await?)
+    }
+
+    /// Checks if the hypervisor supports chunked uploads
+    #[cfg(feature = "experimental-chunked")]
+    #[wasm_bindgen(js_name = mediaUploadChunkedSupported)]
+    pub async fn js_media_upload_chunked_supported(&self) -> Result<bool, VelocityError> {
+        self.media_upload_chunked_supported().await
+    }
+
+    /// Removes media
+    #[wasm_bindgen(js_name = mediaRemove)]
+    pub async fn js_media_remove(&self, mid: MID) -> Result<(), VelocityError> {
+        self.media_remove(mid).await
+    }
+
+    /// Lists all host NICs available for bridging
+    #[wasm_bindgen(js_name = nicList, unchecked_return_type = "VNICListPOSTRes[]")]
+    pub async fn js_nic_list(&self) -> Result<JsValue, VelocityError> {
+        to_js(&self.nic_list().await?)
+    }
+
+    /// Validates a virtual machine configuration, including the referenced media and host NICs
+    #[wasm_bindgen(js_name = vmValidate, unchecked_return_type = "ConfigProblem[]")]
+    pub async fn js_vm_validate(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "CoreVM")] vm: JsValue,
+    ) -> Result<JsValue, VelocityError> {
+        let vm: CoreVM = serde_wasm_bindgen::from_value(vm)?;
+        to_js(&self.vm_validate(&vm).await?)
+    }
+
+    /// Creates a new EFI virtual machine and returns its id
+    #[wasm_bindgen(js_name = vmEfiCreate, unchecked_return_type = "number")]
+    pub async fn js_vm_efi_create(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "EFIVMConfig")] config: JsValue,
+    ) -> Result<JsValue, VelocityError> {
+        let config: EFIVMConfig = serde_wasm_bindgen::from_value(config)?;
+
+        let vmid = self.vm_efi_create(config).await?;
+        to_js(&vmid)
+    }
+}
+
+/// Converts a value to a plain JavaScript object
+/// # Arguments
+/// * `value` - The value to convert
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, VelocityError> {
+    Ok(serde_wasm_bindgen::to_value(value)?)
+}
+
+/// Converts a number passed from JavaScript to an integer, rejecting fractions
+/// and numbers too large to be represented exactly
+/// # Arguments
+/// * `name` - The name of the argument for the error message
+/// * `value` - The number to convert
+fn integer<T: TryFrom<i64>>(name: &str, value: f64) -> Result<T, VelocityError> {
+    let invalid = || {
+        VelocityError::Client(ClientError::InvalidValue(format!(
+            "'{name}' is not a valid id or size: {value}"
+        )))
+    };
+
+    if value.fract() != 0.0 || value.abs() > MAX_SAFE_INTEGER {
+        return Err(invalid());
+    }
+
+    T::try_from(value as i64).map_err(|_| invalid())
+}
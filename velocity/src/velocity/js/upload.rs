@@ -0,0 +1,98 @@
+//! Bindings for uploading browser `File`s and `Blob`s
+
+use js_sys::Function;
+use serde::Serialize;
+use tsify::Tsify;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use web_sys::Blob;
+
+use super::{integer, to_js};
+use crate::{
+    error::VelocityError,
+    transfer::{ProgressHandler, TransferOptions, TransferProgress},
+    Velocity,
+};
+
+#[wasm_bindgen]
+impl Velocity {
+    /// Uploads a `File` or `Blob` as new media, calling `onProgress` with progress updates
+    #[wasm_bindgen(js_name = mediaUploadBlob, unchecked_return_type = "MMediaUploadPUTRes")]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn js_media_upload_blob(
+        &self,
+        mpid: f64,
+        gid: f64,
+        name: String,
+        #[wasm_bindgen(js_name = type)] ty: String,
+        readonly: bool,
+        blob: Blob,
+        #[wasm_bindgen(
+            js_name = onProgress,
+            unchecked_optional_param_type = "(progress: TransferProgress) => void"
+        )]
+        on_progress: Option<Function>,
+    ) -> Result<JsValue, VelocityError> {
+        let res = self
+            .media_upload_blob(
+                integer("mpid", mpid)?,
+                integer("gid", gid)?,
+                &name,
+                &ty,
+                readonly,
+                &blob,
+                TransferOptions::default(),
+                JsProgressHandler(on_progress),
+            )
+            .await?;
+        to_js(&res)
+    }
+}
+
+/// A snapshot of the progress of a transfer as it is passed to JavaScript,
+/// all durations are in seconds
+#[derive(Serialize, Tsify)]
+#[serde(rename = "TransferProgress")]
+struct JsTransferProgress {
+    phase: String,
+    done: u64,
+    total: Option<u64>,
+    rate: f64,
+    average_rate: f64,
+    eta: Option<f64>,
+    elapsed: f64,
+}
+
+/// Reports progress to an optional JavaScript callback
+struct JsProgressHandler(Option<Function>);
+
+// JavaScript values can't be sent between threads, but without the atomics
+// target feature there is only a single thread in WebAssembly
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl Send for JsProgressHandler {}
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl Sync for JsProgressHandler {}
+
+impl ProgressHandler for JsProgressHandler {
+    fn progress(&self, progress: &TransferProgress) {
+        let Some(callback) = &self.0 else {
+            return;
+        };
+
+        let progress = JsTransferProgress {
+            phase: progress.phase.to_string(),
+            done: progress.done,
+            total: progress.total,
+            rate: progress.rate,
+            average_rate: progress.average_rate,
+            eta: progress.eta.map(|eta| eta.as_secs_f64()),
+            elapsed: progress.elapsed.as_secs_f64(),
+        };
+
+        // A failing callback must not abort the transfer
+        if let Ok(progress) = to_js(&progress) {
+            if let Err(e) = callback.call1(&JsValue::NULL, &progress) {
+                log::warn!("Progress callback threw: {:?}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! An in-process mock of the Velocity hypervisor for testing tools built on this crate
+//!
+//! The `MockHypervisor` binds to a random port on localhost and answers every endpoint
+//! this crate wraps, including permission checks, quotas and API error codes. Its state
+//! is seeded from a `Fixture`, which can be loaded from a JSON file:
+//! ```no_run
+//! use velocity::{mock::{Fixture, MockHypervisor}, Velocity};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mock = MockHypervisor::start(Fixture::from_file("fixture.json")?).await?;
+//! let velocity = Velocity::new(&mock.url(), "root", "root").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    convert::Infallible,
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use hyper::{
+    header::{
+        ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_MAX_AGE, CONTENT_TYPE,
+    },
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server,
+};
+use tokio::sync::oneshot;
+
+mod error;
+mod fixture;
+mod routes;
+mod state;
+
+pub use error::*;
+pub use fixture::*;
+pub use state::*;
+
+/// A mock hypervisor serving the Velocity API on localhost
+pub struct MockHypervisor {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockHypervisor {
+    /// Starts a new mock hypervisor on a random port on localhost. This has to be
+    /// called from within a tokio runtime
+    /// # Arguments
+    /// * `fixture` - The fixture to seed the hypervisor state from
+    pub async fn start(fixture: Fixture) -> Result<MockHypervisor, io::Error> {
+        let state = Arc::new(Mutex::new(MockState::from_fixture(fixture)?));
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)
+            .map_err(io::Error::other)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                shutdown_rx.await.ok();
+            });
+
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                log::error!("Mock hypervisor failed: {}", e);
+            }
+        });
+
+        Ok(MockHypervisor {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Returns the base url to point a `Velocity` instance to
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Returns the address the hypervisor is listening on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Locks and returns the hypervisor state for inspection or modification
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().expect("Lock mock state")
+    }
+}
+
+impl Drop for MockHypervisor {
+    /// Shuts down the server
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            // If the server is already gone, there is nothing left to shut down
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+/// Handles a single HTTP request by routing it to the matching endpoint.
+/// Requests from any origin are allowed, so the mock can be used from browsers
+/// # Arguments
+/// * `state` - The hypervisor state to operate on
+/// * `req` - The incoming request
+async fn handle(
+    state: Arc<Mutex<MockState>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, hyper::Error> {
+    // Answer CORS preflights before the browser sends the actual request
+    if req.method() == Method::OPTIONS {
+        return Ok(Response::builder()
+            .status(hyper::StatusCode::NO_CONTENT)
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .header(
+                ACCESS_CONTROL_ALLOW_METHODS,
+                "GET, POST, PUT, DELETE, PATCH",
+            )
+            .header(ACCESS_CONTROL_ALLOW_HEADERS, "*")
+            .header(ACCESS_CONTROL_MAX_AGE, "600")
+            .body(Body::empty())
+            .expect("Build response"));
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+
+    let res = {
+        let mut state = state.lock().expect("Lock mock state");
+        let path = parts.uri.path();
+        routes::route(&mut state, &parts.method, path, &parts.headers, &body)
+    };
+
+    let (status, body) = match res {
+        Ok(body) => (hyper::StatusCode::OK, body),
+        Err(e) => (e.status, e.body()),
+    };
+
+    Ok(Response::builder()
+        .status(status)
+        .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("Build response"))
+}
@@ -0,0 +1,104 @@
+use std::fmt::Display;
+
+use hyper::StatusCode;
+use serde_json::{json, Value};
+
+use crate::GID;
+
+/// An error the mock hypervisor responds with.
+///
+/// Errors of the hypervisor are transmitted as `{"code": ..., "message": ...}`. The mock does not
+/// imitate the individual codes of the hypervisor, its codes are the HTTP status.
+/// Errors of the web framework, malformed requests and unknown routes, carry no code and are
+/// transmitted as `{"error": true, "reason": ...}`
+#[derive(Debug, Clone)]
+pub struct MockError {
+    /// The HTTP status to respond with
+    pub status: StatusCode,
+    /// The Velocity error code, `None` for errors of the web framework
+    pub code: Option<u32>,
+    /// A human-readable description of the error
+    pub message: String,
+}
+
+impl MockError {
+    fn new(status: StatusCode, message: impl Display) -> Self {
+        Self {
+            status,
+            code: Some(status.as_u16() as u32),
+            message: message.to_string(),
+        }
+    }
+
+    /// The request is malformed or contains invalid values
+    pub fn bad_request(reason: impl Display) -> Self {
+        Self {
+            code: None,
+            ..Self::new(StatusCode::BAD_REQUEST, reason)
+        }
+    }
+
+    /// There is no endpoint for the method and path
+    pub fn no_route(method: impl Display, path: &str) -> Self {
+        Self {
+            code: None,
+            ..Self::new(
+                StatusCode::NOT_FOUND,
+                format!("No route for {} {}", method, path),
+            )
+        }
+    }
+
+    /// The authkey is unknown or has expired
+    pub fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "The authkey is invalid or has expired",
+        )
+    }
+
+    /// The user is not allowed to perform the action
+    pub fn forbidden(message: impl Display) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    /// The user is missing a permission on a group
+    pub fn missing_permission(permission: &str, gid: GID) -> Self {
+        Self::forbidden(format!(
+            "Missing permission '{}' on group {}",
+            permission, gid
+        ))
+    }
+
+    /// A resource does not exist
+    pub fn not_found(what: impl Display) -> Self {
+        Self::new(StatusCode::NOT_FOUND, format!("{} not found", what))
+    }
+
+    /// A resource already exists or is in use
+    pub fn conflict(message: impl Display) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    /// An action would exceed a quota
+    pub fn quota_exceeded(message: impl Display) -> Self {
+        Self::new(StatusCode::INSUFFICIENT_STORAGE, message)
+    }
+
+    /// Returns the JSON body to respond with
+    pub fn body(&self) -> Value {
+        match self.code {
+            None => json!({ "error": true, "reason": self.message }),
+            Some(code) => json!({ "code": code, "message": self.message }),
+        }
+    }
+}
+
+impl Display for MockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            None => write!(f, "{}: {}", self.status, self.message),
+            Some(code) => write!(f, "{} ({}): {}", self.status, code, self.message),
+        }
+    }
+}
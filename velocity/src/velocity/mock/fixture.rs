@@ -0,0 +1,121 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{GID, MID, MPID, NICID, UID};
+
+/// The initial state of a `MockHypervisor`.
+///
+/// The root group (GID 0) always exists. If there is a user with UID 0, it holds
+/// every permission on the root group, just like the `root` user on a real hypervisor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fixture {
+    /// The amount of seconds an authkey stays valid
+    pub authkey_lifetime: u64,
+    pub users: Vec<FixtureUser>,
+    pub groups: Vec<FixtureGroup>,
+    pub permissions: Vec<FixturePermission>,
+    pub pools: Vec<FixturePool>,
+    pub assignments: Vec<FixtureAssignment>,
+    pub media: Vec<FixtureMedia>,
+    pub nics: Vec<FixtureNIC>,
+}
+
+impl Fixture {
+    /// Loads a fixture from a JSON file. Fields missing in the file take their default values
+    /// # Arguments
+    /// * `path` - The path to the JSON file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Fixture, io::Error> {
+        let content = fs::read(path)?;
+
+        serde_json::from_slice(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Default for Fixture {
+    /// A hypervisor with only the `root` user (password `root`) and the root group
+    fn default() -> Self {
+        Self {
+            authkey_lifetime: 3600,
+            users: vec![FixtureUser {
+                uid: UID(0),
+                name: "root".to_owned(),
+                password: "root".to_owned(),
+            }],
+            groups: Vec::new(),
+            permissions: Vec::new(),
+            pools: Vec::new(),
+            assignments: Vec::new(),
+            media: Vec::new(),
+            nics: Vec::new(),
+        }
+    }
+}
+
+/// A user known to the hypervisor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureUser {
+    pub uid: UID,
+    pub name: String,
+    pub password: String,
+}
+
+/// A group below the root group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureGroup {
+    pub gid: GID,
+    pub parent_gid: GID,
+    pub name: String,
+}
+
+/// A permission a user holds on a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixturePermission {
+    pub uid: UID,
+    pub gid: GID,
+    /// The permission name, e.g. `velocity.media.list`
+    pub permission: String,
+}
+
+/// A mediapool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixturePool {
+    pub mpid: MPID,
+    pub name: String,
+}
+
+/// The assignment of a mediapool to a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureAssignment {
+    pub gid: GID,
+    pub mpid: MPID,
+    /// The quota in bytes
+    pub quota: u64,
+    pub write: bool,
+    pub manage: bool,
+}
+
+/// A piece of media
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureMedia {
+    pub mid: MID,
+    pub mpid: MPID,
+    pub gid: GID,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub size: u64,
+    pub readonly: bool,
+    /// The SHA-256 checksum of the content, as lowercase hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// A host NIC available for bridging
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureNIC {
+    pub nicid: NICID,
+    pub description: String,
+    pub identifier: String,
+}
@@ -0,0 +1,75 @@
+use hyper::{HeaderMap, Method};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{MockError, MockState};
+
+mod m;
+mod u;
+mod v;
+
+/// Routes a request to the matching endpoint handler
+/// # Arguments
+/// * `state` - The hypervisor state to operate on
+/// * `method` - The request method
+/// * `path` - The request path, e.g. `/u/auth`
+/// * `headers` - The request headers
+/// * `body` - The raw request body
+/// # Returns
+/// The JSON body to respond with on success
+pub fn route(
+    state: &mut MockState,
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Value, MockError> {
+    match (method.as_str(), path) {
+        ("POST", "/u/auth") => u::auth_post(state, parse(body)?),
+        ("PATCH", "/u/auth") => u::auth_patch(state, parse(body)?),
+        ("DELETE", "/u/auth") => u::auth_delete(state, parse(body)?),
+
+        ("POST", "/u/user") => u::user_post(state, parse(body)?),
+        ("PUT", "/u/user") => u::user_put(state, parse(body)?),
+        ("DELETE", "/u/user") => u::user_delete(state, parse(body)?),
+        ("POST", "/u/user/list") => u::user_list_post(state, parse(body)?),
+        ("PUT", "/u/user/permission") => u::user_permission_put(state, parse(body)?),
+        ("DELETE", "/u/user/permission") => u::user_permission_delete(state, parse(body)?),
+
+        ("POST", "/u/group") => u::group_post(state, parse(body)?),
+        ("PUT", "/u/group") => u::group_put(state, parse(body)?),
+        ("DELETE", "/u/group") => u::group_delete(state, parse(body)?),
+        ("POST", "/u/group/list") => u::group_list_post(state, parse(body)?),
+
+        ("POST", "/m/pool/list") => m::pool_list_post(state, parse(body)?),
+        ("PUT", "/m/pool/assign") => m::pool_assign_put(state, parse(body)?),
+        ("DELETE", "/m/pool/assign") => m::pool_assign_delete(state, parse(body)?),
+
+        ("POST", "/m/media/list") => m::media_list_post(state, parse(body)?),
+        ("PUT", "/m/media/create") => m::media_create_put(state, parse(body)?),
+        ("PUT", "/m/media/upload") => m::media_upload_put(state, headers, body),
+        ("PUT", "/m/media/upload/start") if state.chunked_uploads => {
+            m::media_upload_start_put(state, parse(body)?)
+        }
+        ("PUT", "/m/media/upload/chunk") if state.chunked_uploads => {
+            m::media_upload_chunk_put(state, headers, body)
+        }
+        ("POST", "/m/media/upload/status") if state.chunked_uploads => {
+            m::media_upload_status_post(state, parse(body)?)
+        }
+        ("DELETE", "/m/media") => m::media_delete(state, parse(body)?),
+
+        ("POST", "/v/nic/list") => v::nic_list_post(state, parse(body)?),
+        ("PUT", "/v/vm/efi") => v::vm_efi_put(state, parse(body)?),
+
+        _ => Err(MockError::no_route(method, path)),
+    }
+}
+
+/// Parses a JSON request body
+/// # Arguments
+/// * `body` - The raw request body
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, MockError> {
+    serde_json::from_slice(body)
+        .map_err(|e| MockError::bad_request(format!("Malformed request: {}", e)))
+}
@@ -0,0 +1,541 @@
+use hyper::{header::HeaderValue, HeaderMap};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    mock::{FixtureAssignment, FixtureMedia, MockError, MockState, MockUpload},
+    transfer::hex,
+    GID, MID, MPID, UID,
+};
+
+#[derive(Deserialize)]
+pub struct GroupReq {
+    authkey: String,
+    gid: GID,
+}
+
+pub fn pool_list_post(state: &mut MockState, req: GroupReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, "velocity.pool.list")?;
+
+    let pools: Vec<Value> = state
+        .pools
+        .values()
+        .filter_map(|pool| {
+            pool_access(state, req.gid, pool.mpid).map(|access| {
+                json!({
+                    "mpid": pool.mpid,
+                    "name": pool.name,
+                    "write": access.write,
+                    "manage": access.manage,
+                })
+            })
+        })
+        .collect();
+
+    Ok(json!({ "pools": pools }))
+}
+
+#[derive(Deserialize)]
+pub struct MPoolAssignPUTReq {
+    authkey: String,
+    gid: GID,
+    mpid: MPID,
+    quota: u64,
+    write: bool,
+    manage: bool,
+}
+
+pub fn pool_assign_put(state: &mut MockState, req: MPoolAssignPUTReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, "velocity.pool.assign")?;
+
+    if !state.pools.contains_key(&req.mpid) {
+        return Err(MockError::not_found(format!("Pool {}", req.mpid)));
+    }
+
+    state.assignments.insert(
+        (req.gid, req.mpid),
+        FixtureAssignment {
+            gid: req.gid,
+            mpid: req.mpid,
+            quota: req.quota,
+            write: req.write,
+            manage: req.manage,
+        },
+    );
+
+    Ok(json!({}))
+}
+
+#[derive(Deserialize)]
+pub struct MPoolAssignDELETEReq {
+    authkey: String,
+    gid: GID,
+    mpid: MPID,
+}
+
+pub fn pool_assign_delete(
+    state: &mut MockState,
+    req: MPoolAssignDELETEReq,
+) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, "velocity.pool.revoke")?;
+
+    if state.assignments.remove(&(req.gid, req.mpid)).is_none() {
+        return Err(MockError::not_found(format!(
+            "Assignment of pool {} to group {}",
+            req.mpid, req.gid
+        )));
+    }
+
+    Ok(json!({}))
+}
+
+pub fn media_list_post(state: &mut MockState, req: GroupReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, "velocity.media.list")?;
+
+    // Media is visible to the group that owns it and all of that group's subgroups
+    let visible = state.ancestors(req.gid);
+    let media: Vec<Value> = state
+        .media
+        .values()
+        .filter(|m| visible.contains(&m.gid))
+        .map(|m| {
+            json!({
+                "mid": m.mid,
+                "mpid": m.mpid,
+                "name": m.name,
+                "type": m.ty,
+                "size": m.size,
+                "readonly": m.readonly,
+                "sha256": m.sha256,
+            })
+        })
+        .collect();
+
+    Ok(json!({ "media": media }))
+}
+
+#[derive(Deserialize)]
+pub struct MMediaCreatePUTReq {
+    authkey: String,
+    mpid: MPID,
+    gid: GID,
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    size: u64,
+}
+
+pub fn media_create_put(
+    state: &mut MockState,
+    req: MMediaCreatePUTReq,
+) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+
+    let media = create_media(
+        state,
+        uid,
+        FixtureMedia {
+            mid: MID::new(),
+            mpid: req.mpid,
+            gid: req.gid,
+            name: req.name,
+            ty: req.ty,
+            size: req.size,
+            readonly: false,
+            sha256: None,
+        },
+    )?;
+
+    Ok(json!({ "mid": media.mid, "size": media.size }))
+}
+
+/// Returns the value of a request header
+/// # Arguments
+/// * `headers` - The request headers
+/// * `name` - The name of the header
+fn header(headers: &HeaderMap, name: &str) -> Result<String, MockError> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_owned())
+        .ok_or_else(|| MockError::bad_request(format!("Missing header '{}'", name)))
+}
+
+/// Returns the parsed value of a request header
+/// # Arguments
+/// * `headers` - The request headers
+/// * `name` - The name of the header
+fn parse_header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Result<T, MockError> {
+    header(headers, name)?
+        .parse()
+        .map_err(|_| MockError::bad_request(format!("Malformed header '{}'", name)))
+}
+
+pub fn media_upload_put(
+    state: &mut MockState,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Value, MockError> {
+    let header = |name: &str| header(headers, name);
+
+    let uid = state.authenticate(&header("x-velocity-authkey")?)?;
+
+    let body = received(state, body);
+    let sha256 = hex(&Sha256::digest(&body));
+    verify_checksum(headers.get("x-velocity-sha256"), &sha256)?;
+
+    let media = create_media(
+        state,
+        uid,
+        FixtureMedia {
+            mid: MID::new(),
+            mpid: parse_header(headers, "x-velocity-mpid")?,
+            gid: parse_header(headers, "x-velocity-gid")?,
+            name: header("x-velocity-name")?,
+            ty: header("x-velocity-type")?,
+            size: body.len() as u64,
+            readonly: match header("x-velocity-readonly")?.as_str() {
+                "true" => true,
+                "false" => false,
+                _ => {
+                    return Err(MockError::bad_request(
+                        "Malformed header 'x-velocity-readonly'",
+                    ))
+                }
+            },
+            sha256: Some(sha256),
+        },
+    )?;
+
+    Ok(json!({ "mid": media.mid, "size": media.size, "sha256": media.sha256 }))
+}
+
+#[derive(Deserialize)]
+pub struct MMediaUploadStartPUTReq {
+    authkey: String,
+    mpid: MPID,
+    gid: GID,
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    size: u64,
+    readonly: bool,
+    sha256: Option<String>,
+}
+
+pub fn media_upload_start_put(
+    state: &mut MockState,
+    req: MMediaUploadStartPUTReq,
+) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+
+    // The full size is reserved up front, so the quota is enforced once
+    let media = create_media(
+        state,
+        uid,
+        FixtureMedia {
+            mid: MID::new(),
+            mpid: req.mpid,
+            gid: req.gid,
+            name: req.name,
+            ty: req.ty,
+            size: req.size,
+            readonly: req.readonly,
+            sha256: None,
+        },
+    )?;
+
+    // Empty media is complete right away
+    if media.size > 0 {
+        state.uploads.insert(
+            media.mid.clone(),
+            MockUpload {
+                mid: media.mid.clone(),
+                offset: 0,
+                hasher: Sha256::new(),
+                expected: req.sha256,
+            },
+        );
+    } else if let Some(media) = state.media.get_mut(&media.mid) {
+        media.sha256 = Some(hex(&Sha256::digest([])));
+    }
+
+    Ok(json!({ "mid": media.mid, "size": media.size }))
+}
+
+pub fn media_upload_chunk_put(
+    state: &mut MockState,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Value, MockError> {
+    let uid = state.authenticate(&header(headers, "x-velocity-authkey")?)?;
+    let mid: MID = header(headers, "x-velocity-mid")?;
+    let offset: u64 = parse_header(headers, "x-velocity-offset")?;
+
+    let (media, mut upload) = upload_of(state, uid, &mid)?;
+
+    let body = received(state, body);
+    verify_checksum(
+        headers.get("x-velocity-sha256"),
+        &hex(&Sha256::digest(&body)),
+    )?;
+
+    if offset != upload.offset {
+        return Err(MockError::conflict(format!(
+            "Upload of media {} expects offset {}, got {}",
+            mid, upload.offset, offset
+        )));
+    }
+
+    let end = offset + body.len() as u64;
+    if end > media.size {
+        return Err(MockError::bad_request(format!(
+            "Chunk exceeds the size of media {} ({} bytes)",
+            mid, media.size
+        )));
+    }
+
+    upload.hasher.update(&body);
+    upload.offset = end;
+
+    if end < media.size {
+        state.uploads.insert(mid.clone(), upload);
+        return Ok(json!({ "mid": mid, "offset": end }));
+    }
+
+    // The upload is complete, verify it against what the client expected
+    state.uploads.remove(&mid);
+    let sha256 = hex(&upload.hasher.finalize());
+    if let Some(expected) = &upload.expected {
+        if !expected.eq_ignore_ascii_case(&sha256) {
+            state.media.remove(&mid);
+            return Err(MockError::bad_request(format!(
+                "Checksum mismatch: expected {}, got {}",
+                expected, sha256
+            )));
+        }
+    }
+    // The last chunk carries the checksum of the whole media the client has read
+    if let Err(e) = verify_checksum(headers.get("x-velocity-media-sha256"), &sha256) {
+        state.media.remove(&mid);
+        return Err(e);
+    }
+    if let Some(media) = state.media.get_mut(&mid) {
+        media.sha256 = Some(sha256.clone());
+    }
+
+    Ok(json!({ "mid": mid, "offset": end, "sha256": sha256 }))
+}
+
+#[derive(Deserialize)]
+pub struct MMediaUploadStatusPOSTReq {
+    authkey: String,
+    mid: MID,
+}
+
+pub fn media_upload_status_post(
+    state: &mut MockState,
+    req: MMediaUploadStatusPOSTReq,
+) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    let (media, upload) = upload_of(state, uid, &req.mid)?;
+
+    Ok(json!({ "mid": req.mid, "size": media.size, "offset": upload.offset }))
+}
+
+/// Returns the data as the hypervisor received it, corrupted if the state asks for it
+/// # Arguments
+/// * `state` - The hypervisor state
+/// * `body` - The data sent by the client
+fn received(state: &MockState, body: &[u8]) -> Vec<u8> {
+    let mut body = body.to_vec();
+    if state.corrupt_uploads {
+        if let Some(byte) = body.first_mut() {
+            *byte ^= 1;
+        }
+    }
+    body
+}
+
+/// Verifies received data against the checksum header the client sent, if any
+/// # Arguments
+/// * `header` - The `x-velocity-sha256` header
+/// * `sha256` - The checksum of the received data
+fn verify_checksum(header: Option<&HeaderValue>, sha256: &str) -> Result<(), MockError> {
+    match header {
+        Some(expected) => {
+            let expected = expected
+                .to_str()
+                .map_err(|_| MockError::bad_request("Malformed checksum header"))?;
+
+            if !expected.eq_ignore_ascii_case(sha256) {
+                return Err(MockError::bad_request(format!(
+                    "Checksum mismatch: expected {}, got {}",
+                    expected, sha256
+                )));
+            }
+
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Looks up a chunked upload in progress and checks the user may write to it
+/// # Arguments
+/// * `state` - The hypervisor state to operate on
+/// * `uid` - The user accessing the upload
+/// * `mid` - The media id of the upload
+fn upload_of(
+    state: &MockState,
+    uid: UID,
+    mid: &str,
+) -> Result<(FixtureMedia, MockUpload), MockError> {
+    let upload = state
+        .uploads
+        .get(mid)
+        .ok_or_else(|| MockError::not_found(format!("Upload of media {}", mid)))?;
+    let media = state
+        .media
+        .get(mid)
+        .ok_or_else(|| MockError::not_found(format!("Media {}", mid)))?;
+    state.require(uid, media.gid, "velocity.media.create")?;
+
+    Ok((media.clone(), upload.clone()))
+}
+
+#[derive(Deserialize)]
+pub struct MMediaDELETEReq {
+    authkey: String,
+    mid: MID,
+}
+
+pub fn media_delete(state: &mut MockState, req: MMediaDELETEReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+
+    let media = state
+        .media
+        .get(&req.mid)
+        .ok_or_else(|| MockError::not_found(format!("Media {}", req.mid)))?;
+    state.require(uid, media.gid, "velocity.media.remove")?;
+
+    if let Some(vm) = state.vms.values().find(|vm| {
+        vm.config["disks"]
+            .as_array()
+            .map(|disks| disks.iter().any(|d| d["mid"] == req.mid.as_str()))
+            .unwrap_or(false)
+    }) {
+        return Err(MockError::conflict(format!(
+            "Media {} is in use by virtual machine {}",
+            req.mid, vm.vmid
+        )));
+    }
+
+    state.media.remove(&req.mid);
+    state.uploads.remove(&req.mid);
+
+    Ok(json!({}))
+}
+
+/// The access a group has to a pool
+struct PoolAccess {
+    quota: u64,
+    write: bool,
+    manage: bool,
+}
+
+/// Returns the access a group has to a pool. The root group has unlimited access to all pools
+fn pool_access(state: &MockState, gid: GID, mpid: MPID) -> Option<PoolAccess> {
+    if gid == GID(0) && state.pools.contains_key(&mpid) {
+        return Some(PoolAccess {
+            quota: u64::MAX,
+            write: true,
+            manage: true,
+        });
+    }
+
+    state.assignments.get(&(gid, mpid)).map(|a| PoolAccess {
+        quota: a.quota,
+        write: a.write,
+        manage: a.manage,
+    })
+}
+
+/// Checks permissions, pool access and quota and creates a new piece of media
+/// # Arguments
+/// * `state` - The hypervisor state to operate on
+/// * `uid` - The user creating the media
+/// * `media` - The media to create, the `mid` gets assigned
+fn create_media(
+    state: &mut MockState,
+    uid: UID,
+    mut media: FixtureMedia,
+) -> Result<FixtureMedia, MockError> {
+    state.require(uid, media.gid, "velocity.media.create")?;
+
+    if !state.pools.contains_key(&media.mpid) {
+        return Err(MockError::not_found(format!("Pool {}", media.mpid)));
+    }
+
+    let access = pool_access(state, media.gid, media.mpid).ok_or_else(|| {
+        MockError::forbidden(format!(
+            "Group {} has no access to pool {}",
+            media.gid, media.mpid
+        ))
+    })?;
+
+    if !access.manage {
+        return Err(MockError::forbidden(format!(
+            "Group {} cannot manage pool {}",
+            media.gid, media.mpid
+        )));
+    }
+
+    if media.name.is_empty() {
+        return Err(MockError::bad_request("The media name must not be empty"));
+    }
+
+    if state
+        .media
+        .values()
+        .any(|m| m.mpid == media.mpid && m.gid == media.gid && m.name == media.name)
+    {
+        return Err(MockError::conflict(format!(
+            "Media '{}' already exists in pool {}",
+            media.name, media.mpid
+        )));
+    }
+
+    let used: u64 = state
+        .media
+        .values()
+        .filter(|m| m.mpid == media.mpid && m.gid == media.gid)
+        .map(|m| m.size)
+        .sum();
+
+    if used.saturating_add(media.size) > access.quota {
+        return Err(MockError::quota_exceeded(format!(
+            "Group {} would exceed its quota of {} bytes on pool {}",
+            media.gid, access.quota, media.mpid
+        )));
+    }
+
+    let id = state.next_id();
+    media.mid = format!(
+        "{:08x}-{:04x}-4{:03x}-8{:03x}-{:012x}",
+        id >> 32,
+        (id >> 16) & 0xffff,
+        (id >> 4) & 0xfff,
+        id & 0xfff,
+        id
+    );
+
+    state.media.insert(media.mid.clone(), media.clone());
+
+    Ok(media)
+}
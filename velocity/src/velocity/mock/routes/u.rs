@@ -0,0 +1,373 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    mock::{permission_id, FixtureGroup, FixtureUser, MockError, MockState, PERMISSIONS},
+    GID, PID, UID,
+};
+
+#[derive(Deserialize)]
+pub struct AuthkeyReq {
+    authkey: String,
+}
+
+impl AuthkeyReq {
+    pub fn authkey(&self) -> &str {
+        &self.authkey
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UAuthPOSTReq {
+    username: String,
+    password: String,
+}
+
+pub fn auth_post(state: &mut MockState, req: UAuthPOSTReq) -> Result<Value, MockError> {
+    let uid = state
+        .users
+        .values()
+        .find(|u| u.name == req.username && u.password == req.password)
+        .map(|u| u.uid)
+        .ok_or_else(|| MockError::forbidden("Username or password wrong"))?;
+
+    let (authkey, expires) = state.issue_authkey(uid);
+
+    Ok(json!({ "authkey": authkey, "expires": expires }))
+}
+
+pub fn auth_patch(state: &mut MockState, req: AuthkeyReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+
+    // The old key is invalidated by the renewal
+    state.authkeys.remove(&req.authkey);
+    let (authkey, expires) = state.issue_authkey(uid);
+
+    Ok(json!({ "authkey": authkey, "expires": expires }))
+}
+
+pub fn auth_delete(state: &mut MockState, req: AuthkeyReq) -> Result<Value, MockError> {
+    state.authenticate(&req.authkey)?;
+    state.authkeys.remove(&req.authkey);
+
+    Ok(json!({}))
+}
+
+#[derive(Deserialize)]
+pub struct UUserPOSTReq {
+    authkey: String,
+    uid: Option<UID>,
+}
+
+pub fn user_post(state: &mut MockState, req: UUserPOSTReq) -> Result<Value, MockError> {
+    let self_uid = state.authenticate(&req.authkey)?;
+
+    let uid = match req.uid {
+        Some(uid) if uid != self_uid => {
+            state.require(self_uid, GID(0), "velocity.user.view")?;
+            uid
+        }
+        _ => self_uid,
+    };
+
+    let user = state
+        .users
+        .get(&uid)
+        .ok_or_else(|| MockError::not_found(format!("User {}", uid)))?;
+
+    let mut memberships = Vec::new();
+    for group in state.groups.values() {
+        let pids: Vec<PID> = state
+            .permissions
+            .iter()
+            .filter(|(u, g, _)| *u == uid && *g == group.gid)
+            .map(|(_, _, pid)| *pid)
+            .collect();
+
+        if !pids.is_empty() {
+            memberships.push(json!({
+                "gid": group.gid,
+                "parent_gid": group.parent_gid,
+                "name": group.name,
+                "permissions": permissions_json(pids),
+            }));
+        }
+    }
+
+    Ok(json!({ "uid": user.uid, "name": user.name, "memberships": memberships }))
+}
+
+#[derive(Deserialize)]
+pub struct UUserPUTReq {
+    authkey: String,
+    name: String,
+    password: String,
+}
+
+pub fn user_put(state: &mut MockState, req: UUserPUTReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, GID(0), "velocity.user.create")?;
+
+    if req.name.is_empty() {
+        return Err(MockError::bad_request("The username must not be empty"));
+    }
+
+    if state.users.values().any(|u| u.name == req.name) {
+        return Err(MockError::conflict(format!(
+            "User '{}' already exists",
+            req.name
+        )));
+    }
+
+    let new_uid = state
+        .users
+        .keys()
+        .max()
+        .map(|uid| UID(uid.0 + 1))
+        .unwrap_or_default();
+    state.users.insert(
+        new_uid,
+        FixtureUser {
+            uid: new_uid,
+            name: req.name.clone(),
+            password: req.password,
+        },
+    );
+
+    Ok(json!({ "uid": new_uid, "name": req.name }))
+}
+
+#[derive(Deserialize)]
+pub struct UUserDELETEReq {
+    authkey: String,
+    uid: UID,
+}
+
+pub fn user_delete(state: &mut MockState, req: UUserDELETEReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, GID(0), "velocity.user.remove")?;
+
+    if req.uid == UID(0) {
+        return Err(MockError::forbidden("The root user cannot be removed"));
+    }
+
+    if state.users.remove(&req.uid).is_none() {
+        return Err(MockError::not_found(format!("User {}", req.uid)));
+    }
+
+    state.permissions.retain(|(u, _, _)| *u != req.uid);
+    state.authkeys.retain(|_, (u, _)| *u != req.uid);
+
+    Ok(json!({}))
+}
+
+pub fn user_list_post(state: &mut MockState, req: AuthkeyReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, GID(0), "velocity.user.list")?;
+
+    let users: Vec<Value> = state
+        .users
+        .values()
+        .map(|u| json!({ "uid": u.uid, "name": u.name }))
+        .collect();
+
+    Ok(json!({ "users": users }))
+}
+
+#[derive(Deserialize)]
+pub struct UUserPermissionReq {
+    authkey: String,
+    gid: GID,
+    uid: UID,
+    permission: String,
+}
+
+/// Checks the preconditions for changing a permission and returns its `PID`
+fn permission_change(
+    state: &MockState,
+    req: &UUserPermissionReq,
+    required: &str,
+) -> Result<PID, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, required)?;
+
+    if !state.users.contains_key(&req.uid) {
+        return Err(MockError::not_found(format!("User {}", req.uid)));
+    }
+
+    let pid = permission_id(&req.permission)
+        .ok_or_else(|| MockError::not_found(format!("Permission '{}'", req.permission)))?;
+
+    // Nobody can hand out permissions they don't hold themselves
+    if !state.has_permission(uid, req.gid, &req.permission) {
+        return Err(MockError::missing_permission(&req.permission, req.gid));
+    }
+
+    Ok(pid)
+}
+
+pub fn user_permission_put(
+    state: &mut MockState,
+    req: UUserPermissionReq,
+) -> Result<Value, MockError> {
+    let pid = permission_change(state, &req, "velocity.user.assign")?;
+
+    state.permissions.insert((req.uid, req.gid, pid));
+
+    Ok(json!({}))
+}
+
+pub fn user_permission_delete(
+    state: &mut MockState,
+    req: UUserPermissionReq,
+) -> Result<Value, MockError> {
+    let pid = permission_change(state, &req, "velocity.user.revoke")?;
+
+    if !state.permissions.remove(&(req.uid, req.gid, pid)) {
+        return Err(MockError::not_found(format!(
+            "Permission '{}' of user {} on group {}",
+            req.permission, req.uid, req.gid
+        )));
+    }
+
+    Ok(json!({}))
+}
+
+#[derive(Deserialize)]
+pub struct UGroupPOSTReq {
+    authkey: String,
+    gid: GID,
+}
+
+pub fn group_post(state: &mut MockState, req: UGroupPOSTReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, "velocity.group.view")?;
+
+    let group = &state.groups[&req.gid];
+
+    let mut memberships = Vec::new();
+    for user in state.users.values() {
+        let pids: Vec<PID> = state
+            .permissions
+            .iter()
+            .filter(|(u, g, _)| *u == user.uid && *g == group.gid)
+            .map(|(_, _, pid)| *pid)
+            .collect();
+
+        if !pids.is_empty() {
+            memberships.push(json!({
+                "uid": user.uid,
+                "name": user.name,
+                "permissions": permissions_json(pids),
+            }));
+        }
+    }
+
+    Ok(json!({
+        "name": group.name,
+        "gid": group.gid,
+        "parent_gid": group.parent_gid,
+        "memberships": memberships,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct UGroupPUTReq {
+    authkey: String,
+    name: String,
+    parent_gid: GID,
+}
+
+pub fn group_put(state: &mut MockState, req: UGroupPUTReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.parent_gid, "velocity.group.create")?;
+
+    if req.name.is_empty() {
+        return Err(MockError::bad_request("The group name must not be empty"));
+    }
+
+    if state
+        .groups
+        .values()
+        .any(|g| g.parent_gid == req.parent_gid && g.gid != GID(0) && g.name == req.name)
+    {
+        return Err(MockError::conflict(format!(
+            "Group '{}' already exists in group {}",
+            req.name, req.parent_gid
+        )));
+    }
+
+    let gid = state
+        .groups
+        .keys()
+        .max()
+        .map(|gid| GID(gid.0 + 1))
+        .unwrap_or_default();
+    state.groups.insert(
+        gid,
+        FixtureGroup {
+            gid,
+            parent_gid: req.parent_gid,
+            name: req.name.clone(),
+        },
+    );
+
+    Ok(json!({ "gid": gid, "parent_gid": req.parent_gid, "name": req.name }))
+}
+
+pub fn group_delete(state: &mut MockState, req: UGroupPOSTReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, "velocity.group.remove")?;
+
+    if req.gid == GID(0) {
+        return Err(MockError::forbidden("The root group cannot be removed"));
+    }
+
+    // Removing a group removes all of its subgroups and everything they own
+    let removed = state.descendants(req.gid);
+    state.groups.retain(|gid, _| !removed.contains(gid));
+    state
+        .permissions
+        .retain(|(_, gid, _)| !removed.contains(gid));
+    state
+        .assignments
+        .retain(|(gid, _), _| !removed.contains(gid));
+    state.media.retain(|_, m| !removed.contains(&m.gid));
+    state.vms.retain(|_, vm| !removed.contains(&vm.gid));
+
+    Ok(json!({}))
+}
+
+pub fn group_list_post(state: &mut MockState, req: AuthkeyReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+
+    let groups: Vec<Value> = state
+        .groups
+        .values()
+        .filter_map(|group| {
+            let pids = state.effective_permissions(uid, group.gid);
+
+            match pids.is_empty() {
+                true => None,
+                false => Some(json!({
+                    "name": group.name,
+                    "gid": group.gid,
+                    "parent_gid": group.parent_gid,
+                    "permissions": permissions_json(pids),
+                })),
+            }
+        })
+        .collect();
+
+    Ok(json!({ "groups": groups }))
+}
+
+/// Serializes a list of permissions the way the hypervisor responds with them
+fn permissions_json<I: IntoIterator<Item = PID>>(pids: I) -> Vec<Value> {
+    pids.into_iter()
+        .map(|pid| {
+            let (name, description) = PERMISSIONS[pid.0 as usize];
+            json!({ "pid": pid, "name": name, "description": description })
+        })
+        .collect()
+}
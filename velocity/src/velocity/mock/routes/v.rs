@@ -0,0 +1,165 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    mock::{MockError, MockState, MockVM},
+    GID, MID, NICID, VMID,
+};
+
+use super::u::AuthkeyReq;
+
+pub fn nic_list_post(state: &mut MockState, req: AuthkeyReq) -> Result<Value, MockError> {
+    state.authenticate(req.authkey())?;
+
+    let nics: Vec<Value> = state
+        .nics
+        .values()
+        .map(|n| {
+            json!({
+                "nicid": n.nicid,
+                "description": n.description,
+                "identifier": n.identifier,
+            })
+        })
+        .collect();
+
+    Ok(json!({ "host_nics": nics }))
+}
+
+#[derive(Deserialize)]
+pub struct VVMEFIPUTReq {
+    authkey: String,
+    name: String,
+    gid: GID,
+    cpus: u32,
+    memory_mib: u64,
+    disks: Vec<Disk>,
+    nics: Vec<NIC>,
+    #[serde(flatten)]
+    rest: serde_json::Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct Disk {
+    mid: MID,
+    mode: String,
+    readonly: bool,
+}
+
+#[derive(Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+struct NIC {
+    #[serde(rename = "type")]
+    ty: String,
+    host: Option<NICID>,
+}
+
+pub fn vm_efi_put(state: &mut MockState, req: VVMEFIPUTReq) -> Result<Value, MockError> {
+    let uid = state.authenticate(&req.authkey)?;
+    state.require(uid, req.gid, "velocity.vm.create")?;
+
+    if req.name.is_empty() {
+        return Err(MockError::bad_request("The name must not be empty"));
+    }
+    if req.cpus == 0 {
+        return Err(MockError::bad_request(
+            "A virtual machine needs at least 1 CPU",
+        ));
+    }
+    if req.memory_mib == 0 {
+        return Err(MockError::bad_request("A virtual machine needs memory"));
+    }
+
+    // Media is visible to the group that owns it and all of that group's subgroups
+    let visible = state.ancestors(req.gid);
+    for disk in &req.disks {
+        let media = state
+            .media
+            .get(&disk.mid)
+            .ok_or_else(|| MockError::not_found(format!("Media {}", disk.mid)))?;
+
+        if !visible.contains(&media.gid) {
+            return Err(MockError::forbidden(format!(
+                "Media {} is not available to group {}",
+                disk.mid, req.gid
+            )));
+        }
+        if !disk.readonly && media.readonly {
+            return Err(MockError::bad_request(format!(
+                "Media {} is read-only",
+                disk.mid
+            )));
+        }
+        if !["USB", "BLOCK", "VIRTIO"].contains(&disk.mode.as_str()) {
+            return Err(MockError::bad_request(format!(
+                "Unknown disk mode '{}'",
+                disk.mode
+            )));
+        }
+    }
+
+    for nic in &req.nics {
+        match (nic.ty.as_str(), nic.host) {
+            ("NAT", _) => {}
+            ("BRIDGE", Some(host)) => {
+                if !state.nics.contains_key(&host) {
+                    return Err(MockError::not_found(format!("Host NIC {}", host)));
+                }
+            }
+            ("BRIDGE", None) => {
+                return Err(MockError::bad_request("A BRIDGE NIC requires a host NIC"))
+            }
+            (ty, _) => return Err(MockError::bad_request(format!("Unknown NIC type '{}'", ty))),
+        }
+    }
+
+    if state
+        .vms
+        .values()
+        .any(|vm| vm.gid == req.gid && vm.name == req.name)
+    {
+        return Err(MockError::conflict(format!(
+            "Virtual machine '{}' already exists in group {}",
+            req.name, req.gid
+        )));
+    }
+
+    let vmid = state
+        .vms
+        .keys()
+        .max()
+        .map(|vmid| VMID(vmid.0 + 1))
+        .unwrap_or_default();
+
+    let mut config = req.rest;
+    config.insert("name".to_owned(), json!(req.name));
+    config.insert("gid".to_owned(), json!(req.gid));
+    config.insert("cpus".to_owned(), json!(req.cpus));
+    config.insert("memory_mib".to_owned(), json!(req.memory_mib));
+    config.insert(
+        "disks".to_owned(),
+        req.disks
+            .iter()
+            .map(|d| json!({ "mid": d.mid, "mode": d.mode, "readonly": d.readonly }))
+            .collect(),
+    );
+    config.insert(
+        "nics".to_owned(),
+        req.nics
+            .iter()
+            .map(|n| json!({ "type": n.ty, "host": n.host }))
+            .collect(),
+    );
+
+    state.vms.insert(
+        vmid,
+        MockVM {
+            vmid,
+            gid: req.gid,
+            name: req.name,
+            config: Value::Object(config),
+        },
+    );
+
+    Ok(json!({ "vmid": vmid }))
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value;
+use sha2::Sha256;
+
+use super::{
+    Fixture, FixtureAssignment, FixtureGroup, FixtureMedia, FixtureNIC, FixturePool, FixtureUser,
+    MockError,
+};
+use crate::{GID, MID, MPID, NICID, PID, UID, VMID};
+
+/// All permissions the hypervisor knows about, their `PID` is their index in this list
+pub const PERMISSIONS: &[(&str, &str)] = &[
+    ("velocity.user.create", "Create new users"),
+    ("velocity.user.remove", "Remove users"),
+    ("velocity.user.list", "List all users"),
+    ("velocity.user.view", "View information about other users"),
+    ("velocity.user.assign", "Assign permissions to users"),
+    ("velocity.user.revoke", "Revoke permissions from users"),
+    ("velocity.group.create", "Create subgroups"),
+    ("velocity.group.remove", "Remove groups"),
+    ("velocity.group.view", "View group information"),
+    ("velocity.pool.list", "List the pools assigned to a group"),
+    ("velocity.pool.assign", "Assign pools to groups"),
+    ("velocity.pool.revoke", "Revoke pools from groups"),
+    ("velocity.media.list", "List the media of a group"),
+    ("velocity.media.create", "Create and upload media"),
+    ("velocity.media.remove", "Remove media"),
+    ("velocity.vm.create", "Create virtual machines"),
+];
+
+/// A virtual machine that has been created on the mock hypervisor
+#[derive(Debug, Clone)]
+pub struct MockVM {
+    pub vmid: VMID,
+    pub gid: GID,
+    pub name: String,
+    /// The full configuration the virtual machine has been created with
+    pub config: Value,
+}
+
+/// A chunked upload that has been started, but not completed yet
+#[derive(Debug, Clone)]
+pub struct MockUpload {
+    pub mid: MID,
+    /// The offset up to which chunks have been received
+    pub offset: u64,
+    /// The checksum of all chunks received until now
+    pub hasher: Sha256,
+    /// The checksum the client expects the media to have
+    pub expected: Option<String>,
+}
+
+/// The complete state of a `MockHypervisor`
+#[derive(Debug)]
+pub struct MockState {
+    /// The amount of seconds an authkey stays valid
+    pub authkey_lifetime: u64,
+    pub users: BTreeMap<UID, FixtureUser>,
+    pub groups: BTreeMap<GID, FixtureGroup>,
+    /// The permissions users hold on groups: `(uid, gid, pid)`
+    pub permissions: BTreeSet<(UID, GID, PID)>,
+    pub pools: BTreeMap<MPID, FixturePool>,
+    /// The pool assignments, keyed by `(gid, mpid)`
+    pub assignments: BTreeMap<(GID, MPID), FixtureAssignment>,
+    pub media: BTreeMap<MID, FixtureMedia>,
+    pub nics: BTreeMap<NICID, FixtureNIC>,
+    pub vms: BTreeMap<VMID, MockVM>,
+    /// The chunked uploads that are still in progress
+    pub uploads: BTreeMap<MID, MockUpload>,
+    /// All issued authkeys and the user and expiration time they belong to
+    pub authkeys: HashMap<String, (UID, u64)>,
+    /// Flips a bit in all uploaded data to simulate corruption in transit
+    pub corrupt_uploads: bool,
+    /// Serves the chunked upload routes, which are an experimental extension of the
+    /// hypervisor API. Enabled with the `experimental-chunked` feature, disable to act
+    /// like a hypervisor without them
+    pub chunked_uploads: bool,
+    /// A counter to generate unique ids from
+    counter: u64,
+}
+
+impl MockState {
+    /// Creates a new state from a fixture, ensuring the root group exists and the
+    /// root user holds all permissions on it
+    /// # Arguments
+    /// * `fixture` - The fixture to seed the state from
+    pub fn from_fixture(fixture: Fixture) -> Result<MockState, io::Error> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut state = MockState {
+            authkey_lifetime: fixture.authkey_lifetime,
+            users: fixture.users.into_iter().map(|u| (u.uid, u)).collect(),
+            groups: fixture.groups.into_iter().map(|g| (g.gid, g)).collect(),
+            permissions: BTreeSet::new(),
+            pools: fixture.pools.into_iter().map(|p| (p.mpid, p)).collect(),
+            assignments: fixture
+                .assignments
+                .into_iter()
+                .map(|a| ((a.gid, a.mpid), a))
+                .collect(),
+            media: fixture
+                .media
+                .into_iter()
+                .map(|m| (m.mid.clone(), m))
+                .collect(),
+            nics: fixture.nics.into_iter().map(|n| (n.nicid, n)).collect(),
+            vms: BTreeMap::new(),
+            uploads: BTreeMap::new(),
+            authkeys: HashMap::new(),
+            corrupt_uploads: false,
+            chunked_uploads: cfg!(feature = "experimental-chunked"),
+            counter: 0,
+        };
+
+        state.groups.insert(
+            GID(0),
+            FixtureGroup {
+                gid: GID(0),
+                parent_gid: GID(0),
+                name: "root".to_owned(),
+            },
+        );
+
+        if state.users.contains_key(&UID(0)) {
+            for pid in 0..PERMISSIONS.len() {
+                state.permissions.insert((UID(0), GID(0), PID(pid as i64)));
+            }
+        }
+
+        for permission in fixture.permissions {
+            let pid = permission_id(&permission.permission).ok_or_else(|| {
+                invalid(format!("Unknown permission '{}'", permission.permission))
+            })?;
+            state
+                .permissions
+                .insert((permission.uid, permission.gid, pid));
+        }
+
+        for group in state.groups.values() {
+            if !state.groups.contains_key(&group.parent_gid) {
+                return Err(invalid(format!(
+                    "Parent group {} of group {} does not exist",
+                    group.parent_gid, group.gid
+                )));
+            }
+        }
+
+        Ok(state)
+    }
+
+    /// Issues a new authkey for a user
+    /// # Arguments
+    /// * `uid` - The user to issue the key for
+    /// # Returns
+    /// The key and the UNIX timestamp it expires at
+    pub fn issue_authkey(&mut self, uid: UID) -> (String, u64) {
+        let key = format!("mock-authkey-{:016x}", self.next_id());
+        let expires = now() + self.authkey_lifetime;
+
+        self.authkeys.insert(key.clone(), (uid, expires));
+
+        (key, expires)
+    }
+
+    /// Checks an authkey and returns the user it belongs to
+    /// # Arguments
+    /// * `authkey` - The authkey to check
+    pub fn authenticate(&self, authkey: &str) -> Result<UID, MockError> {
+        match self.authkeys.get(authkey) {
+            Some((uid, expires)) if *expires > now() && self.users.contains_key(uid) => Ok(*uid),
+            _ => Err(MockError::unauthorized()),
+        }
+    }
+
+    /// Returns the group and all its ancestors, starting with the group itself
+    /// # Arguments
+    /// * `gid` - The group to start at
+    pub fn ancestors(&self, gid: GID) -> Vec<GID> {
+        let mut res = vec![gid];
+        let mut cur = gid;
+
+        while let Some(group) = self.groups.get(&cur) {
+            if group.gid == group.parent_gid || res.contains(&group.parent_gid) {
+                break;
+            }
+            cur = group.parent_gid;
+            res.push(cur);
+        }
+
+        res
+    }
+
+    /// Returns the group and all its descendants
+    /// # Arguments
+    /// * `gid` - The group to start at
+    pub fn descendants(&self, gid: GID) -> Vec<GID> {
+        let mut res = vec![gid];
+        let mut i = 0;
+
+        while i < res.len() {
+            let cur = res[i];
+            res.extend(
+                self.groups
+                    .values()
+                    .filter(|g| g.parent_gid == cur && g.gid != cur)
+                    .map(|g| g.gid),
+            );
+            i += 1;
+        }
+
+        res
+    }
+
+    /// Checks if a user holds a permission on a group, either directly or inherited
+    /// from one of the group's ancestors
+    /// # Arguments
+    /// * `uid` - The user to check
+    /// * `gid` - The group to check on
+    /// * `permission` - The name of the permission to check for
+    pub fn has_permission(&self, uid: UID, gid: GID, permission: &str) -> bool {
+        match permission_id(permission) {
+            Some(pid) => self
+                .ancestors(gid)
+                .into_iter()
+                .any(|gid| self.permissions.contains(&(uid, gid, pid))),
+            None => false,
+        }
+    }
+
+    /// Ensures that a group exists and a user holds a permission on it
+    /// # Arguments
+    /// * `uid` - The user to check
+    /// * `gid` - The group to check on
+    /// * `permission` - The name of the permission to check for
+    pub fn require(&self, uid: UID, gid: GID, permission: &str) -> Result<(), MockError> {
+        if !self.groups.contains_key(&gid) {
+            return Err(MockError::not_found(format!("Group {}", gid)));
+        }
+
+        match self.has_permission(uid, gid, permission) {
+            true => Ok(()),
+            false => Err(MockError::missing_permission(permission, gid)),
+        }
+    }
+
+    /// Returns the permissions a user holds on a group, including inherited ones
+    /// # Arguments
+    /// * `uid` - The user to look up
+    /// * `gid` - The group to look up
+    pub fn effective_permissions(&self, uid: UID, gid: GID) -> BTreeSet<PID> {
+        let ancestors = self.ancestors(gid);
+
+        self.permissions
+            .iter()
+            .filter(|(u, g, _)| *u == uid && ancestors.contains(g))
+            .map(|(_, _, pid)| *pid)
+            .collect()
+    }
+
+    /// Returns the next unique id
+    pub fn next_id(&mut self) -> u64 {
+        self.counter += 1;
+        self.counter
+    }
+}
+
+/// Looks up the `PID` of a permission by its name
+/// # Arguments
+/// * `name` - The permission name, e.g. `velocity.media.list`
+pub fn permission_id(name: &str) -> Option<PID> {
+    PERMISSIONS
+        .iter()
+        .position(|(n, _)| *n == name)
+        .map(|pid| PID(pid as i64))
+}
+
+/// Returns the current UNIX timestamp in seconds
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,107 @@
+//! Redaction of secrets before anything gets logged
+
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+use crate::transport::TransportBody;
+
+/// The placeholder secrets get replaced with
+const REDACTED: &str = "<redacted>";
+
+/// JSON keys whose values are secrets
+const SECRET_KEYS: &[&str] = &["authkey", "password"];
+
+/// Headers whose values are secrets
+const SECRET_HEADERS: &[&str] = &["x-velocity-authkey", "authorization", "cookie"];
+
+/// Renders headers for logging, redacting all secret values
+/// # Arguments
+/// * `headers` - The headers to render
+pub fn headers(headers: &HeaderMap) -> String {
+    let rendered: Vec<String> = headers
+        .iter()
+        .map(|(name, value)| {
+            let value = match SECRET_HEADERS.contains(&name.as_str()) {
+                true => REDACTED,
+                false => value.to_str().unwrap_or("<binary>"),
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect();
+
+    format!("[{}]", rendered.join(", "))
+}
+
+/// Renders a request body for logging, redacting all secret values
+/// # Arguments
+/// * `body` - The body to render
+pub fn body(body: &TransportBody) -> String {
+    match body {
+        TransportBody::Empty => "<empty>".to_owned(),
+        TransportBody::Bytes(bytes) => json(bytes),
+        TransportBody::Stream(_) => "<stream>".to_owned(),
+    }
+}
+
+/// Renders a raw JSON body for logging, redacting all secret values.
+/// Bodies that are not JSON are only described by their length, they could contain anything
+/// # Arguments
+/// * `bytes` - The raw body to render
+pub fn json(bytes: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => format!("<{} bytes>", bytes.len()),
+    }
+}
+
+/// Recursively replaces the values of all secret keys
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    *value = Value::String(REDACTED.to_owned());
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_redact_json() {
+        let body = br#"{"username":"root","password":"secret","nested":[{"authkey":"key"}]}"#;
+        let rendered = json(body);
+
+        assert!(!rendered.contains("secret"));
+        assert!(!rendered.contains("\"key\""));
+        assert!(rendered.contains("root"));
+    }
+
+    #[test]
+    fn test_redact_non_json() {
+        assert_eq!(json(b"password=secret"), "<15 bytes>");
+    }
+
+    #[test]
+    fn test_redact_headers() {
+        let mut map = HeaderMap::new();
+        map.insert("x-velocity-authkey", HeaderValue::from_static("key"));
+        map.insert("x-velocity-name", HeaderValue::from_static("disk"));
+
+        let rendered = headers(&map);
+        assert!(!rendered.contains("key,") && !rendered.ends_with("key]"));
+        assert!(rendered.contains("x-velocity-name: disk"));
+    }
+}
@@ -1,13 +1,21 @@
-//! Reqwest wrappers
+//! Request wrappers
 
-use reqwest::{RequestBuilder, StatusCode};
+use web_time::Instant;
+
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    StatusCode,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
-    error::{VelocityAPIError, VelocityError},
+    error::{ClientError, UnexpectedResponse, VelocityAPIError, VelocityError},
+    transport::{TransportBody, TransportRequest, TransportResponse},
     Velocity,
 };
 
+use super::redact;
+
 /// A JSON response, containing a status code and a response structure
 #[allow(dead_code)]
 pub struct JSONResponse<T: DeserializeOwned> {
@@ -19,8 +27,72 @@ pub struct JSONResponse<T: DeserializeOwned> {
 
 impl Velocity {
     /// Generates a full URL from the internal base url and the endpoint
+    /// # Arguments
+    /// * `endpoint` - The endpoint to generate the URL for: e.g. `/u/auth`
     pub fn url(&self, endpoint: &str) -> String {
-        format!("{}/{}", self.base_url, endpoint)
+        format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            endpoint.trim_start_matches('/')
+        )
+    }
+
+    /// Delivers a request through the transport, logging the request and its outcome.
+    ///
+    /// Method, endpoint, status, latency and response size are logged at `debug`,
+    /// headers and bodies at `trace`. All secrets are redacted
+    /// # Arguments
+    /// * `request` - The request to deliver
+    pub async fn execute(
+        &self,
+        request: TransportRequest,
+    ) -> Result<TransportResponse, VelocityError> {
+        let method = request.method.clone();
+        let endpoint = request
+            .url
+            .strip_prefix(self.base_url.trim_end_matches('/'))
+            .unwrap_or(&request.url)
+            .to_owned();
+
+        log::trace!(
+            "{} {} headers: {} body: {}",
+            method,
+            endpoint,
+            redact::headers(&request.headers),
+            redact::body(&request.body)
+        );
+
+        let start = Instant::now();
+        let res = self.transport.execute(request).await;
+        let elapsed = start.elapsed();
+
+        match &res {
+            Ok(response) => {
+                log::debug!(
+                    "{} {} -> {} in {:.1?} ({} bytes)",
+                    method,
+                    endpoint,
+                    response.status.as_u16(),
+                    elapsed,
+                    response.body.len()
+                );
+                log::trace!(
+                    "{} {} response body: {}",
+                    method,
+                    endpoint,
+                    redact::json(&response.body)
+                );
+            }
+            Err(e) => log::warn!(
+                "{} {} failed after {:.1?}: {}",
+                method,
+                endpoint,
+                elapsed,
+                e
+            ),
+        }
+
+        res
     }
 
     /// Execute a raw request, expecting a JSON response
@@ -30,69 +102,47 @@ impl Velocity {
     /// A `JSONResponse` struct containing the status code and the expected response structure
     pub async fn request_json_raw<RESPONSE>(
         &self,
-        request: RequestBuilder,
+        request: TransportRequest,
     ) -> Result<JSONResponse<RESPONSE>, VelocityError>
     where
         RESPONSE: DeserializeOwned + std::fmt::Debug,
     {
-        let r_response = request.send().await?;
-
-        let status = r_response.status();
-
-        match status {
-            StatusCode::OK => {
-                let response = r_response.json::<RESPONSE>().await?;
-
-                Ok(JSONResponse { status, response })
-            }
-            StatusCode::BAD_REQUEST => {
-                #[derive(Deserialize)]
-                struct In {
-                    reason: String,
-                }
-
-                let status: u32 = r_response.status().as_u16() as u32;
-                let err = r_response.json::<In>().await?;
-
-                Err(VelocityError::APIError(VelocityAPIError {
-                    code: status,
-                    message: err.reason,
-                }))
-            }
-            _ => Err(VelocityError::APIError(
-                r_response.json::<VelocityAPIError>().await?,
-            )),
-        }
+        json_response(self.execute(request).await?)
     }
 
     /// Execute a raw request expecting no body
     /// # Arguments
     /// * `request` - The built request to send
-    pub async fn request_raw(&self, request: RequestBuilder) -> Result<StatusCode, VelocityError> {
-        let r_response = request.send().await?;
+    pub async fn request_raw(
+        &self,
+        request: TransportRequest,
+    ) -> Result<StatusCode, VelocityError> {
+        let response = RawResponse(self.execute(request).await?);
 
-        let status = r_response.status();
+        match response.0.status {
+            StatusCode::OK => Ok(response.0.status),
+            _ => Err(response.into_error()),
+        }
+    }
 
-        match status {
-            StatusCode::OK => Ok(status),
-            StatusCode::BAD_REQUEST => {
-                #[derive(Deserialize)]
-                struct In {
-                    reason: String,
-                }
+    /// Builds a request to the remote API with a JSON body
+    /// # Arguments
+    /// * `method` - The method to use for the request
+    /// * `endpoint` - The endpoint to route the request to: e.g. `/u/auth`
+    /// * `request` - The request structure to serialize into the body
+    pub fn json_request<T: Serialize>(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+        request: &T,
+    ) -> Result<TransportRequest, VelocityError> {
+        let mut req = TransportRequest::new(method, self.url(endpoint));
 
-                let status: u32 = r_response.status().as_u16() as u32;
-                let err = r_response.json::<In>().await?;
+        req.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        req.body = TransportBody::Bytes(serialize(request)?.into());
 
-                Err(VelocityError::APIError(VelocityAPIError {
-                    code: status,
-                    message: err.reason,
-                }))
-            }
-            _ => Err(VelocityError::APIError(
-                r_response.json::<VelocityAPIError>().await?,
-            )),
-        }
+        Ok(req)
     }
 
     /// Executes a request to the remote API and expects a JSON response
@@ -112,10 +162,7 @@ impl Velocity {
         REQUEST: Serialize,
         RESPONSE: DeserializeOwned + std::fmt::Debug,
     {
-        let request = self
-            .http_client
-            .request(method, self.url(endpoint))
-            .json(&request);
+        let request = self.json_request(method, endpoint, request)?;
 
         self.request_json_raw(request).await
     }
@@ -131,11 +178,94 @@ impl Velocity {
         endpoint: &str,
         request: &T,
     ) -> Result<StatusCode, VelocityError> {
-        let request = self
-            .http_client
-            .request(method, self.url(endpoint))
-            .json(&request);
+        let request = self.json_request(method, endpoint, request)?;
 
         self.request_raw(request).await
     }
 }
+
+/// Serializes a structure into a JSON body
+/// # Arguments
+/// * `value` - The structure to serialize
+pub(crate) fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, VelocityError> {
+    serde_json::to_vec(value)
+        .map_err(|e| VelocityError::Client(ClientError::InvalidValue(e.to_string())))
+}
+
+/// Interprets a fully received response, expecting a JSON response
+/// # Arguments
+/// * `response` - The response to interpret
+pub(crate) fn json_response<RESPONSE: DeserializeOwned>(
+    response: TransportResponse,
+) -> Result<JSONResponse<RESPONSE>, VelocityError> {
+    let response = RawResponse(response);
+
+    match response.0.status {
+        StatusCode::OK => Ok(JSONResponse {
+            status: response.0.status,
+            response: response.parse()?,
+        }),
+        _ => Err(response.into_error()),
+    }
+}
+
+/// A fully received response that has not been interpreted yet
+struct RawResponse(TransportResponse);
+
+impl RawResponse {
+    /// Deserializes the body as JSON, erroring with an `UnexpectedResponse` if that fails
+    fn parse<T: DeserializeOwned>(&self) -> Result<T, VelocityError> {
+        serde_json::from_slice(&self.0.body).map_err(|e| self.unexpected(Some(e)))
+    }
+
+    /// Interprets a non-OK response as the error the hypervisor responded with.
+    ///
+    /// `400` errors carry a `reason`, all others a `VelocityAPIError` structure.
+    /// If the body matches neither, an `UnexpectedResponse` is returned
+    fn into_error(self) -> VelocityError {
+        let status = self.0.status.as_u16();
+
+        let res = match self.0.status {
+            StatusCode::BAD_REQUEST => {
+                #[derive(Deserialize)]
+                struct In {
+                    reason: String,
+                }
+
+                self.parse::<In>().map(|err| VelocityAPIError {
+                    code: status as u32,
+                    message: err.reason,
+                    status,
+                })
+            }
+            _ => self.parse::<VelocityAPIError>().map(|mut err| {
+                err.status = status;
+                err
+            }),
+        };
+
+        match res {
+            Ok(err) => VelocityError::APIError(err),
+            Err(err) => err,
+        }
+    }
+
+    /// Creates an `UnexpectedResponse` error from this response
+    /// # Arguments
+    /// * `source` - The error that occured while interpreting the body
+    fn unexpected(&self, source: Option<serde_json::Error>) -> VelocityError {
+        let content_type = self
+            .0
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_owned());
+
+        VelocityError::UnexpectedResponse(UnexpectedResponse::new(
+            self.0.status.as_u16(),
+            content_type,
+            &self.0.body,
+            source,
+        ))
+    }
+}
@@ -0,0 +1,294 @@
+//! Resolves user-supplied names to the ids the API works with.
+//!
+//! Every resolver accepts an id as well, anything that parses as an id is used as is
+//! without contacting the hypervisor. Names have to match exactly, a name that matches
+//! more than one resource is an error listing all candidates
+use std::collections::{HashMap, HashSet};
+
+use futures_util::{stream, StreamExt};
+
+use crate::{
+    endpoints::{m::MMediaListPOSTRes, u::UGroupListPOSTRes},
+    error::{ClientError, VelocityError},
+    Velocity, GID, MID, MPID, NICID, UID,
+};
+
+/// How many listings a resolution requests at the same time
+const CONCURRENT_LISTINGS: usize = 8;
+
+impl Velocity {
+    /// Resolves a user by its name or `UID`
+    /// # Arguments
+    /// * `name_or_id` - The name or `UID` of the user
+    pub async fn resolve_user(&self, name_or_id: &str) -> Result<UID, VelocityError> {
+        if let Ok(uid) = name_or_id.parse() {
+            return Ok(uid);
+        }
+
+        let matches = self
+            .user_list()
+            .await?
+            .into_iter()
+            .filter(|u| u.name == name_or_id)
+            .map(|u| (u.uid, format!("{} (UID {})", u.name, u.uid)))
+            .collect();
+
+        pick("user", name_or_id, matches)
+    }
+
+    /// Resolves a group by its name, its path or its `GID`. A path lists the names of the
+    /// group and its ancestors separated by `/`, e.g. `root/team/ci`, starting at the topmost
+    /// group the user can see. Paths have to match completely, a single name matches
+    /// the groups of that name anywhere
+    /// # Arguments
+    /// * `path_or_id` - The name, path or `GID` of the group
+    pub async fn resolve_group(&self, path_or_id: &str) -> Result<GID, VelocityError> {
+        if let Ok(gid) = path_or_id.parse() {
+            return Ok(gid);
+        }
+
+        let wanted: Vec<&str> = path_or_id.split('/').filter(|c| !c.is_empty()).collect();
+        let groups = self.group_list().await?;
+        let by_gid: HashMap<GID, &UGroupListPOSTRes> = groups.iter().map(|g| (g.gid, g)).collect();
+
+        let matches = groups
+            .iter()
+            .filter_map(|g| {
+                let path = group_path(&by_gid, g);
+                let matched = match wanted.len() {
+                    0 => false,
+                    1 => g.name == wanted[0],
+                    _ => path == wanted,
+                };
+                matched.then(|| (g.gid, format!("{} (GID {})", path.join("/"), g.gid)))
+            })
+            .collect();
+
+        pick("group", path_or_id, matches)
+    }
+
+    /// Resolves a media pool by its name or `MPID`. Names are looked up in all pools
+    /// if the user can list the pools of the root group, otherwise in the pools
+    /// of all groups the user can list pools of
+    /// # Arguments
+    /// * `name_or_id` - The name or `MPID` of the pool
+    pub async fn resolve_pool(&self, name_or_id: &str) -> Result<MPID, VelocityError> {
+        if let Ok(mpid) = name_or_id.parse() {
+            return Ok(mpid);
+        }
+
+        let groups = self.group_list().await?;
+
+        // The root group lists every pool, which saves asking every group for its pools
+        let all = match groups.iter().any(|g| g.gid == GID(0)) {
+            true => match self.pool_list(GID(0)).await {
+                Ok(pools) => Some(pools),
+                Err(e) if e.is_permission_denied() => None,
+                Err(e) => return Err(e),
+            },
+            false => None,
+        };
+        let listed = match all {
+            Some(pools) => pools,
+            None => {
+                let gids = groups.iter().map(|g| g.gid).collect();
+                list_all(gids, |gid| self.pool_list(gid))
+                    .await?
+                    .into_iter()
+                    .flat_map(|(_, pools)| pools.unwrap_or_default())
+                    .collect()
+            }
+        };
+
+        let mut pools: Vec<(MPID, String)> = Vec::new();
+        for pool in listed {
+            let known = pools.iter().any(|(mpid, _)| *mpid == pool.mpid);
+            if pool.name == name_or_id && !known {
+                pools.push((pool.mpid, format!("{} (MPID {})", pool.name, pool.mpid)));
+            }
+        }
+
+        pick("pool", name_or_id, pools)
+    }
+
+    /// Resolves a piece of media by its name or `MID`. Names are looked up in the media
+    /// of all groups the user can list media of
+    /// # Arguments
+    /// * `name_or_id` - The name or `MID` of the media
+    pub async fn resolve_media(&self, name_or_id: &str) -> Result<MID, VelocityError> {
+        if is_mid(name_or_id) {
+            return Ok(name_or_id.to_owned());
+        }
+
+        let mut media: Vec<(MID, String)> = Vec::new();
+        for m in self.all_media().await? {
+            let known = media.iter().any(|(mid, _)| *mid == m.mid);
+            if m.name == name_or_id && !known {
+                let description = format!("{} (MID {})", m.name, m.mid);
+                media.push((m.mid, description));
+            }
+        }
+
+        pick("media", name_or_id, media)
+    }
+
+    /// Lists the media of all groups the user can list media of. Groups list the media
+    /// of their ancestors as well, so only the groups without visible subgroups are asked.
+    /// If one of them does not allow listing its media, its parent is asked instead.
+    /// Media shows up once for every listing it is part of
+    async fn all_media(&self) -> Result<Vec<MMediaListPOSTRes>, VelocityError> {
+        let groups = self.group_list().await?;
+        let by_gid: HashMap<GID, &UGroupListPOSTRes> = groups.iter().map(|g| (g.gid, g)).collect();
+        let parents: HashSet<GID> = groups
+            .iter()
+            .filter(|g| g.parent_gid != g.gid)
+            .map(|g| g.parent_gid)
+            .collect();
+
+        let mut pending: Vec<GID> = groups
+            .iter()
+            .map(|g| g.gid)
+            .filter(|gid| !parents.contains(gid))
+            .collect();
+        let mut asked: HashSet<GID> = HashSet::new();
+        let mut media = Vec::new();
+
+        while !pending.is_empty() {
+            asked.extend(pending.iter().copied());
+
+            let mut next = Vec::new();
+            for (gid, res) in list_all(pending, |gid| self.media_list(gid)).await? {
+                match res {
+                    Some(listed) => media.extend(listed),
+                    None => {
+                        let parent = by_gid[&gid].parent_gid;
+                        if by_gid.contains_key(&parent)
+                            && !asked.contains(&parent)
+                            && !next.contains(&parent)
+                        {
+                            next.push(parent);
+                        }
+                    }
+                }
+            }
+            pending = next;
+        }
+
+        Ok(media)
+    }
+
+    /// Resolves a host NIC by its identifier, e.g. `en0`, its description or its `NICID`
+    /// # Arguments
+    /// * `name_or_id` - The identifier, description or `NICID` of the NIC
+    pub async fn resolve_nic(&self, name_or_id: &str) -> Result<NICID, VelocityError> {
+        if let Ok(nicid) = name_or_id.parse() {
+            return Ok(nicid);
+        }
+
+        let matches = self
+            .nic_list()
+            .await?
+            .into_iter()
+            .filter(|n| n.identifier == name_or_id || n.description == name_or_id)
+            .map(|n| {
+                let description =
+                    format!("{} - {} (NICID {})", n.identifier, n.description, n.nicid);
+                (n.nicid, description)
+            })
+            .collect();
+
+        pick("NIC", name_or_id, matches)
+    }
+}
+
+/// Returns the names of a group and its visible ancestors, starting at the topmost one
+/// # Arguments
+/// * `groups` - All visible groups by their `GID`
+/// * `group` - The group to return the path of
+fn group_path<'a>(
+    groups: &HashMap<GID, &'a UGroupListPOSTRes>,
+    group: &'a UGroupListPOSTRes,
+) -> Vec<&'a str> {
+    let mut path = vec![group.name.as_str()];
+    let mut seen = vec![group.gid];
+    let mut cur = group;
+
+    while let Some(parent) = groups.get(&cur.parent_gid) {
+        if seen.contains(&parent.gid) {
+            break;
+        }
+        path.push(parent.name.as_str());
+        seen.push(parent.gid);
+        cur = parent;
+    }
+
+    path.reverse();
+    path
+}
+
+/// Requests a listing for several groups, a few at a time
+/// # Arguments
+/// * `gids` - The groups to request the listing for
+/// * `list` - Requests the listing of a single group
+/// # Returns
+/// The listing of every group, `None` if the user is not allowed to see it
+async fn list_all<T, F, Fut>(
+    gids: Vec<GID>,
+    list: F,
+) -> Result<Vec<(GID, Option<Vec<T>>)>, VelocityError>
+where
+    F: Fn(GID) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<T>, VelocityError>>,
+{
+    stream::iter(gids)
+        .map(|gid| {
+            let listing = list(gid);
+            async move {
+                match listing.await {
+                    Ok(listing) => Ok((gid, Some(listing))),
+                    Err(e) if e.is_permission_denied() => Ok((gid, None)),
+                    Err(e) => Err(e),
+                }
+            }
+        })
+        .buffered(CONCURRENT_LISTINGS)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+/// Checks if a string is formatted like a `MID`, which is a UUID
+/// # Arguments
+/// * `s` - The string to check
+fn is_mid(s: &str) -> bool {
+    s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Picks the only match of a lookup
+/// # Arguments
+/// * `kind` - The kind of resource that has been looked up
+/// * `name` - The name that has been looked up
+/// * `matches` - The ids of all matching resources along with a description of them
+fn pick<T>(
+    kind: &'static str,
+    name: &str,
+    mut matches: Vec<(T, String)>,
+) -> Result<T, VelocityError> {
+    match matches.len() {
+        0 => Err(VelocityError::Client(ClientError::UnknownName {
+            kind,
+            name: name.to_owned(),
+        })),
+        1 => Ok(matches.remove(0).0),
+        _ => Err(VelocityError::Client(ClientError::AmbiguousName {
+            kind,
+            name: name.to_owned(),
+            candidates: matches.into_iter().map(|(_, d)| d).collect(),
+        })),
+    }
+}
@@ -0,0 +1,214 @@
+//! Options and state shared by all media transfers
+
+use std::{future::Future, io};
+
+use futures_util::future;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt};
+pub use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "experimental-chunked")]
+mod journal;
+mod progress;
+#[cfg(feature = "native")]
+mod throttle;
+#[cfg(feature = "experimental-chunked")]
+pub use journal::UploadJournal;
+pub use progress::*;
+#[cfg(feature = "native")]
+#[cfg(all(feature = "native", feature = "experimental-chunked"))]
+pub(crate) use throttle::pieces;
+#[cfg(feature = "native")]
+pub(crate) use throttle::throttle;
+#[cfg(feature = "native")]
+pub use throttle::BandwidthLimit;
+
+use crate::error::{ClientError, VelocityError};
+
+/// The default size of a single chunk in chunked uploads: 8 MiB
+pub const DEFAULT_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The default amount of times a failed chunk is retried before giving up
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Options that control how a transfer of media is executed
+#[derive(Debug, Clone)]
+pub struct TransferOptions {
+    /// The token that cancels the transfer
+    pub cancel: Option<CancellationToken>,
+    /// The size of a single chunk for chunked uploads
+    pub chunk_size: u64,
+    /// How many times a chunk that failed due to a connection problem is retried
+    pub retries: u32,
+    /// The SHA-256 checksum the uploaded data is expected to have, as lowercase hex
+    pub sha256: Option<String>,
+    /// The bandwidth limit for this transfer, the client's default limit is used if this is `None`
+    #[cfg(feature = "native")]
+    pub limit: Option<BandwidthLimit>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            cancel: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            retries: DEFAULT_RETRIES,
+            sha256: None,
+            #[cfg(feature = "native")]
+            limit: None,
+        }
+    }
+}
+
+impl TransferOptions {
+    /// Creates new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the transfer cancellable. Once the token is cancelled, the transfer gets
+    /// aborted and fails with `ClientError::Cancelled`
+    /// # Arguments
+    /// * `token` - The token that cancels the transfer
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Sets the size of a single chunk for chunked uploads
+    /// # Arguments
+    /// * `chunk_size` - The size in bytes, at least 1
+    pub fn chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Sets how many times a chunk is retried after a connection problem
+    /// # Arguments
+    /// * `retries` - The amount of retries per chunk
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the SHA-256 checksum the data is expected to have. The hypervisor verifies the
+    /// upload against it and rejects it on a mismatch
+    /// # Arguments
+    /// * `sha256` - The checksum as hex
+    pub fn expected_sha256(mut self, sha256: &str) -> Self {
+        self.sha256 = Some(sha256.to_ascii_lowercase());
+        self
+    }
+
+    /// Limits the bandwidth of the transfer instead of using the client's default limit
+    /// # Arguments
+    /// * `limit` - The limit to use, it can be changed while the transfer is running
+    #[cfg(feature = "native")]
+    pub fn limit(mut self, limit: BandwidthLimit) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Checks a checksum against the expected one, if there is one
+    /// # Arguments
+    /// * `actual` - The checksum that has been computed
+    pub(crate) fn verify_sha256(&self, actual: &str) -> Result<(), VelocityError> {
+        verify_sha256(self.sha256.as_deref(), actual)
+    }
+
+    /// Runs a transfer future, aborting it if the cancellation token fires first
+    /// # Arguments
+    /// * `transfer` - The future doing the transfer
+    /// * `transferred` - Reports the amount of bytes transferred on cancellation
+    pub(crate) async fn run<T, F>(
+        &self,
+        transfer: F,
+        transferred: impl FnOnce() -> u64,
+    ) -> Result<T, VelocityError>
+    where
+        F: Future<Output = Result<T, VelocityError>>,
+    {
+        let token = match &self.cancel {
+            Some(token) => token,
+            None => return transfer.await,
+        };
+
+        let cancelled = || {
+            Err(VelocityError::Client(ClientError::Cancelled {
+                transferred: transferred(),
+            }))
+        };
+
+        // Do not even start the transfer if it has been cancelled already
+        if token.is_cancelled() {
+            return cancelled();
+        }
+
+        // Dropping the transfer future aborts the underlying request
+        let token_cancelled = token.cancelled();
+        futures_util::pin_mut!(transfer, token_cancelled);
+
+        match future::select(transfer, token_cancelled).await {
+            future::Either::Left((res, _)) => res,
+            future::Either::Right(_) => cancelled(),
+        }
+    }
+}
+
+/// Computes the SHA-256 checksum of all the data a reader provides
+/// # Arguments
+/// * `reader` - The reader to consume
+/// # Returns
+/// The checksum as lowercase hex
+pub async fn sha256<R: AsyncRead>(reader: R) -> io::Result<String> {
+    futures_util::pin_mut!(reader);
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Formats bytes as lowercase hex
+/// # Arguments
+/// * `bytes` - The bytes to format
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Checks that a checksum is a SHA-256 digest in hex, as the hypervisor expects it
+/// # Arguments
+/// * `sha256` - The checksum to check
+pub(crate) fn check_sha256(sha256: &str) -> Result<(), VelocityError> {
+    if sha256.len() == 64 && sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(VelocityError::Client(ClientError::InvalidValue(format!(
+            "'{}' is not a SHA-256 checksum",
+            sha256.escape_debug()
+        ))))
+    }
+}
+
+/// Checks a checksum against an expected one
+/// # Arguments
+/// * `expected` - The expected checksum, nothing is checked if this is `None`
+/// * `actual` - The checksum that has been computed
+pub(crate) fn verify_sha256(expected: Option<&str>, actual: &str) -> Result<(), VelocityError> {
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(actual) => {
+            Err(VelocityError::Client(ClientError::ChecksumMismatch {
+                expected: expected.to_owned(),
+                actual: actual.to_owned(),
+            }))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,58 @@
+//! The journal of a chunked upload, which allows resuming it
+
+use std::path::PathBuf;
+#[cfg(feature = "native")]
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "native")]
+use crate::error::VelocityError;
+use crate::{GID, MID, MPID};
+
+/// The local record of a chunked upload, used to resume it after an interruption
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UploadJournal {
+    /// The media id of the media being uploaded
+    pub mid: MID,
+    /// The media pool the media lives in
+    pub mpid: MPID,
+    /// The group that owns the media
+    pub gid: GID,
+    /// The name of the media
+    pub name: String,
+    /// The file that gets uploaded
+    pub source: PathBuf,
+    /// The total size of the upload
+    pub size: u64,
+    /// The offset up to which the hypervisor has acknowledged the upload
+    pub offset: u64,
+}
+
+#[cfg(feature = "native")]
+impl UploadJournal {
+    /// Loads a journal from a file
+    /// # Arguments
+    /// * `path` - The path to the journal file
+    pub async fn load(path: &Path) -> Result<UploadJournal, VelocityError> {
+        let data = tokio::fs::read(path).await?;
+
+        serde_json::from_slice(&data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e).into())
+    }
+
+    /// Saves this journal to a file. The file is replaced atomically so an interruption
+    /// never leaves a partially written journal behind
+    /// # Arguments
+    /// * `path` - The path to the journal file
+    pub async fn save(&self, path: &Path) -> Result<(), VelocityError> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, path).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! Progress reporting for media transfers
+
+use std::{collections::VecDeque, fmt::Display, sync::Mutex, time::Duration};
+
+use tokio::sync::watch;
+use web_time::Instant;
+
+/// The time window the instantaneous rate is averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(2);
+
+/// The phase a transfer is in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TransferPhase {
+    /// Data that has been transferred before is read again to compute its checksum
+    Hashing,
+    /// Data is being sent to the hypervisor
+    #[default]
+    Uploading,
+    /// All data has been sent, the hypervisor is processing it
+    Processing,
+}
+
+impl Display for TransferPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Hashing => "hashing",
+                Self::Uploading => "uploading",
+                Self::Processing => "processing",
+            }
+        )
+    }
+}
+
+/// A snapshot of the progress of a transfer
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransferProgress {
+    /// The phase the transfer is in
+    pub phase: TransferPhase,
+    /// The amount of bytes done in the current phase
+    pub done: u64,
+    /// The total amount of bytes, if known
+    pub total: Option<u64>,
+    /// The rate over the last few seconds in bytes per second
+    pub rate: f64,
+    /// The rate since the start of the transfer in bytes per second
+    pub average_rate: f64,
+    /// The estimated time until the transfer is complete, if it can be estimated
+    pub eta: Option<Duration>,
+    /// The time since the start of the transfer
+    pub elapsed: Duration,
+}
+
+/// Receives progress updates of a transfer. This is implemented for callbacks taking
+/// a `&TransferProgress`, for `tokio::sync::watch::Sender<TransferProgress>` and for `()`,
+/// which discards all updates
+pub trait ProgressHandler: Send + Sync {
+    /// Handles a progress update
+    /// # Arguments
+    /// * `progress` - The current progress
+    fn progress(&self, progress: &TransferProgress);
+}
+
+impl<F: Fn(&TransferProgress) + Send + Sync> ProgressHandler for F {
+    fn progress(&self, progress: &TransferProgress) {
+        self(progress)
+    }
+}
+
+impl ProgressHandler for watch::Sender<TransferProgress> {
+    fn progress(&self, progress: &TransferProgress) {
+        self.send_replace(progress.clone());
+    }
+}
+
+impl ProgressHandler for () {
+    fn progress(&self, _progress: &TransferProgress) {}
+}
+
+/// Tracks the progress of a transfer, computes rates and reports it to a handler.
+/// All transfers use one internally, it can also be used to aggregate several transfers
+pub struct ProgressTracker<P> {
+    handler: P,
+    total: Option<u64>,
+    state: Mutex<TrackerState>,
+}
+
+/// The mutable state of a `ProgressTracker`
+struct TrackerState {
+    start: Instant,
+    /// The amount of bytes that were done before the transfer started, e.g. when resuming
+    initial: Option<u64>,
+    /// Recent samples of `(time, done)` to compute the instantaneous rate from
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl<P: ProgressHandler> ProgressTracker<P> {
+    /// Creates a new tracker, the transfer is considered to start now
+    /// # Arguments
+    /// * `handler` - The handler to report to
+    /// * `total` - The total amount of bytes, if known
+    pub fn new(handler: P, total: Option<u64>) -> Self {
+        Self {
+            handler,
+            total,
+            state: Mutex::new(TrackerState {
+                start: Instant::now(),
+                initial: None,
+                samples: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Reports the current progress
+    /// # Arguments
+    /// * `phase` - The phase the transfer is in
+    /// * `done` - The amount of bytes done
+    pub fn report(&self, phase: TransferPhase, done: u64) {
+        let progress = {
+            let mut state = self.state.lock().expect("Lock progress state");
+            let now = Instant::now();
+            let elapsed = now - state.start;
+
+            // Rates are only meaningful while data is transferred
+            let (rate, average_rate) = match phase {
+                TransferPhase::Uploading => {
+                    let initial = *state.initial.get_or_insert(done);
+
+                    state.samples.push_back((now, done));
+                    while state
+                        .samples
+                        .front()
+                        .is_some_and(|(t, _)| now - *t > RATE_WINDOW)
+                    {
+                        state.samples.pop_front();
+                    }
+
+                    let rate = match state.samples.front() {
+                        Some((t, d)) if now > *t => {
+                            done.saturating_sub(*d) as f64 / (now - *t).as_secs_f64()
+                        }
+                        _ => 0.0,
+                    };
+                    let average_rate = match elapsed.is_zero() {
+                        true => 0.0,
+                        false => done.saturating_sub(initial) as f64 / elapsed.as_secs_f64(),
+                    };
+
+                    (rate, average_rate)
+                }
+                _ => (0.0, 0.0),
+            };
+
+            let eta = match (phase, self.total) {
+                (TransferPhase::Uploading, Some(total)) => {
+                    let rate = if rate > 0.0 { rate } else { average_rate };
+                    (rate > 0.0)
+                        .then(|| Duration::from_secs_f64(total.saturating_sub(done) as f64 / rate))
+                }
+                _ => None,
+            };
+
+            TransferProgress {
+                phase,
+                done,
+                total: self.total,
+                rate,
+                average_rate,
+                eta,
+                elapsed,
+            }
+        };
+
+        self.handler.progress(&progress);
+    }
+}
@@ -0,0 +1,161 @@
+//! Bandwidth throttling for media transfers
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use web_time::Instant;
+
+/// A limit on the bytes per second a transfer may use. Clones share the same limit,
+/// so changing it through any clone affects all transfers using it, even running ones
+#[derive(Debug, Default, Clone)]
+pub struct BandwidthLimit {
+    /// The limit in bytes per second, `0` means unlimited
+    rate: Arc<AtomicU64>,
+}
+
+impl BandwidthLimit {
+    /// Creates a new limit
+    /// # Arguments
+    /// * `rate` - The limit in bytes per second, `None` for no limit
+    pub fn new(rate: Option<u64>) -> Self {
+        let limit = Self::default();
+        limit.set(rate);
+        limit
+    }
+
+    /// Changes the limit
+    /// # Arguments
+    /// * `rate` - The limit in bytes per second, `None` for no limit
+    pub fn set(&self, rate: Option<u64>) {
+        self.rate.store(rate.unwrap_or_default(), Ordering::SeqCst);
+    }
+
+    /// Returns the limit in bytes per second, `None` if there is no limit
+    pub fn get(&self) -> Option<u64> {
+        match self.rate.load(Ordering::SeqCst) {
+            0 => None,
+            rate => Some(rate),
+        }
+    }
+}
+
+/// A token bucket that holds back data once it exceeds the bandwidth limit.
+/// It allows bursts of up to one second worth of data
+struct TokenBucket {
+    limit: BandwidthLimit,
+    /// The amount of bytes that may be sent right away, negative if in debt
+    tokens: f64,
+    /// The last time the bucket got refilled
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a new, empty bucket
+    /// # Arguments
+    /// * `limit` - The limit to enforce
+    fn new(limit: BandwidthLimit) -> Self {
+        Self {
+            limit,
+            tokens: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    /// Takes tokens for `amount` bytes out of the bucket, waiting until the debt is paid off
+    /// # Arguments
+    /// * `amount` - The amount of bytes to send
+    async fn acquire(&mut self, amount: u64) {
+        let now = Instant::now();
+        let elapsed = (now - self.last).as_secs_f64();
+        self.last = now;
+
+        // The limit is read every time, so it can change in the middle of a transfer
+        let rate = match self.limit.get() {
+            Some(rate) => rate as f64,
+            None => {
+                self.tokens = 0.0;
+                return;
+            }
+        };
+
+        self.tokens = (self.tokens + elapsed * rate).min(rate) - amount as f64;
+
+        if self.tokens < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-self.tokens / rate)).await;
+        }
+    }
+}
+
+/// Throttles a stream of data to a bandwidth limit
+/// # Arguments
+/// * `stream` - The stream to throttle
+/// * `limit` - The limit to enforce
+pub(crate) fn throttle<S>(
+    stream: S,
+    limit: BandwidthLimit,
+) -> impl Stream<Item = Result<Bytes, io::Error>> + Send
+where
+    S: Stream<Item = Result<Bytes, io::Error>> + Send,
+{
+    async_stream::stream! {
+        let mut bucket = TokenBucket::new(limit);
+
+        futures_util::pin_mut!(stream);
+        while let Some(chunk) = stream.next().await {
+            if let Ok(chunk) = &chunk {
+                bucket.acquire(chunk.len() as u64).await;
+            }
+            yield chunk;
+        }
+    }
+}
+
+/// Splits data into small pieces so throttling it does not cause bursts
+/// # Arguments
+/// * `data` - The data to split
+#[cfg(feature = "experimental-chunked")]
+pub(crate) fn pieces(data: Bytes) -> impl Stream<Item = Result<Bytes, io::Error>> + Send {
+    const PIECE_SIZE: usize = 64 * 1024;
+
+    let count = data.len().div_ceil(PIECE_SIZE);
+    futures_util::stream::iter(
+        (0..count)
+            .map(move |i| Ok(data.slice(i * PIECE_SIZE..((i + 1) * PIECE_SIZE).min(data.len())))),
+    )
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_shared() {
+        let limit = BandwidthLimit::new(Some(1024));
+        let clone = limit.clone();
+        assert_eq!(clone.get(), Some(1024));
+
+        clone.set(None);
+        assert_eq!(limit.get(), None);
+    }
+
+    #[cfg(feature = "experimental-chunked")]
+    #[tokio::test]
+    async fn test_pieces() {
+        let data = Bytes::from(vec![1u8; 150 * 1024]);
+        let pieces: Vec<Bytes> = pieces(data.clone()).map(|p| p.unwrap()).collect().await;
+
+        assert_eq!(
+            pieces.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            vec![64 * 1024, 64 * 1024, 22 * 1024]
+        );
+        assert_eq!(pieces.concat(), data);
+    }
+}
@@ -0,0 +1,120 @@
+//! The transport layer all requests to the hypervisor go through
+//!
+//! A `Transport` takes a fully built `TransportRequest` and delivers a fully received
+//! `TransportResponse`, so the rest of this crate does not depend on a specific HTTP client.
+//! `ReqwestTransport` is the default, `MemoryTransport` answers requests in-process.
+
+use std::{fmt::Debug, future::Future, io, pin::Pin};
+
+use bytes::Bytes;
+use futures_util::Stream;
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Method, StatusCode,
+};
+
+use crate::error::{ClientError, VelocityError};
+
+mod http;
+mod memory;
+
+pub use http::*;
+pub use memory::*;
+
+/// The future a `Transport` returns for every request
+#[cfg(not(target_arch = "wasm32"))]
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TransportResponse, VelocityError>> + Send + 'a>>;
+
+/// The future a `Transport` returns for every request. Browsers are single threaded
+/// and their requests can't be sent between threads
+#[cfg(target_arch = "wasm32")]
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TransportResponse, VelocityError>> + 'a>>;
+
+/// A stream of body chunks for requests that stream their body, e.g. media uploads
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, io::Error>> + Send>>;
+
+/// A backend that is capable of delivering requests to the hypervisor
+pub trait Transport: Debug + Send + Sync {
+    /// Delivers a request and receives the full response
+    /// # Arguments
+    /// * `request` - The request to deliver
+    fn execute(&self, request: TransportRequest) -> TransportFuture<'_>;
+}
+
+/// A request that is ready to be delivered by a `Transport`
+#[derive(Debug)]
+pub struct TransportRequest {
+    /// The method to use for the request
+    pub method: Method,
+    /// The full URL to route the request to
+    pub url: String,
+    /// The headers to send along
+    pub headers: HeaderMap,
+    /// The body of the request
+    pub body: TransportBody,
+}
+
+/// The body of a `TransportRequest`
+pub enum TransportBody {
+    /// The request has no body
+    Empty,
+    /// The request body is fully known in advance
+    Bytes(Bytes),
+    /// The request body gets streamed in chunks
+    Stream(BodyStream),
+}
+
+impl Debug for TransportBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Empty"),
+            Self::Bytes(b) => write!(f, "Bytes({} bytes)", b.len()),
+            Self::Stream(_) => write!(f, "Stream"),
+        }
+    }
+}
+
+/// A fully received response to a `TransportRequest`
+#[derive(Debug, Clone)]
+pub struct TransportResponse {
+    /// The status the request exited with
+    pub status: StatusCode,
+    /// The headers of the response
+    pub headers: HeaderMap,
+    /// The raw response body
+    pub body: Bytes,
+}
+
+impl TransportRequest {
+    /// Creates a new request without headers and body
+    /// # Arguments
+    /// * `method` - The method to use for the request
+    /// * `url` - The full URL to route the request to
+    pub fn new(method: Method, url: String) -> Self {
+        Self {
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: TransportBody::Empty,
+        }
+    }
+
+    /// Sets a header to a string, failing if the string contains characters
+    /// HTTP headers can't carry, e.g. line breaks
+    /// # Arguments
+    /// * `name` - The name of the header
+    /// * `value` - The value of the header
+    pub fn header(&mut self, name: &'static str, value: &str) -> Result<(), VelocityError> {
+        let value = HeaderValue::from_str(value).map_err(|_| {
+            VelocityError::Client(ClientError::InvalidValue(format!(
+                "'{}' can't be sent as the {} header",
+                value.escape_debug(),
+                name
+            )))
+        })?;
+        self.headers.insert(name, value);
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(target_arch = "wasm32")]
+use bytes::Bytes;
+#[cfg(target_arch = "wasm32")]
+use futures_util::TryStreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Body;
+
+use super::{Transport, TransportBody, TransportFuture, TransportRequest, TransportResponse};
+
+/// The default `Transport`, delivering requests over HTTP using `reqwest`
+#[derive(Debug, Default, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Creates a new transport using an existing `reqwest` client
+    /// # Arguments
+    /// * `client` - The client to deliver requests with
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn execute(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let builder = self
+                .client
+                .request(request.method, request.url)
+                .headers(request.headers);
+
+            let builder = match request.body {
+                TransportBody::Empty => builder,
+                TransportBody::Bytes(bytes) => builder.body(bytes),
+                #[cfg(not(target_arch = "wasm32"))]
+                TransportBody::Stream(stream) => builder.body(Body::wrap_stream(stream)),
+                // Browsers can't stream request bodies through fetch, so the body is collected first
+                #[cfg(target_arch = "wasm32")]
+                TransportBody::Stream(stream) => {
+                    let chunks: Vec<Bytes> = stream.try_collect().await?;
+                    builder.body(chunks.concat())
+                }
+            };
+
+            let response = builder.send().await?;
+
+            Ok(TransportResponse {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: response.bytes().await?,
+            })
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use std::{fmt::Debug, sync::Mutex};
+
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    Method, StatusCode,
+};
+use serde::Serialize;
+
+use crate::{error::VelocityError, velocity::reqwest::serialize};
+
+use super::{Transport, TransportBody, TransportFuture, TransportRequest, TransportResponse};
+
+/// A request that has been received by a `MemoryTransport`, with its body fully collected
+#[derive(Debug, Clone)]
+pub struct MemoryRequest {
+    /// The method used for the request
+    pub method: Method,
+    /// The full URL the request was routed to
+    pub url: String,
+    /// The headers sent along
+    pub headers: HeaderMap,
+    /// The full request body
+    pub body: Bytes,
+}
+
+impl MemoryRequest {
+    /// Returns the path of the request URL, without the scheme and host
+    pub fn path(&self) -> &str {
+        let url = match self.url.split_once("://") {
+            Some((_, rest)) => rest,
+            None => &self.url,
+        };
+
+        match url.find('/') {
+            Some(idx) => &url[idx..],
+            None => "/",
+        }
+    }
+
+    /// Deserializes the body as JSON
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// The handler a `MemoryTransport` answers requests with
+type Handler = dyn Fn(&MemoryRequest) -> TransportResponse + Send + Sync;
+
+/// A `Transport` that answers all requests in-process using a handler function,
+/// recording every request it receives. Useful for tests
+pub struct MemoryTransport {
+    handler: Box<Handler>,
+    requests: Mutex<Vec<MemoryRequest>>,
+}
+
+impl MemoryTransport {
+    /// Creates a new in-memory transport
+    /// # Arguments
+    /// * `handler` - The function that answers each request
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&MemoryRequest) -> TransportResponse + Send + Sync + 'static,
+    {
+        Self {
+            handler: Box::new(handler),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns all requests this transport has received until now
+    pub fn requests(&self) -> Vec<MemoryRequest> {
+        self.requests.lock().expect("Lock requests").clone()
+    }
+}
+
+impl Debug for MemoryTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryTransport")
+            .field("requests", &self.requests)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn execute(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let body = match request.body {
+                TransportBody::Empty => Bytes::new(),
+                TransportBody::Bytes(bytes) => bytes,
+                TransportBody::Stream(mut stream) => {
+                    let mut body = BytesMut::new();
+                    while let Some(chunk) = stream.next().await {
+                        body.extend_from_slice(&chunk?);
+                    }
+                    body.freeze()
+                }
+            };
+
+            let request = MemoryRequest {
+                method: request.method,
+                url: request.url,
+                headers: request.headers,
+                body,
+            };
+
+            let response = (self.handler)(&request);
+            self.requests.lock().expect("Lock requests").push(request);
+
+            Ok(response)
+        })
+    }
+}
+
+impl TransportResponse {
+    /// Creates a response with a JSON body
+    /// # Arguments
+    /// * `status` - The status to respond with
+    /// * `body` - The structure to serialize into the body
+    pub fn json<T: Serialize>(status: StatusCode, body: &T) -> Result<Self, VelocityError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        Ok(Self {
+            status,
+            headers,
+            body: serialize(body)?.into(),
+        })
+    }
+
+    /// Creates a response with a plain text body
+    /// # Arguments
+    /// * `status` - The status to respond with
+    /// * `body` - The text to put into the body
+    pub fn text(status: StatusCode, body: &str) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+
+        Self {
+            status,
+            headers,
+            body: Bytes::copy_from_slice(body.as_bytes()),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::{error::Error, sync::Arc};
+
+    use super::*;
+    use crate::Velocity;
+
+    #[tokio::test]
+    async fn test_records_and_answers() {
+        let transport = Arc::new(MemoryTransport::new(|_| {
+            TransportResponse::json(
+                StatusCode::OK,
+                &serde_json::json!({"authkey": "key", "expires": 1}),
+            )
+            .unwrap()
+        }));
+        let v = Velocity::with_transport("http://velocity", transport.clone());
+
+        let key = v.authenticate("root", "secret").await.unwrap();
+        assert_eq!(key.key(), "key");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::POST);
+        assert_eq!(requests[0].path(), "/u/auth");
+
+        let body: serde_json::Value = requests[0].json().unwrap();
+        assert_eq!(body["username"], "root");
+    }
+
+    #[tokio::test]
+    async fn test_non_json_error() {
+        let transport = Arc::new(MemoryTransport::new(|_| {
+            TransportResponse::text(StatusCode::BAD_GATEWAY, "")
+        }));
+        let v = Velocity::with_transport("http://velocity", transport);
+
+        let err = v.authenticate("root", "secret").await.unwrap_err();
+        match &err {
+            VelocityError::UnexpectedResponse(e) => {
+                assert_eq!(e.status, 502);
+                assert_eq!(e.content_type.as_deref(), Some("text/plain"));
+            }
+            e => panic!("Unexpected error: {e:?}"),
+        }
+        assert!(err.source().is_some());
+    }
+}
@@ -0,0 +1,142 @@
+//! Tests for the blocking client
+#![cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use std::{
+    io::{self, Cursor, Read},
+    sync::{Arc, Mutex},
+};
+
+use common::*;
+use tokio::runtime::Runtime;
+use velocity::{
+    blocking::Velocity,
+    error::{VelocityError, VelocityErrorKind},
+    mock::MockHypervisor,
+    transfer::{self, TransferOptions, TransferPhase, TransferProgress},
+    GID, MPID,
+};
+
+/// Starts a mock hypervisor on a runtime of its own, as the blocking client
+/// must not be used from within a runtime
+fn blocking_hypervisor() -> (MockHypervisor, Runtime) {
+    let runtime = Runtime::new().expect("Create runtime");
+    let mock = runtime.block_on(hypervisor());
+
+    (mock, runtime)
+}
+
+/// Logs in to the hypervisor as `username`, using the username as the password
+fn blocking_login(mock: &MockHypervisor, username: &str) -> Velocity {
+    Velocity::new(&mock.url(), username, username).expect("Log in")
+}
+
+#[test]
+fn test_blocking_requests() {
+    let (mock, _runtime) = blocking_hypervisor();
+    let v = blocking_login(&mock, "alice");
+
+    assert_eq!(v.user_info(None).unwrap().name, "alice");
+    assert_eq!(v.media_list(GID(1)).unwrap().len(), 1);
+    assert_eq!(v.pool_list(GID(1)).unwrap().len(), 2);
+    assert_api_error(
+        v.group_info(GID(2)),
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+
+    // Clones share the authentication state
+    let clone = v.clone();
+    v.deauthenticate().unwrap();
+    assert_not_authenticated(clone.media_list(GID(1)));
+    assert!(Velocity::new(&mock.url(), "alice", "wrong").is_err());
+}
+
+#[test]
+fn test_blocking_media_upload() {
+    let (mock, _runtime) = blocking_hypervisor();
+    let v = blocking_login(&mock, "alice");
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let u = updates.clone();
+    let res = v
+        .media_upload(
+            MPID(0),
+            GID(1),
+            "blocking",
+            "DISK",
+            false,
+            Cursor::new(vec![0x42u8; 512]),
+            Some(512),
+            TransferOptions::default(),
+            move |progress: &TransferProgress| u.lock().unwrap().push(progress.clone()),
+        )
+        .unwrap();
+    assert_eq!(res.size, 512);
+
+    let last = updates.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(last.phase, TransferPhase::Processing);
+    assert_eq!(last.done, 512);
+
+    let sha256 = Runtime::new()
+        .unwrap()
+        .block_on(transfer::sha256(&[0x42u8; 512][..]))
+        .unwrap();
+    assert_eq!(res.sha256.unwrap(), sha256);
+
+    // Data of unknown length is streamed as well
+    let res = v
+        .media_upload(
+            MPID(0),
+            GID(1),
+            "piped",
+            "DISK",
+            false,
+            Cursor::new(vec![0x42u8; 300]),
+            None,
+            TransferOptions::default(),
+            (),
+        )
+        .unwrap();
+    assert_eq!(res.size, 300);
+}
+
+#[test]
+fn test_blocking_media_upload_read_error() {
+    /// A reader that fails after providing some data
+    struct Failing(usize);
+
+    impl Read for Failing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0 {
+                0 => Err(io::Error::other("Broken disk")),
+                _ => {
+                    let n = buf.len().min(self.0);
+                    buf[..n].fill(0x42);
+                    self.0 -= n;
+                    Ok(n)
+                }
+            }
+        }
+    }
+
+    let (mock, _runtime) = blocking_hypervisor();
+    let v = blocking_login(&mock, "alice");
+
+    let res = v.media_upload(
+        MPID(0),
+        GID(1),
+        "failing",
+        "DISK",
+        false,
+        Failing(100),
+        None,
+        TransferOptions::default(),
+        (),
+    );
+    assert!(
+        matches!(res, Err(VelocityError::Reqwest(_) | VelocityError::IO(_))),
+        "Expected the read error, got {:?}",
+        res
+    );
+}
@@ -0,0 +1,78 @@
+//! Shared setup for the endpoint tests
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use velocity::{
+    error::{ClientError, VelocityError, VelocityErrorKind},
+    mock::{Fixture, MockHypervisor},
+    transport::ReqwestTransport,
+    Velocity,
+};
+
+/// The media that is part of the `basic` fixture
+pub const DEBIAN_MID: &str = "00000000-0000-4000-8000-000000000001";
+
+/// Starts a mock hypervisor seeded with the `basic` fixture
+pub async fn hypervisor() -> MockHypervisor {
+    let fixture = Fixture::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/basic.json"
+    ))
+    .expect("Load fixture");
+
+    MockHypervisor::start(fixture)
+        .await
+        .expect("Start mock hypervisor")
+}
+
+/// Logs in to the hypervisor as `username`, using the username as the password
+pub async fn login(mock: &MockHypervisor, username: &str) -> Velocity {
+    Velocity::new(&mock.url(), username, username)
+        .await
+        .expect("Log in")
+}
+
+/// Creates a client that has never authenticated
+pub fn unauthenticated(mock: &MockHypervisor) -> Velocity {
+    Velocity::with_transport(&mock.url(), Arc::new(ReqwestTransport::default()))
+}
+
+/// Asserts that the result is an API error of the expected kind, code and HTTP status
+#[track_caller]
+pub fn assert_api_error<T: std::fmt::Debug>(
+    res: Result<T, VelocityError>,
+    kind: VelocityErrorKind,
+    status: u16,
+) {
+    match res {
+        Err(VelocityError::APIError(e)) => {
+            assert_eq!(e.status, status, "Unexpected status: {:?}", e);
+            assert_eq!(e.kind(), kind, "Unexpected kind: {:?}", e);
+            assert_eq!(e.code, status as u32, "Unexpected code: {:?}", e);
+        }
+        res => panic!("Expected API error with status {}, got {:?}", status, res),
+    }
+}
+
+/// Asserts that the result is a `400` error with a reason
+#[track_caller]
+pub fn assert_bad_request<T: std::fmt::Debug>(res: Result<T, VelocityError>) {
+    match res {
+        Err(VelocityError::APIError(e)) => {
+            assert_eq!(e.status, 400, "Unexpected status: {:?}", e);
+            assert_eq!(e.code, 400);
+            assert!(!e.message.is_empty());
+        }
+        res => panic!("Expected a 400 error, got {:?}", res),
+    }
+}
+
+/// Asserts that the call failed client-side because there is no authkey
+#[track_caller]
+pub fn assert_not_authenticated<T: std::fmt::Debug>(res: Result<T, VelocityError>) {
+    match res {
+        Err(VelocityError::Client(ClientError::NotAuthenticated)) => {}
+        res => panic!("Expected NotAuthenticated, got {:?}", res),
+    }
+}
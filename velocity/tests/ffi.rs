@@ -0,0 +1,112 @@
+//! Tests for the C API
+#![cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use std::{env, fs, path::PathBuf, process::Command};
+
+use common::*;
+use tokio::runtime::Runtime;
+
+/// The directory of the crate
+const MANIFEST_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+/// Generates the header from the sources
+fn generate_header() -> String {
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", MANIFEST_DIR))
+        .expect("Load cbindgen.toml");
+
+    let mut header = Vec::new();
+    cbindgen::Builder::new()
+        .with_crate(MANIFEST_DIR)
+        .with_config(config)
+        .generate()
+        .expect("Generate header")
+        .write(&mut header);
+
+    String::from_utf8(header).expect("Header is UTF-8")
+}
+
+/// Builds the shared library with the `ffi` feature, as the test harness only builds the
+/// `rlib`. A target directory of its own keeps it from clashing with the running build
+fn build_library() -> PathBuf {
+    let exe = env::current_exe().expect("Find test binary");
+    let target_dir = exe
+        .ancestors()
+        .nth(3)
+        .expect("Find target directory")
+        .join("ffi");
+
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .args(["build", "--quiet", "--lib", "--features", "ffi"])
+        .arg("--manifest-path")
+        .arg(format!("{}/Cargo.toml", MANIFEST_DIR))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("Run cargo");
+    assert!(status.success(), "Building the shared library failed");
+
+    target_dir.join("debug")
+}
+
+#[test]
+fn test_header_up_to_date() {
+    let path = format!("{}/include/velocity.h", MANIFEST_DIR);
+    let header = generate_header();
+
+    if env::var_os("VELOCITY_BLESS").is_some() {
+        fs::write(&path, header).expect("Write header");
+        return;
+    }
+
+    let committed = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        committed == header,
+        "include/velocity.h is outdated, run `VELOCITY_BLESS=1 cargo test --test ffi` to update it"
+    );
+}
+
+#[test]
+fn test_c_smoke() {
+    let runtime = Runtime::new().expect("Create runtime");
+    let mock = runtime.block_on(hypervisor());
+
+    let lib_dir = build_library();
+
+    let tmp = env::temp_dir().join(format!("velocity-ffi-{}", std::process::id()));
+    fs::create_dir_all(&tmp).expect("Create temporary directory");
+    let exe = tmp.join("smoke");
+    let disk = tmp.join("disk.img");
+    fs::write(&disk, vec![0x42u8; 4096]).expect("Write disk image");
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_owned()))
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg(format!("-I{}/include", MANIFEST_DIR))
+        .arg(format!("{}/tests/ffi/smoke.c", MANIFEST_DIR))
+        .arg("-o")
+        .arg(&exe)
+        .arg(format!("-L{}", lib_dir.display()))
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lvelocity")
+        .status()
+        .expect("Run the C compiler");
+    assert!(status.success(), "Compiling smoke.c failed");
+
+    let output = Command::new(&exe)
+        .arg(mock.url())
+        .arg(&disk)
+        // Cargo points this at `target/debug`, which takes precedence over the rpath
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .expect("Run smoke test");
+    fs::remove_dir_all(&tmp).ok();
+
+    assert!(
+        output.status.success(),
+        "Smoke test failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,110 @@
+/* Exercises the C API against a mock hypervisor: smoke <base url> <file to upload> */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "velocity.h"
+
+#define CHECK(call, expected)                                                      \
+    do {                                                                           \
+        VelocityStatus status = (call);                                            \
+        if (status != (expected)) {                                                \
+            const char *error = velocity_last_error();                             \
+            fprintf(stderr, "%s:%d: %s returned %d, expected %d: %s\n", __FILE__,  \
+                    __LINE__, #call, (int)status, (int)(expected),                 \
+                    error ? error : "(no error)");                                 \
+            exit(1);                                                               \
+        }                                                                          \
+    } while (0)
+
+struct Progress {
+    uint64_t calls;
+    uint64_t done;
+    uint64_t total;
+};
+
+static void on_progress(uint64_t done, uint64_t total, void *user_data) {
+    struct Progress *progress = user_data;
+    progress->calls++;
+    progress->done = done;
+    progress->total = total;
+}
+
+static void print_list(const char *what, char *json) {
+    printf("%s: %s\n", what, json);
+    velocity_string_free(json);
+}
+
+int main(int argc, char **argv) {
+    if (argc != 3) {
+        fprintf(stderr, "Usage: %s <base url> <file to upload>\n", argv[0]);
+        return 2;
+    }
+
+    VelocityClient *client = NULL;
+    char *json = NULL;
+
+    CHECK(velocity_login(argv[1], "root", "wrong", &client), VELOCITY_STATUS_PERMISSION_DENIED);
+    if (client != NULL || velocity_last_error() == NULL) {
+        fprintf(stderr, "A failed login must not create a client\n");
+        return 1;
+    }
+    CHECK(velocity_login(NULL, "root", "root", &client), VELOCITY_STATUS_INVALID_ARGUMENT);
+    CHECK(velocity_login(argv[1], "root", "root", NULL), VELOCITY_STATUS_INVALID_ARGUMENT);
+
+    CHECK(velocity_login(argv[1], "root", "root", &client), VELOCITY_STATUS_OK);
+    if (velocity_last_error() != NULL) {
+        fprintf(stderr, "A successful call must clear the last error\n");
+        return 1;
+    }
+
+    CHECK(velocity_user_list(client, NULL), VELOCITY_STATUS_INVALID_ARGUMENT);
+    CHECK(velocity_user_list(client, &json), VELOCITY_STATUS_OK);
+    print_list("users", json);
+    CHECK(velocity_group_list(client, &json), VELOCITY_STATUS_OK);
+    print_list("groups", json);
+    CHECK(velocity_pool_list(client, 0, &json), VELOCITY_STATUS_OK);
+    print_list("pools", json);
+    CHECK(velocity_nic_list(client, &json), VELOCITY_STATUS_OK);
+    print_list("nics", json);
+    CHECK(velocity_media_list(client, 42, &json), VELOCITY_STATUS_NOT_FOUND);
+
+    struct Progress progress = {0};
+    char *mid = NULL;
+    CHECK(velocity_media_upload(client, 0, 0, "smoke", "DISK", false, argv[2], on_progress,
+                                &progress, &mid),
+          VELOCITY_STATUS_OK);
+    if (progress.calls == 0 || progress.done != progress.total) {
+        fprintf(stderr, "Unexpected progress: %llu of %llu in %llu calls\n",
+                (unsigned long long)progress.done, (unsigned long long)progress.total,
+                (unsigned long long)progress.calls);
+        return 1;
+    }
+    printf("uploaded %s, %llu bytes\n", mid, (unsigned long long)progress.total);
+    CHECK(velocity_media_upload(client, 0, 0, "missing", "DISK", false, "/nonexistent/disk.img",
+                                NULL, NULL, &mid),
+          VELOCITY_STATUS_TRANSPORT);
+    velocity_string_free(mid);
+    CHECK(velocity_media_upload(client, 0, 0, "no-out", "DISK", false, argv[2], NULL, NULL, NULL),
+          VELOCITY_STATUS_INVALID_ARGUMENT);
+
+    const char *config =
+        "{\"name\": \"smoke\", \"gid\": 1, \"cpus\": 2, \"memory_mib\": 2048,"
+        " \"displays\": [{\"name\": \"main\", \"width\": 1920, \"height\": 1080, \"ppi\": 72}],"
+        " \"disks\": [{\"mid\": \"00000000-0000-4000-8000-000000000001\", \"mode\": \"USB\","
+        " \"readonly\": true}],"
+        " \"nics\": [{\"type\": \"NAT\", \"host\": null}],"
+        " \"autostart\": false, \"rosetta\": false}";
+    VMID vmid = -1;
+    CHECK(velocity_vm_efi_create(client, config, &vmid), VELOCITY_STATUS_OK);
+    printf("created vm %lld\n", (long long)vmid);
+    CHECK(velocity_vm_efi_create(client, config, &vmid), VELOCITY_STATUS_CONFLICT);
+    CHECK(velocity_vm_efi_create(client, "{}", &vmid), VELOCITY_STATUS_INVALID_ARGUMENT);
+    CHECK(velocity_vm_efi_create(client, config, NULL), VELOCITY_STATUS_INVALID_ARGUMENT);
+
+    CHECK(velocity_logout(client), VELOCITY_STATUS_OK);
+    CHECK(velocity_user_list(client, &json), VELOCITY_STATUS_NOT_AUTHENTICATED);
+    velocity_client_free(client);
+
+    return 0;
+}
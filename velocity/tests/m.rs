@@ -0,0 +1,699 @@
+//! Tests for the `/m` endpoints
+#![cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use common::*;
+use velocity::{
+    error::{ClientError, VelocityError, VelocityErrorKind},
+    transfer::{
+        self, BandwidthLimit, CancellationToken, TransferOptions, TransferPhase, TransferProgress,
+        UploadJournal,
+    },
+    GID, MPID,
+};
+
+/// Writes a temporary file with `size` bytes of content to upload
+async fn temp_file(name: &str, size: usize) -> tokio::fs::File {
+    let path = std::env::temp_dir().join(format!("velocity-test-{}-{}", std::process::id(), name));
+    tokio::fs::write(&path, vec![0x42u8; size])
+        .await
+        .expect("Write temporary file");
+
+    let file = tokio::fs::File::open(&path)
+        .await
+        .expect("Open temporary file");
+    tokio::fs::remove_file(&path).await.ok();
+
+    file
+}
+
+#[tokio::test]
+async fn test_unauthenticated() {
+    let mock = hypervisor().await;
+    let v = unauthenticated(&mock);
+
+    assert_not_authenticated(v.pool_list(GID(1)).await);
+    assert_not_authenticated(v.pool_assign(GID(1), MPID(0), 0, true, true).await);
+    assert_not_authenticated(v.pool_revoke(GID(1), MPID(0)).await);
+    assert_not_authenticated(v.media_list(GID(1)).await);
+    assert_not_authenticated(v.media_allocate(MPID(0), GID(1), "disk", "DISK", 10).await);
+    assert_not_authenticated(
+        v.media_upload(
+            MPID(0),
+            GID(1),
+            "disk",
+            "DISK",
+            false,
+            temp_file("unauth", 1).await,
+            Some(1),
+            TransferOptions::default(),
+            (),
+        )
+        .await,
+    );
+    assert_not_authenticated(v.media_remove(DEBIAN_MID.to_owned()).await);
+}
+
+#[tokio::test]
+async fn test_pool_list() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let pools = v.pool_list(GID(1)).await.unwrap();
+    assert_eq!(pools.len(), 2);
+    assert_eq!(pools[0].name, "default");
+    assert!(pools[0].manage);
+    assert!(!pools[1].manage);
+
+    assert_api_error(
+        v.pool_list(GID(0)).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+
+    // The root group sees all pools
+    let root = login(&mock, "root").await;
+    assert_eq!(root.pool_list(GID(0)).await.unwrap().len(), 2);
+    assert!(root.pool_list(GID(2)).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_pool_assign_revoke() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    v.pool_assign(GID(2), MPID(1), 500, true, false)
+        .await
+        .unwrap();
+    let pools = v.pool_list(GID(2)).await.unwrap();
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].mpid, MPID(1));
+    assert!(pools[0].write);
+    assert!(!pools[0].manage);
+
+    assert_api_error(
+        v.pool_assign(GID(2), MPID(42), 500, true, false).await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+
+    v.pool_revoke(GID(2), MPID(1)).await.unwrap();
+    assert!(v.pool_list(GID(2)).await.unwrap().is_empty());
+
+    assert_api_error(
+        v.pool_revoke(GID(2), MPID(1)).await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+
+    let alice = login(&mock, "alice").await;
+    assert_api_error(
+        alice.pool_assign(GID(1), MPID(1), 500, true, true).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+    assert_api_error(
+        alice.pool_revoke(GID(1), MPID(0)).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
+
+#[tokio::test]
+async fn test_media_list() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let media = v.media_list(GID(1)).await.unwrap();
+    assert_eq!(media.len(), 1);
+    assert_eq!(media[0].mid, DEBIAN_MID);
+    assert_eq!(media[0].ty, "ISO");
+    assert!(media[0].readonly);
+
+    // Subgroups see the media of their parent groups
+    let media = v.media_list(GID(2)).await.unwrap();
+    assert_eq!(media.len(), 1);
+    assert_eq!(media[0].mid, DEBIAN_MID);
+    assert_api_error(
+        v.media_list(GID(0)).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+    assert_api_error(
+        v.media_list(GID(42)).await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+}
+
+#[tokio::test]
+async fn test_media_allocate() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let media = v
+        .media_allocate(MPID(0), GID(1), "disk", "DISK", 900)
+        .await
+        .unwrap();
+    assert_eq!(media.size, 900);
+    assert!(v
+        .media_list(GID(1))
+        .await
+        .unwrap()
+        .iter()
+        .any(|m| m.mid == media.mid));
+
+    assert_api_error(
+        v.media_allocate(MPID(0), GID(1), "disk", "DISK", 0).await,
+        VelocityErrorKind::Conflict,
+        409,
+    );
+    assert_api_error(
+        v.media_allocate(MPID(0), GID(1), "too-big", "DISK", 1)
+            .await,
+        VelocityErrorKind::QuotaExceeded,
+        507,
+    );
+    assert_api_error(
+        v.media_allocate(MPID(1), GID(1), "no-manage", "DISK", 1)
+            .await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+    assert_api_error(
+        v.media_allocate(MPID(42), GID(1), "no-pool", "DISK", 1)
+            .await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+    assert_bad_request(v.media_allocate(MPID(0), GID(1), "", "DISK", 1).await);
+}
+
+#[tokio::test]
+async fn test_media_upload() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let total = Arc::new(AtomicU64::new(0));
+    let uploaded = Arc::new(AtomicU64::new(0));
+
+    let (t, u) = (total.clone(), uploaded.clone());
+    let res = v
+        .media_upload(
+            MPID(0),
+            GID(1),
+            "upload",
+            "DISK",
+            true,
+            temp_file("upload", 300 * 1024).await,
+            Some(300 * 1024),
+            TransferOptions::default(),
+            move |progress: &TransferProgress| {
+                t.store(progress.total.unwrap_or_default(), Ordering::SeqCst);
+                u.store(progress.done, Ordering::SeqCst);
+            },
+        )
+        .await;
+
+    // The quota of 1000 bytes is exceeded
+    assert_api_error(res, VelocityErrorKind::QuotaExceeded, 507);
+    assert_eq!(total.load(Ordering::SeqCst), 300 * 1024);
+    assert_eq!(uploaded.load(Ordering::SeqCst), 300 * 1024);
+
+    let res = v
+        .media_upload(
+            MPID(0),
+            GID(1),
+            "upload",
+            "DISK",
+            true,
+            temp_file("upload-small", 512).await,
+            Some(512),
+            TransferOptions::default(),
+            (),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.size, 512);
+
+    let media = v.media_list(GID(1)).await.unwrap();
+    let uploaded = media.iter().find(|m| m.mid == res.mid).unwrap();
+    assert_eq!(uploaded.name, "upload");
+    assert!(uploaded.readonly);
+    assert_eq!(uploaded.sha256, res.sha256);
+    assert_eq!(
+        res.sha256.unwrap(),
+        transfer::sha256(&[0x42u8; 512][..]).await.unwrap()
+    );
+
+    assert_api_error(
+        v.media_upload(
+            MPID(0),
+            GID(2),
+            "upload",
+            "DISK",
+            true,
+            temp_file("upload-forbidden", 1).await,
+            Some(1),
+            TransferOptions::default(),
+            (),
+        )
+        .await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
+
+#[tokio::test]
+async fn test_media_upload_unknown_length() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let total = Arc::new(AtomicU64::new(u64::MAX));
+    let t = total.clone();
+    let res = v
+        .media_upload(
+            MPID(0),
+            GID(1),
+            "piped",
+            "DISK",
+            false,
+            &[0x42u8; 700][..],
+            None,
+            TransferOptions::default(),
+            move |progress: &TransferProgress| {
+                t.store(progress.total.unwrap_or_default(), Ordering::SeqCst)
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.size, 700);
+    assert_eq!(total.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_media_upload_progress() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let (tx, rx) = tokio::sync::watch::channel(TransferProgress::default());
+    v.media_upload(
+        MPID(0),
+        GID(0),
+        "watched",
+        "DISK",
+        false,
+        &[0x42u8; 256 * 1024][..],
+        Some(256 * 1024),
+        TransferOptions::default(),
+        tx,
+    )
+    .await
+    .unwrap();
+
+    let progress = rx.borrow().clone();
+    assert_eq!(progress.phase, TransferPhase::Processing);
+    assert_eq!(progress.done, 256 * 1024);
+    assert_eq!(progress.total, Some(256 * 1024));
+    assert_eq!(progress.eta, None);
+    assert!(progress.elapsed > Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_media_upload_throttled() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    // 64 KiB at 128 KiB/s take half a second
+    let start = Instant::now();
+    v.media_upload(
+        MPID(0),
+        GID(0),
+        "throttled",
+        "DISK",
+        false,
+        &[0x42u8; 64 * 1024][..],
+        Some(64 * 1024),
+        TransferOptions::new().limit(BandwidthLimit::new(Some(128 * 1024))),
+        (),
+    )
+    .await
+    .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(400));
+
+    // At 16 KiB/s this would take 16 seconds, unless the default limit is lifted on the way
+    v.transfer_limit().set(Some(16 * 1024));
+    let limit = v.transfer_limit().clone();
+    let start = Instant::now();
+    v.media_upload(
+        MPID(0),
+        GID(0),
+        "unthrottled",
+        "DISK",
+        false,
+        &[0x42u8; 256 * 1024][..],
+        Some(256 * 1024),
+        TransferOptions::default(),
+        move |progress: &TransferProgress| {
+            if progress.done >= 16 * 1024 {
+                limit.set(None);
+            }
+        },
+    )
+    .await
+    .unwrap();
+    assert!(start.elapsed() < Duration::from_secs(4));
+}
+
+#[tokio::test]
+async fn test_media_upload_checksum() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let upload = |name: &'static str, options: TransferOptions| {
+        v.media_upload(
+            MPID(0),
+            GID(0),
+            name,
+            "DISK",
+            false,
+            &[0x42u8; 64][..],
+            Some(64),
+            options,
+            (),
+        )
+    };
+
+    // The hypervisor rejects data that does not match the expected checksum
+    assert_bad_request(
+        upload(
+            "wrong",
+            TransferOptions::new().expected_sha256(&"0".repeat(64)),
+        )
+        .await,
+    );
+
+    let sha256 = transfer::sha256(&[0x42u8; 64][..]).await.unwrap();
+    let res = upload("right", TransferOptions::new().expected_sha256(&sha256))
+        .await
+        .unwrap();
+    assert_eq!(res.sha256, Some(sha256.clone()));
+
+    // Data corrupted in transit gets noticed by the client
+    mock.state().corrupt_uploads = true;
+    match upload("corrupted", TransferOptions::default()).await {
+        Err(e @ VelocityError::Client(ClientError::ChecksumMismatch { .. })) => {
+            assert_eq!(e.kind(), VelocityErrorKind::ChecksumMismatch)
+        }
+        res => panic!("Expected a checksum mismatch, got {:?}", res),
+    }
+}
+
+#[tokio::test]
+async fn test_media_upload_file() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let source = std::env::temp_dir().join(format!("velocity-test-{}-file", std::process::id()));
+    tokio::fs::write(&source, vec![0x42u8; 64 * 1024])
+        .await
+        .unwrap();
+    let sha256 = transfer::sha256(&[0x42u8; 64 * 1024][..]).await.unwrap();
+
+    let upload = |name: &'static str, progress: Arc<std::sync::Mutex<Vec<TransferPhase>>>| {
+        v.media_upload_file(
+            MPID(0),
+            GID(0),
+            name,
+            "DISK",
+            false,
+            &source,
+            TransferOptions::default(),
+            move |p: &TransferProgress| progress.lock().unwrap().push(p.phase),
+        )
+    };
+
+    // The file gets hashed before anything is sent
+    let phases = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let res = upload("file", phases.clone()).await.unwrap();
+    assert_eq!(res.sha256, Some(sha256));
+    {
+        let phases = phases.lock().unwrap();
+        assert_eq!(phases.first(), Some(&TransferPhase::Hashing));
+        assert_eq!(phases.last(), Some(&TransferPhase::Processing));
+    }
+
+    // The checksum travels along, so the hypervisor rejects data corrupted in transit
+    mock.state().corrupt_uploads = true;
+    assert_bad_request(upload("corrupted", Default::default()).await);
+    let media = v.media_list(GID(0)).await.unwrap();
+    assert!(media.iter().all(|m| m.name != "corrupted"));
+
+    tokio::fs::remove_file(&source).await.ok();
+}
+
+#[tokio::test]
+async fn test_media_upload_invalid_header() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let upload = |name: &'static str, options: TransferOptions| {
+        v.media_upload(
+            MPID(0),
+            GID(0),
+            name,
+            "DISK",
+            false,
+            &[0x42u8; 64][..],
+            Some(64),
+            options,
+            (),
+        )
+    };
+
+    // Values that can't be sent as headers are reported instead of panicking
+    match upload("line\nbreak", TransferOptions::default()).await {
+        Err(VelocityError::Client(ClientError::InvalidValue(_))) => {}
+        res => panic!("Expected an invalid value, got {:?}", res),
+    }
+    match upload(
+        "checksum",
+        TransferOptions::new().expected_sha256("not hex"),
+    )
+    .await
+    {
+        Err(VelocityError::Client(ClientError::InvalidValue(_))) => {}
+        res => panic!("Expected an invalid value, got {:?}", res),
+    }
+}
+
+#[tokio::test]
+async fn test_media_upload_cancel() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    // Cancel the upload as soon as the first chunk has been read
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let res = v
+        .media_upload(
+            MPID(0),
+            GID(1),
+            "cancelled",
+            "DISK",
+            false,
+            temp_file("upload-cancel", 4 * 1024 * 1024).await,
+            Some(4 * 1024 * 1024),
+            TransferOptions::new().cancel_token(token),
+            move |progress: &TransferProgress| {
+                if progress.done > 0 {
+                    cancel.cancel()
+                }
+            },
+        )
+        .await;
+
+    match res {
+        Err(VelocityError::Client(ClientError::Cancelled { transferred })) => {
+            assert!(transferred > 0 && transferred < 4 * 1024 * 1024)
+        }
+        res => panic!("Expected a cancelled upload, got {:?}", res),
+    }
+
+    // The media never made it to the hypervisor
+    let media = v.media_list(GID(1)).await.unwrap();
+    assert!(media.iter().all(|m| m.name != "cancelled"));
+}
+
+#[tokio::test]
+async fn test_media_upload_resume() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("velocity-test-{}-chunked", std::process::id()));
+    let journal = source.with_extension("journal");
+    tokio::fs::write(&source, vec![0x42u8; 10 * 1024])
+        .await
+        .unwrap();
+
+    // Interrupt the upload after the first acknowledged chunk
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let res = v
+        .media_upload_chunked(
+            MPID(0),
+            GID(0),
+            "chunked",
+            "DISK",
+            false,
+            &source,
+            &journal,
+            TransferOptions::new().chunk_size(4096).cancel_token(token),
+            move |progress: &TransferProgress| {
+                if progress.done > 0 {
+                    cancel.cancel()
+                }
+            },
+        )
+        .await;
+
+    match res {
+        Err(VelocityError::Client(ClientError::Cancelled { transferred })) => {
+            assert_eq!(transferred, 4096)
+        }
+        res => panic!("Expected a cancelled upload, got {:?}", res),
+    }
+
+    let record = UploadJournal::load(&journal).await.unwrap();
+    assert_eq!(record.offset, 4096);
+    assert_eq!(record.size, 10 * 1024);
+    assert_eq!(
+        v.media_upload_status(record.mid.clone())
+            .await
+            .unwrap()
+            .offset,
+        4096
+    );
+
+    // Resume where the hypervisor left off
+    let progress = Arc::new(AtomicU64::new(0));
+    let p = progress.clone();
+    let res = v
+        .media_upload_resume(
+            &journal,
+            TransferOptions::new().chunk_size(4096),
+            move |progress: &TransferProgress| {
+                p.store(progress.done, Ordering::SeqCst);
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.mid, record.mid);
+    assert_eq!(res.size, 10 * 1024);
+    assert_eq!(
+        res.sha256,
+        Some(transfer::sha256(&[0x42u8; 10 * 1024][..]).await.unwrap())
+    );
+    assert_eq!(progress.load(Ordering::SeqCst), 10 * 1024);
+    assert!(!journal.exists());
+    assert_api_error(
+        v.media_upload_status(res.mid.clone()).await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+
+    let media = v.media_list(GID(0)).await.unwrap();
+    assert!(media
+        .iter()
+        .any(|m| m.mid == res.mid && m.size == 10 * 1024 && m.sha256 == res.sha256));
+
+    tokio::fs::remove_file(&source).await.ok();
+}
+
+#[tokio::test]
+async fn test_media_upload_chunked_supported() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    assert!(v.media_upload_chunked_supported().await.unwrap());
+
+    // A hypervisor without the chunked upload routes does not know them at all
+    mock.state().chunked_uploads = false;
+    assert!(!v.media_upload_chunked_supported().await.unwrap());
+    match v.media_upload_status(DEBIAN_MID.to_owned()).await {
+        Err(VelocityError::UnexpectedResponse(e)) => assert_eq!(e.status, 404),
+        res => panic!("Expected an unknown route, got {:?}", res),
+    }
+
+    // Plain uploads work regardless
+    let res = v
+        .media_upload(
+            MPID(0),
+            GID(0),
+            "plain",
+            "DISK",
+            false,
+            &[0x42u8; 1024][..],
+            Some(1024),
+            TransferOptions::new(),
+            |_: &TransferProgress| {},
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.size, 1024);
+}
+
+#[tokio::test]
+async fn test_media_remove() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let alice = login(&mock, "alice").await;
+    assert_api_error(
+        alice.media_remove(DEBIAN_MID.to_owned()).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+
+    v.media_remove(DEBIAN_MID.to_owned()).await.unwrap();
+    assert!(v.media_list(GID(1)).await.unwrap().is_empty());
+
+    assert_api_error(
+        v.media_remove(DEBIAN_MID.to_owned()).await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+}
+
+#[tokio::test]
+async fn test_cors_preflight() {
+    let mock = hypervisor().await;
+
+    // Browsers ask before sending the custom headers of an upload
+    let res = reqwest::Client::new()
+        .request(reqwest::Method::OPTIONS, mock.url() + "/m/media/upload")
+        .header("Origin", "http://localhost:8000")
+        .header("Access-Control-Request-Method", "PUT")
+        .header("Access-Control-Request-Headers", "x-velocity-authkey")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), 204);
+    assert_eq!(res.headers()["access-control-allow-origin"], "*");
+    assert!(res.headers()["access-control-allow-methods"]
+        .to_str()
+        .unwrap()
+        .contains("PUT"));
+}
@@ -0,0 +1,124 @@
+//! Tests for resolving names to ids
+#![cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use common::*;
+use velocity::{
+    error::{ClientError, VelocityError, VelocityErrorKind},
+    GID, MPID, NICID, UID,
+};
+
+/// Asserts that a name matched several resources and returns their descriptions
+#[track_caller]
+fn assert_ambiguous<T: std::fmt::Debug>(res: Result<T, VelocityError>) -> Vec<String> {
+    match res {
+        Err(VelocityError::Client(ClientError::AmbiguousName { candidates, .. })) => candidates,
+        res => panic!("Expected an ambiguous name, got {:?}", res),
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_ids() {
+    let mock = hypervisor().await;
+    let v = unauthenticated(&mock);
+
+    // Ids are used as is, without asking the hypervisor
+    assert_eq!(v.resolve_user("1").await.unwrap(), UID(1));
+    assert_eq!(v.resolve_group("42").await.unwrap(), GID(42));
+    assert_eq!(v.resolve_pool("0").await.unwrap(), MPID(0));
+    assert_eq!(v.resolve_nic("0").await.unwrap(), NICID(0));
+    assert_eq!(v.resolve_media(DEBIAN_MID).await.unwrap(), DEBIAN_MID);
+    assert_not_authenticated(v.resolve_group("team").await);
+}
+
+#[tokio::test]
+async fn test_resolve_names() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    assert_eq!(v.resolve_user("alice").await.unwrap(), UID(1));
+    assert_eq!(v.resolve_group("team").await.unwrap(), GID(1));
+    assert_eq!(v.resolve_pool("fast").await.unwrap(), MPID(1));
+    assert_eq!(v.resolve_media("debian.iso").await.unwrap(), DEBIAN_MID);
+    assert_eq!(v.resolve_nic("en0").await.unwrap(), NICID(0));
+
+    let res = v.resolve_user("bob").await;
+    assert_eq!(res.unwrap_err().kind(), VelocityErrorKind::NotFound);
+    let res = v.resolve_nic("en1").await;
+    assert_eq!(res.unwrap_err().to_string(), "There is no NIC named 'en1'");
+}
+
+#[tokio::test]
+async fn test_resolve_group_paths() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let other = v.group_create(GID(0), "ci").await.unwrap().gid;
+
+    assert_eq!(v.resolve_group("root/team/ci").await.unwrap(), GID(2));
+    assert_eq!(v.resolve_group("/root/ci").await.unwrap(), other);
+    assert_eq!(v.resolve_group("root").await.unwrap(), GID(0));
+
+    let candidates = assert_ambiguous(v.resolve_group("ci").await);
+    assert_eq!(
+        candidates,
+        vec![
+            "root/team/ci (GID 2)".to_owned(),
+            format!("root/ci (GID {})", other)
+        ]
+    );
+
+    let res = v.resolve_group("other/ci").await;
+    assert_eq!(res.unwrap_err().kind(), VelocityErrorKind::NotFound);
+
+    // Paths have to match completely, not just their end
+    let res = v.resolve_group("team/ci").await;
+    assert_eq!(res.unwrap_err().kind(), VelocityErrorKind::NotFound);
+
+    // Paths start at the topmost group the user can see
+    let alice = login(&mock, "alice").await;
+    assert_eq!(alice.resolve_group("team/ci").await.unwrap(), GID(2));
+    assert_eq!(alice.resolve_group("ci").await.unwrap(), GID(2));
+}
+
+#[tokio::test]
+async fn test_resolve_ambiguous_media() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let mid = v
+        .media_allocate(MPID(0), GID(0), "debian.iso", "ISO", 100)
+        .await
+        .unwrap()
+        .mid;
+
+    let candidates = assert_ambiguous(v.resolve_media("debian.iso").await);
+    assert_eq!(candidates.len(), 2);
+    assert!(
+        candidates.iter().any(|c| c.contains(&mid)),
+        "{:?}",
+        candidates
+    );
+    assert!(
+        candidates.iter().any(|c| c.contains(DEBIAN_MID)),
+        "{:?}",
+        candidates
+    );
+
+    // Only the innermost groups get asked, they list the media of their ancestors as well
+    let team = v
+        .media_allocate(MPID(0), GID(1), "team.img", "DISK", 100)
+        .await
+        .unwrap()
+        .mid;
+    let alice = login(&mock, "alice").await;
+    assert_eq!(alice.resolve_media("team.img").await.unwrap(), team);
+    assert_eq!(alice.resolve_pool("fast").await.unwrap(), MPID(1));
+
+    // Media in groups the user can't list is not considered
+    mock.state()
+        .permissions
+        .retain(|(uid, gid, _)| !(*uid == UID(1) && *gid == GID(1)));
+    let res = alice.resolve_media("debian.iso").await;
+    assert_eq!(res.unwrap_err().kind(), VelocityErrorKind::NotFound);
+}
@@ -0,0 +1,56 @@
+//! Tests for sharing a `Velocity` instance across tasks
+#![cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use common::*;
+use velocity::GID;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_parallel_requests_during_reauth() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+    let old = v.get_authkey().unwrap();
+
+    let tasks: Vec<_> = (0..16)
+        .map(|_| {
+            let v = v.clone();
+            tokio::spawn(async move {
+                for _ in 0..20 {
+                    let media = v.media_list(GID(1)).await.expect("List media");
+                    assert_eq!(media.len(), 1);
+                }
+            })
+        })
+        .collect();
+
+    // Renew the key a few times while the tasks are hammering the hypervisor.
+    // Each renewal invalidates the previous key on the hypervisor
+    for _ in 0..5 {
+        v.clone().reauthenticate().await.expect("Reauthenticate");
+        tokio::task::yield_now().await;
+    }
+
+    for task in tasks {
+        task.await.expect("Task failed");
+    }
+
+    // All clones see the renewed key
+    let new = v.get_authkey().unwrap();
+    assert_ne!(old.key(), new.key());
+    assert!(mock.state().authenticate(old.key()).is_err());
+    assert!(mock.state().authenticate(new.key()).is_ok());
+    v.media_list(GID(1)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_clones_share_authentication() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+    let clone = v.clone();
+
+    v.deauthenticate().await.unwrap();
+    assert_not_authenticated(clone.media_list(GID(1)).await);
+
+    clone.authenticate("alice", "alice").await.unwrap();
+    v.media_list(GID(1)).await.unwrap();
+}
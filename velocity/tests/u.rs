@@ -0,0 +1,298 @@
+//! Tests for the `/u` endpoints
+#![cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use common::*;
+use velocity::{error::VelocityErrorKind, Velocity, GID, UID};
+
+#[tokio::test]
+async fn test_authenticate() {
+    let mock = hypervisor().await;
+
+    let v = unauthenticated(&mock);
+    assert_not_authenticated(v.get_authkey());
+
+    let key = v.authenticate("alice", "alice").await.unwrap();
+    assert_eq!(v.get_authkey().unwrap().key(), key.key());
+}
+
+#[tokio::test]
+async fn test_authenticate_wrong_password() {
+    let mock = hypervisor().await;
+
+    let res = Velocity::new(&mock.url(), "alice", "wrong").await;
+    assert_api_error(res, VelocityErrorKind::PermissionDenied, 403);
+}
+
+#[tokio::test]
+async fn test_authenticate_malformed() {
+    let mock = hypervisor().await;
+
+    // The mock rejects an empty body like the hypervisor rejects malformed requests
+    let v = unauthenticated(&mock);
+    let res = v.request::<()>(reqwest::Method::POST, "/u/auth", &()).await;
+    assert_bad_request(res);
+}
+
+#[tokio::test]
+async fn test_reauthenticate() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let old = v.get_authkey().unwrap();
+    let new = v.reauthenticate().await.unwrap();
+    assert_ne!(old.key(), new.key());
+    assert_eq!(v.get_authkey().unwrap().key(), new.key());
+
+    // The renewed key keeps working, the old one has been invalidated
+    v.user_info(None).await.unwrap();
+    assert!(mock.state().authenticate(old.key()).is_err());
+}
+
+#[tokio::test]
+async fn test_reauthenticate_expired() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    mock.state().authkeys.clear();
+
+    let res = v.reauthenticate().await;
+    assert_api_error(res, VelocityErrorKind::InvalidAuthkey, 401);
+}
+
+#[tokio::test]
+async fn test_deauthenticate() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    v.deauthenticate().await.unwrap();
+    assert_not_authenticated(v.get_authkey());
+    assert!(mock.state().authkeys.is_empty());
+}
+
+#[tokio::test]
+async fn test_unauthenticated() {
+    let mock = hypervisor().await;
+    let v = unauthenticated(&mock);
+
+    assert_not_authenticated(v.reauthenticate().await);
+    assert_not_authenticated(v.deauthenticate().await);
+    assert_not_authenticated(v.user_create("bob", "bob").await);
+    assert_not_authenticated(v.user_remove(UID(1)).await);
+    assert_not_authenticated(v.user_info(None).await);
+    assert_not_authenticated(v.user_list().await);
+    assert_not_authenticated(
+        v.user_add_permission(GID(1), UID(1), "velocity.user.list")
+            .await,
+    );
+    assert_not_authenticated(
+        v.user_revoke_permission(GID(1), UID(1), "velocity.user.list")
+            .await,
+    );
+    assert_not_authenticated(v.group_info(GID(0)).await);
+    assert_not_authenticated(v.group_create(GID(0), "new").await);
+    assert_not_authenticated(v.group_remove(GID(1)).await);
+    assert_not_authenticated(v.group_list().await);
+}
+
+#[tokio::test]
+async fn test_user_info() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let info = v.user_info(None).await.unwrap();
+    assert_eq!(info.uid, UID(1));
+    assert_eq!(info.name, "alice");
+    assert_eq!(info.memberships.len(), 1);
+    assert_eq!(info.memberships[0].gid, GID(1));
+    assert_eq!(info.memberships[0].permissions.len(), 4);
+
+    // Alice may look at herself, but not at others
+    v.user_info(Some(UID(1))).await.unwrap();
+    let res = v.user_info(Some(UID(0))).await;
+    assert_api_error(res, VelocityErrorKind::PermissionDenied, 403);
+
+    let root = login(&mock, "root").await;
+    assert_eq!(root.user_info(Some(UID(1))).await.unwrap().name, "alice");
+    let res = root.user_info(Some(UID(42))).await;
+    assert_api_error(res, VelocityErrorKind::NotFound, 404);
+}
+
+#[tokio::test]
+async fn test_user_create_remove() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let uid = v.user_create("bob", "bob").await.unwrap();
+    assert!(v.user_list().await.unwrap().iter().any(|u| u.uid == uid));
+    login(&mock, "bob").await;
+
+    let res = v.user_create("bob", "other").await;
+    assert_api_error(res, VelocityErrorKind::Conflict, 409);
+
+    let res = v.user_create("", "empty").await;
+    assert_bad_request(res);
+
+    v.user_remove(uid).await.unwrap();
+    assert!(!v.user_list().await.unwrap().iter().any(|u| u.uid == uid));
+
+    let res = v.user_remove(uid).await;
+    assert_api_error(res, VelocityErrorKind::NotFound, 404);
+}
+
+#[tokio::test]
+async fn test_user_create_forbidden() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    assert_api_error(
+        v.user_create("bob", "bob").await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+    assert_api_error(
+        v.user_remove(UID(0)).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+    assert_api_error(
+        v.user_list().await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
+
+#[tokio::test]
+async fn test_user_list() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let users = v.user_list().await.unwrap();
+    let names: Vec<&str> = users.iter().map(|u| u.name.as_str()).collect();
+    assert_eq!(names, vec!["root", "alice"]);
+}
+
+#[tokio::test]
+async fn test_user_permissions() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    v.user_add_permission(GID(2), UID(1), "velocity.group.view")
+        .await
+        .unwrap();
+
+    let alice = login(&mock, "alice").await;
+    alice.group_info(GID(2)).await.unwrap();
+
+    v.user_revoke_permission(GID(2), UID(1), "velocity.group.view")
+        .await
+        .unwrap();
+    assert_api_error(
+        alice.group_info(GID(2)).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+
+    assert_api_error(
+        v.user_revoke_permission(GID(2), UID(1), "velocity.group.view")
+            .await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+    assert_api_error(
+        v.user_add_permission(GID(2), UID(1), "velocity.does.not.exist")
+            .await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+    assert_api_error(
+        v.user_add_permission(GID(42), UID(1), "velocity.group.view")
+            .await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+
+    // Alice can't assign permissions at all
+    assert_api_error(
+        alice
+            .user_add_permission(GID(1), UID(1), "velocity.group.view")
+            .await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
+
+#[tokio::test]
+async fn test_group_info() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let info = v.group_info(GID(1)).await.unwrap();
+    assert_eq!(info.name, "team");
+    assert_eq!(info.parent_gid, GID(0));
+    assert_eq!(info.memberships.len(), 1);
+    assert_eq!(info.memberships[0].name, "alice");
+
+    assert_api_error(
+        v.group_info(GID(42)).await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+}
+
+#[tokio::test]
+async fn test_group_create_remove() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "root").await;
+
+    let group = v.group_create(GID(1), "staging").await.unwrap();
+    assert_eq!(group.parent_gid, GID(1));
+    assert_eq!(group.name, "staging");
+
+    assert_api_error(
+        v.group_create(GID(1), "staging").await,
+        VelocityErrorKind::Conflict,
+        409,
+    );
+    assert_bad_request(v.group_create(GID(1), "").await);
+    assert_api_error(
+        v.group_create(GID(42), "orphan").await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+
+    // Removing the parent removes the subgroups as well
+    v.group_remove(GID(1)).await.unwrap();
+    let groups = v.group_list().await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].gid, GID(0));
+
+    assert_api_error(
+        v.group_remove(group.gid).await,
+        VelocityErrorKind::NotFound,
+        404,
+    );
+    assert_api_error(
+        v.group_remove(GID(0)).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
+
+#[tokio::test]
+async fn test_group_list() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    // Alice sees her group and its subgroup, which inherits her permissions
+    let groups = v.group_list().await.unwrap();
+    let gids: Vec<GID> = groups.iter().map(|g| g.gid).collect();
+    assert_eq!(gids, vec![GID(1), GID(2)]);
+    assert_eq!(groups[1].permissions.len(), 4);
+
+    assert_api_error(
+        v.group_create(GID(1), "new").await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
@@ -0,0 +1,212 @@
+//! Tests for the `/v` endpoints
+#![cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use common::*;
+use velocity::{
+    endpoints::v::{
+        v_vm_efi::EFIVMConfig, v_vm_validate::ConfigProblem, CoreVM, DiskConfig, DiskMode,
+        DisplayConfig, NICConfig, NICType,
+    },
+    error::VelocityErrorKind,
+    GID, NICID,
+};
+
+/// A minimal, valid virtual machine configuration
+fn config(name: &str, gid: GID) -> EFIVMConfig {
+    EFIVMConfig {
+        core: CoreVM {
+            name: name.to_owned(),
+            gid,
+            cpus: 2,
+            memory_mib: 2048,
+            displays: vec![DisplayConfig {
+                name: "main".to_owned(),
+                width: 1920,
+                height: 1080,
+                ppi: 72,
+            }],
+            disks: vec![DiskConfig {
+                mid: DEBIAN_MID.to_owned(),
+                mode: DiskMode::USB,
+                readonly: true,
+            }],
+            nics: vec![NICConfig {
+                ty: NICType::NAT,
+                host: None,
+            }],
+            autostart: false,
+        },
+        rosetta: false,
+    }
+}
+
+#[test]
+fn test_vm_config_roundtrip() {
+    let cfg = config("vm", GID(1));
+    let json = serde_json::to_value(&cfg).unwrap();
+
+    // The core configuration is flattened into the top level
+    assert_eq!(json["name"], "vm");
+    assert_eq!(json["nics"][0]["type"], "NAT");
+    assert_eq!(serde_json::from_value::<EFIVMConfig>(json).unwrap(), cfg);
+
+    let mut template = cfg.clone();
+    template.core.disks[0].mode = DiskMode::VIRTIO;
+    assert_ne!(template, cfg);
+}
+
+#[tokio::test]
+async fn test_unauthenticated() {
+    let mock = hypervisor().await;
+    let v = unauthenticated(&mock);
+
+    assert_not_authenticated(v.nic_list().await);
+    assert_not_authenticated(v.vm_efi_create(config("vm", GID(1))).await);
+}
+
+#[tokio::test]
+async fn test_nic_list() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let nics = v.nic_list().await.unwrap();
+    assert_eq!(nics.len(), 1);
+    assert_eq!(nics[0].nicid, NICID(0));
+    assert_eq!(nics[0].identifier, "en0");
+
+    mock.state().authkeys.clear();
+    assert_api_error(v.nic_list().await, VelocityErrorKind::InvalidAuthkey, 401);
+}
+
+#[tokio::test]
+async fn test_vm_efi_create() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let vmid = v.vm_efi_create(config("vm", GID(1))).await.unwrap();
+    assert_eq!(mock.state().vms[&vmid].name, "vm");
+
+    // Subgroups can use their parent's media
+    let mut cfg = config("bridged", GID(2));
+    cfg.core.nics.push(NICConfig {
+        ty: NICType::BRIDGE,
+        host: Some(NICID(0)),
+    });
+    v.vm_efi_create(cfg).await.unwrap();
+
+    assert_api_error(
+        v.vm_efi_create(config("vm", GID(1))).await,
+        VelocityErrorKind::Conflict,
+        409,
+    );
+    assert_api_error(
+        v.vm_efi_create(config("vm", GID(0))).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
+
+#[tokio::test]
+async fn test_vm_efi_create_invalid() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    let mut cfg = config("no-cpus", GID(1));
+    cfg.core.cpus = 0;
+    assert_bad_request(v.vm_efi_create(cfg).await);
+
+    let mut cfg = config("no-host", GID(1));
+    cfg.core.nics.push(NICConfig {
+        ty: NICType::BRIDGE,
+        host: None,
+    });
+    assert_bad_request(v.vm_efi_create(cfg).await);
+
+    let mut cfg = config("unknown-host", GID(1));
+    cfg.core.nics.push(NICConfig {
+        ty: NICType::BRIDGE,
+        host: Some(NICID(42)),
+    });
+    assert_api_error(v.vm_efi_create(cfg).await, VelocityErrorKind::NotFound, 404);
+
+    let mut cfg = config("writable-iso", GID(1));
+    cfg.core.disks[0].readonly = false;
+    assert_bad_request(v.vm_efi_create(cfg).await);
+
+    let mut cfg = config("unknown-disk", GID(1));
+    cfg.core.disks[0].mid = "unknown".to_owned();
+    assert_api_error(v.vm_efi_create(cfg).await, VelocityErrorKind::NotFound, 404);
+}
+
+/// Returns the fields of all problems
+fn fields(problems: &[ConfigProblem]) -> Vec<&str> {
+    problems.iter().map(|p| p.field.as_str()).collect()
+}
+
+#[test]
+fn test_vm_validate_offline() {
+    assert_eq!(config("vm", GID(1)).core.validate(), vec![]);
+
+    let mut cfg = config("", GID(1));
+    cfg.core.cpus = 0;
+    cfg.core.displays[0].ppi = 0;
+    cfg.core.nics.push(NICConfig {
+        ty: NICType::BRIDGE,
+        host: None,
+    });
+
+    // Every problem is reported at once
+    let problems = cfg.core.validate();
+    assert_eq!(
+        fields(&problems),
+        vec!["name", "cpus", "displays[0].ppi", "nics[1].host"]
+    );
+    assert_eq!(
+        problems[3].to_string(),
+        "nics[1].host: A BRIDGE NIC requires a host NIC"
+    );
+}
+
+#[tokio::test]
+async fn test_vm_validate() {
+    let mock = hypervisor().await;
+    let v = login(&mock, "alice").await;
+
+    // Subgroups can use their parent's media
+    assert_eq!(
+        v.vm_validate(&config("vm", GID(2)).core).await.unwrap(),
+        vec![]
+    );
+
+    let mut cfg = config("vm", GID(1));
+    cfg.core.memory_mib = 0;
+    cfg.core.disks[0].readonly = false;
+    cfg.core.disks.push(DiskConfig {
+        mid: "unknown".to_owned(),
+        mode: DiskMode::VIRTIO,
+        readonly: false,
+    });
+    cfg.core.nics.push(NICConfig {
+        ty: NICType::BRIDGE,
+        host: Some(NICID(42)),
+    });
+
+    let problems = v.vm_validate(&cfg.core).await.unwrap();
+    assert_eq!(
+        fields(&problems),
+        vec![
+            "memory_mib",
+            "nics[1].host",
+            "disks[0].readonly",
+            "disks[1].mid"
+        ]
+    );
+
+    // Groups the user can't list media of are an error
+    assert_api_error(
+        v.vm_validate(&config("vm", GID(0)).core).await,
+        VelocityErrorKind::PermissionDenied,
+        403,
+    );
+}
@@ -0,0 +1,111 @@
+//! Browser tests for uploading `File`s and `Blob`s. They need a running mock hypervisor,
+//! see `examples/mock_hypervisor.rs`, whose url is passed in `VELOCITY_MOCK_URL` at build time.
+//! `tests/web.sh` starts one and runs the tests against it
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use std::sync::{Arc, Mutex};
+
+use js_sys::{Array, Uint8Array};
+use velocity::{
+    error::{ClientError, VelocityError, VelocityErrorKind},
+    transfer::{CancellationToken, TransferOptions, TransferPhase, TransferProgress},
+    Velocity, GID, MPID,
+};
+use wasm_bindgen_test::*;
+use web_sys::Blob;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// Logs in to the mock hypervisor as `alice`
+async fn login() -> Velocity {
+    // Checked at runtime, so the tests still build without a mock hypervisor
+    let Some(url) = option_env!("VELOCITY_MOCK_URL") else {
+        panic!("VELOCITY_MOCK_URL is not set, run the tests through tests/web.sh");
+    };
+
+    Velocity::new(url, "alice", "alice").await.expect("Log in")
+}
+
+/// Creates a blob of `len` bytes
+fn blob(len: usize) -> Blob {
+    let data = Uint8Array::from(&vec![0x42u8; len][..]);
+    Blob::new_with_u8_array_sequence(&Array::of1(&data)).expect("Create blob")
+}
+
+#[wasm_bindgen_test]
+async fn test_media_upload_blob() {
+    let v = login().await;
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let u = updates.clone();
+    let res = v
+        .media_upload_blob(
+            MPID(0),
+            GID(1),
+            "blob",
+            "DISK",
+            true,
+            &blob(512),
+            TransferOptions::default(),
+            move |progress: &TransferProgress| u.lock().unwrap().push(progress.clone()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.size, 512);
+
+    let last = updates.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(last.phase, TransferPhase::Processing);
+    assert_eq!(last.done, 512);
+    assert_eq!(last.total, Some(512));
+
+    let media = v.media_list(GID(1)).await.unwrap();
+    let uploaded = media.iter().find(|m| m.mid == res.mid).unwrap();
+    assert_eq!(uploaded.name, "blob");
+    assert_eq!(uploaded.sha256, res.sha256);
+}
+
+#[wasm_bindgen_test]
+async fn test_media_upload_blob_errors() {
+    let v = login().await;
+
+    match v
+        .media_upload_blob(
+            MPID(0),
+            GID(2),
+            "blob",
+            "DISK",
+            true,
+            &blob(1),
+            TransferOptions::default(),
+            (),
+        )
+        .await
+    {
+        Err(VelocityError::APIError(e)) => {
+            assert_eq!(e.status, 403);
+            assert_eq!(e.kind(), VelocityErrorKind::PermissionDenied);
+        }
+        res => panic!("Expected a permission error, got {:?}", res),
+    }
+
+    let token = CancellationToken::new();
+    token.cancel();
+    match v
+        .media_upload_blob(
+            MPID(0),
+            GID(1),
+            "blob",
+            "DISK",
+            true,
+            &blob(1),
+            TransferOptions::default().cancel_token(token),
+            (),
+        )
+        .await
+    {
+        Err(VelocityError::Client(ClientError::Cancelled { transferred })) => {
+            assert_eq!(transferred, 0)
+        }
+        res => panic!("Expected a cancellation, got {:?}", res),
+    }
+}
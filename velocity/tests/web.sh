@@ -0,0 +1,25 @@
+#!/bin/sh
+# Runs the browser tests in tests/web.rs against the mock hypervisor from
+# examples/mock_hypervisor.rs. Needs wasm-pack and the browser to test in:
+#   tests/web.sh [--firefox | --chrome | --safari]
+set -eu
+
+cd "$(dirname "$0")/.."
+browser="${1:---firefox}"
+
+log="$(mktemp)"
+cargo run --quiet --example mock_hypervisor --features mock >"$log" &
+mock=$!
+trap 'kill "$mock" 2>/dev/null; rm -f "$log"' EXIT
+
+# The mock hypervisor prints its url once it is listening
+while [ ! -s "$log" ]; do
+    if ! kill -0 "$mock" 2>/dev/null; then
+        echo "The mock hypervisor failed to start" >&2
+        exit 1
+    fi
+    sleep 1
+done
+
+VELOCITY_MOCK_URL="$(head -n 1 "$log")" \
+    wasm-pack test --headless "$browser" -- --no-default-features --features wasm